@@ -1,6 +1,7 @@
 extern crate texture;
 extern crate texture_gen;
 
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::path::Path;
@@ -9,11 +10,30 @@ use std::io::Write;
 
 fn generate_code_fragment<P: AsRef<Path>>(path: P) -> String {
     let model = texture::load_file(path).unwrap();
-    let fragment = texture_gen::to_rust_code(&model.image);
+    let fragment = texture_gen::to_rust_code(model.image.as_rgba8().unwrap());
 
     fragment
 }
 
+fn generate_mip_chain_code_fragment<P: AsRef<Path>>(path: P) -> String {
+    let model = texture::load_file(path).unwrap();
+    let chain = model.image.as_rgba8().unwrap().generate_mipmaps(texture::Filter::Box);
+
+    texture_gen::mip_chain_to_rust_code(&chain)
+}
+
+/// Pack the mipmap levels of an image into a texture atlas, one sprite per level.
+fn sample_atlas<P: AsRef<Path>>(path: P) -> texture::TextureAtlas {
+    let model = texture::load_file(path).unwrap();
+    let chain = model.image.as_rgba8().unwrap().generate_mipmaps(texture::Filter::Box);
+    let sprites: Vec<(String, &texture::TexImage2D)> = chain.iter().enumerate().map(|(i, level)| {
+        (format!("level{}", i), level)
+    }).collect();
+    let options = texture::AtlasOptions::new().padding(1).extrusion(1);
+
+    texture::TextureAtlas::pack(sprites, &options).unwrap()
+}
+
 fn write_code_fragment(fragment: &str, fragment_name: &str) -> io::Result<()> {
     let path = Path::new("tests").join(fragment_name);
     let mut file = File::create(&path)?;
@@ -23,5 +43,75 @@ fn write_code_fragment(fragment: &str, fragment_name: &str) -> io::Result<()> {
 
 fn main() -> io::Result<()> {
     let fragment = generate_code_fragment("assets/sample.png");
-    write_code_fragment(&fragment, "sample_png_test.in")
+    write_code_fragment(&fragment, "sample_png_test.in")?;
+
+    let model = texture::load_file("assets/sample.png").unwrap();
+    let image = model.image.as_rgba8().unwrap();
+    let fragment = texture_gen::to_rust_code_with_encoding(image, texture_gen::DataEncoding::ByteArray);
+    write_code_fragment(&fragment, "sample_png_byte_array_test.in")?;
+
+    let wide_image: texture::TexImage2D<texture::Rgba16> = image.convert();
+    let fragment = texture_gen::to_rust_code_with_encoding(&wide_image, texture_gen::DataEncoding::ByteArray);
+    write_code_fragment(&fragment, "sample_png_byte_array_rgba16_test.in")?;
+
+    let fragment = texture_gen::to_rust_code_with_encoding(image, texture_gen::DataEncoding::IncludeBytes("sample_png_test.raw"));
+    write_code_fragment(&fragment, "sample_png_include_bytes_test.in")?;
+    fs::write(Path::new("tests").join("sample_png_test.raw"), texture_gen::to_raw_bytes(image))?;
+
+    let fragment = texture_gen::to_static_rust_code(image, "SAMPLE_PNG");
+    write_code_fragment(&fragment, "sample_png_static_test.in")?;
+
+    let options = texture_gen::CodegenOptions::new()
+        .crate_path("::teximage2d")
+        .item_name("sample_png")
+        .visibility(texture_gen::Visibility::Crate)
+        .rustfmt_skip(true)
+        .allow("clippy::all")
+        .doc_comment(true)
+        .source_path("assets/sample.png");
+    write_code_fragment(&texture_gen::to_rust_code_with(image, &options), "sample_png_codegen_options_test.in")?;
+
+    let fragment = generate_mip_chain_code_fragment("assets/sample.png");
+    write_code_fragment(&fragment, "sample_png_mip_chain_test.in")?;
+
+    let chain = image.generate_mipmaps(texture::Filter::Box);
+    let compressed = texture::CompressedTexImage2D::from_mip_chain(
+        &chain, texture::CompressedFormat::Bc7, texture::Quality::Fast
+    ).unwrap();
+    write_code_fragment(&texture_gen::compressed_to_rust_code(&compressed), "sample_png_compressed_test.in")?;
+
+    let atlas = sample_atlas("assets/sample.png");
+    write_code_fragment(&texture_gen::to_rust_code(&atlas.image), "sample_atlas_test.in")?;
+    write_code_fragment(&texture_gen::atlas_regions_to_rust_code(&atlas), "sample_atlas_regions_test.in")?;
+
+    let options = texture::LoadOptions::new();
+    let cube: texture::TexImageCube = texture::load_cube_faces(&["assets/sample.png"; 6], &options).unwrap();
+    write_code_fragment(&texture_gen::cube_to_rust_code(&cube), "sample_cube_test.in")?;
+
+    let array: texture::TexImage2DArray = texture::load_array_grid("assets/sample.png", 2, 2, &options).unwrap();
+    write_code_fragment(&texture_gen::array_to_rust_code(&array), "sample_array_test.in")?;
+
+    texture_gen::Builder::new()
+        .glob("assets/*.png")
+        .build()
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    texture_gen::Builder::new()
+        .glob("assets/*.png")
+        .module_name("lazy_textures")
+        .embedding(texture_gen::Embedding::Lazy)
+        .build()
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    texture_gen::Builder::new()
+        .glob("assets/*.png")
+        .module_name("compressed_textures")
+        .embedding(texture_gen::Embedding::Compressed {
+            format: texture::CompressedFormat::Etc2Rgb,
+            quality: texture::Quality::Fast,
+        })
+        .build()
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    Ok(())
 }
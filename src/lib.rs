@@ -1,5 +1,7 @@
 extern crate texture;
 extern crate texture_gen;
+extern crate texture_macros;
 
 pub use texture::*;
 pub use texture_gen::*;
+pub use texture_macros::include_texture;
@@ -1,8 +1,28 @@
-use teximage2d;
-use teximage2d::{TexImage2D, Rgba};
+use teximage2d::{
+    AtlasOptions, AtlasRegion, CompressedFormat, CompressedTexImage2D, CompressedTexImage2DRef, Filter, LoadOptions,
+    MipChain, Quality, TexImage2D, TexImage2DArray, TexImageCube, TextureAtlas, Rgba, Rgba16,
+};
 
 const SAMPLE_DATA: &str = "assets/sample.png";
 
+include!("sample_png_static_test.in");
+
+include!("sample_png_codegen_options_test.in");
+
+mod textures {
+    include!(concat!(env!("OUT_DIR"), "/textures.rs"));
+}
+
+mod lazy_textures {
+    include!(concat!(env!("OUT_DIR"), "/lazy_textures.rs"));
+}
+
+mod compressed_textures {
+    include!(concat!(env!("OUT_DIR"), "/compressed_textures.rs"));
+}
+
+const SAMPLE_ATLAS_REGIONS: &[(&str, AtlasRegion)] = include!("sample_atlas_regions_test.in");
+
 
 /// The integration test data expected from the sample image.
 /// NOTE: 
@@ -47,8 +67,144 @@ fn test_teximage2d_code_generator() {
 
 #[test]
 fn test_tex_image_2d_code_generator_end_to_end() {
-    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image;
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let result = include!("sample_png_test.in");
 
     assert_eq!(result, expected);
 }
+
+
+#[test]
+fn test_byte_array_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let result = include!("sample_png_byte_array_test.in");
+
+    assert_eq!(result, expected.as_view());
+    assert_eq!(result.to_tex_image(), expected);
+}
+
+
+#[test]
+fn test_byte_array_code_generator_borrows_wide_channels_in_place() {
+    let expected: TexImage2D<Rgba16> = teximage2d::load_file(SAMPLE_DATA).unwrap().image.convert();
+    let result = include!("sample_png_byte_array_rgba16_test.in");
+
+    assert_eq!(result, expected.as_view());
+}
+
+
+#[test]
+fn test_include_bytes_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let result = include!("sample_png_include_bytes_test.in");
+
+    assert_eq!(result, expected.as_view());
+    assert_eq!(result.to_tex_image(), expected);
+}
+
+
+#[test]
+fn test_static_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+
+    assert_eq!(SAMPLE_PNG.to_tex_image(), expected);
+    assert_eq!(SAMPLE_PNG, expected.as_view());
+}
+
+
+#[test]
+fn test_mip_chain_code_generator_end_to_end() {
+    let image = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let expected = image.generate_mipmaps(Filter::Box);
+    let result: MipChain = include!("sample_png_mip_chain_test.in");
+
+    assert_eq!(result, expected);
+    assert_eq!(result.level_count(), 5);
+}
+
+
+#[test]
+fn test_compressed_code_generator_end_to_end() {
+    let image = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let chain = image.generate_mipmaps(Filter::Box);
+    let expected = CompressedTexImage2D::from_mip_chain(&chain, CompressedFormat::Bc7, Quality::Fast).unwrap();
+    let result: CompressedTexImage2DRef = include!("sample_png_compressed_test.in");
+
+    assert_eq!(result.to_compressed_tex_image(), expected);
+    assert_eq!(result.level_count(), 5);
+    assert_eq!(result.decompress(), expected.decompress());
+}
+
+
+#[test]
+fn test_atlas_code_generator_end_to_end() {
+    let image = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let chain = image.generate_mipmaps(Filter::Box);
+    let sprites: Vec<(String, &TexImage2D)> = chain.iter().enumerate().map(|(i, level)| {
+        (format!("level{}", i), level)
+    }).collect();
+    let expected = TextureAtlas::pack(sprites, &AtlasOptions::new().padding(1).extrusion(1)).unwrap();
+    let result: TexImage2D = include!("sample_atlas_test.in");
+
+    assert_eq!(result, expected.image);
+    assert_eq!(SAMPLE_ATLAS_REGIONS.len(), expected.regions.len());
+    for (name, region) in expected.regions.iter() {
+        assert_eq!(teximage2d::find_atlas_region(SAMPLE_ATLAS_REGIONS, name), Some(region));
+    }
+}
+
+
+#[test]
+fn test_cube_code_generator_end_to_end() {
+    let expected: TexImageCube = teximage2d::load_cube_faces(&[SAMPLE_DATA; 6], &LoadOptions::new()).unwrap();
+    let result: TexImageCube = include!("sample_cube_test.in");
+
+    assert_eq!(result, expected);
+}
+
+
+#[test]
+fn test_array_code_generator_end_to_end() {
+    let expected: TexImage2DArray = teximage2d::load_array_grid(SAMPLE_DATA, 2, 2, &LoadOptions::new()).unwrap();
+    let result: TexImage2DArray = include!("sample_array_test.in");
+
+    assert_eq!(result, expected);
+    assert_eq!(result.layer_count(), 4);
+}
+
+
+#[test]
+fn test_builder_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap();
+    let result = textures::sample();
+
+    assert_eq!(result, expected.image.as_rgba8().unwrap().as_view());
+}
+
+
+#[test]
+fn test_lazy_builder_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap();
+    let result = lazy_textures::SAMPLE.get();
+
+    assert_eq!(result, expected.image.as_rgba8().unwrap());
+}
+
+
+#[test]
+fn test_compressed_builder_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let result = compressed_textures::sample();
+
+    assert_eq!(result.format(), CompressedFormat::Etc2Rgb);
+    assert_eq!(result.level(0), Some(&expected.compress(CompressedFormat::Etc2Rgb, Quality::Fast)[..]));
+}
+
+
+#[test]
+fn test_codegen_options_code_generator_end_to_end() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap();
+    let result = sample_png();
+
+    assert_eq!(result, expected.image.as_rgba8().unwrap().clone());
+}
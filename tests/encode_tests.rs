@@ -0,0 +1,128 @@
+use teximage2d::{ImageFormat, La8, R8, Rg8, Rgb8, Rgba, TexImage2D};
+
+
+const SAMPLE_DATA: &str = "assets/sample.png";
+
+fn sample_image() -> TexImage2D {
+    teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap()
+}
+
+fn round_trip(image: &TexImage2D, format: ImageFormat) -> TexImage2D {
+    let mut buffer = vec![];
+    image.write_to(&mut buffer, format).unwrap();
+
+    teximage2d::load_from_memory(&buffer).unwrap().image.into_rgba8().unwrap()
+}
+
+/// Saving a loaded PNG image and loading it again yields the same texture image.
+#[test]
+fn test_png_round_trip_is_lossless() {
+    let expected = sample_image();
+    let result = round_trip(&expected, ImageFormat::Png);
+
+    assert_eq!(result, expected);
+}
+
+/// Saving a loaded PNG image as a TGA image and loading it again yields the same
+/// texture image.
+#[test]
+fn test_tga_round_trip_is_lossless() {
+    let expected = sample_image();
+    let result = round_trip(&expected, ImageFormat::Tga);
+
+    assert_eq!(result, expected);
+}
+
+/// Saving a loaded PNG image as a BMP image and loading it again yields the same
+/// texture image.
+#[test]
+fn test_bmp_round_trip_is_lossless() {
+    let expected = sample_image();
+    let result = round_trip(&expected, ImageFormat::Bmp);
+
+    assert_eq!(result, expected);
+}
+
+/// Encoding preserves the bottom up row order of the texture image for images
+/// that are not symmetric.
+#[test]
+fn test_round_trip_preserves_row_order() {
+    let data = (0..12).map(|i| Rgb8::new(i * 20, 255 - i * 20, i)).collect();
+    let image = TexImage2D::from_data(3, 4, data).convert();
+    for format in [ImageFormat::Png, ImageFormat::Tga, ImageFormat::Bmp].iter() {
+        let result = round_trip(&image, *format);
+
+        assert_eq!(result, image, "format: {:?}", format);
+    }
+}
+
+/// Single channel images are encoded as grayscale images.
+#[test]
+fn test_single_channel_images_are_encoded_as_grayscale() {
+    let data = (0..6).map(|i| R8::new(i * 40)).collect();
+    let image: TexImage2D<R8> = TexImage2D::from_data(2, 3, data);
+    for format in [ImageFormat::Png, ImageFormat::Tga, ImageFormat::Bmp].iter() {
+        let result = round_trip(&image.convert(), *format);
+        let expected: Vec<u8> = image.data.iter().map(|pixel| pixel.r).collect();
+
+        assert_eq!(result.data.iter().map(|pixel| pixel.r).collect::<Vec<u8>>(), expected);
+        assert_eq!(result.data.iter().map(|pixel| pixel.g).collect::<Vec<u8>>(), vec![0; 6]);
+
+        let mut buffer = vec![];
+        image.write_to(&mut buffer, *format).unwrap();
+        let result = teximage2d::load_from_memory(&buffer).unwrap().image.into_rgba8().unwrap();
+        let gray: Vec<(u8, u8, u8)> = result.data.iter().map(|pixel| (pixel.r, pixel.g, pixel.b)).collect();
+        let expected: Vec<(u8, u8, u8)> = image.data.iter().map(|pixel| (pixel.r, pixel.r, pixel.r)).collect();
+
+        assert_eq!(gray, expected, "format: {:?}", format);
+    }
+}
+
+/// Images without pixels cannot be encoded.
+#[test]
+fn test_empty_image_cannot_be_encoded() {
+    let image: TexImage2D = TexImage2D::new(0, 0);
+    let mut buffer = vec![];
+
+    assert!(image.write_to(&mut buffer, ImageFormat::Png).is_err());
+}
+
+#[test]
+fn test_image_format_from_path() {
+    assert_eq!(ImageFormat::from_path("textures/albedo.PNG"), Some(ImageFormat::Png));
+    assert_eq!(ImageFormat::from_path("textures/albedo.tga"), Some(ImageFormat::Tga));
+    assert_eq!(ImageFormat::from_path("textures/albedo.bmp"), Some(ImageFormat::Bmp));
+    assert_eq!(ImageFormat::from_path("textures/albedo"), None);
+}
+
+/// Two channel images are encoded as grayscale with alpha in PNG files, so
+/// both channels survive the round trip.
+#[test]
+fn test_two_channel_images_are_encoded_as_grayscale_alpha() {
+    let data = (0..6).map(|i| Rg8::new(i * 40, 255 - i * 30)).collect();
+    let image: TexImage2D<Rg8> = TexImage2D::from_data(2, 3, data);
+    let mut buffer = vec![];
+    image.write_to(&mut buffer, ImageFormat::Png).unwrap();
+    let result = teximage2d::load_from_memory(&buffer).unwrap().image.into_rgba8().unwrap();
+    let expected: Vec<(u8, u8)> = image.data.iter().map(|pixel| (pixel.r, pixel.g)).collect();
+
+    assert_eq!(result.data.iter().map(|pixel| (pixel.r, pixel.a)).collect::<Vec<(u8, u8)>>(), expected);
+}
+
+/// Two channel images keep their alpha channel in TGA and BMP files, which
+/// store them as gray colors with alpha.
+#[test]
+fn test_two_channel_images_keep_their_alpha_in_tga_and_bmp_files() {
+    let data = (0..6).map(|i| La8::new(i * 40, 255 - i * 30)).collect();
+    let image: TexImage2D<La8> = TexImage2D::from_data(2, 3, data);
+    for format in [ImageFormat::Tga, ImageFormat::Bmp].iter() {
+        let mut buffer = vec![];
+        image.write_to(&mut buffer, *format).unwrap();
+        let result = teximage2d::load_from_memory(&buffer).unwrap().image.into_rgba8().unwrap();
+        let expected: Vec<Rgba> = image.data.iter()
+            .map(|pixel| Rgba::new(pixel.l, pixel.l, pixel.l, pixel.a))
+            .collect();
+
+        assert_eq!(result.data, expected, "format: {:?}", format);
+    }
+}
@@ -1,5 +1,7 @@
-use teximage2d;
-use teximage2d::{Rgba, TexImage2D};
+use teximage2d::{
+    ColorSpace, CompressedFormat, CubeFace, CubeLayout, Filter, LoadOptions, Policy, Rgb8, Rgba, TexImage2D, TexImage2DArray, TexImage2DError,
+    TexImage2DWarning, TexImageCube, Quality,
+};
 
 
 const SAMPLE_DATA: &str = "assets/sample.png";
@@ -38,7 +40,7 @@ fn image() -> TexImage2D {
 /// The file loader yields the correct width.
 #[test]
 fn test_load_file_yields_correct_width() {
-    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image;
+    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let expected = image();
 
     assert_eq!(result.width, expected.width);
@@ -47,7 +49,7 @@ fn test_load_file_yields_correct_width() {
 /// The file loader yields the correct width.
 #[test]
 fn test_load_file_yields_correct_height() {
-    let result = texture::load_file(SAMPLE_DATA).unwrap().image;
+    let result = texture::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let expected = image();
 
     assert_eq!(result.height, expected.height);
@@ -57,7 +59,7 @@ fn test_load_file_yields_correct_height() {
 /// of pixels in the expected image.
 #[test]
 fn test_load_file_yields_correct_pixel_count() {
-    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image;
+    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let expected = image();
 
     assert_eq!(result.pixel_count(), expected.pixel_count());
@@ -68,7 +70,7 @@ fn test_load_file_yields_correct_pixel_count() {
 /// `number of pixels == width * height.`
 #[test]
 fn test_height_times_width_equals_pixel_count() {
-    let image = teximage2d::load_file(SAMPLE_DATA).unwrap().image;
+    let image = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let height = image.height as usize;
     let width = image.width as usize;
     let pixel_count = image.pixel_count();
@@ -79,7 +81,7 @@ fn test_height_times_width_equals_pixel_count() {
 /// The file loader yields the correct data block.
 #[test]
 fn test_load_file_yields_correct_data_block() {
-    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image;
+    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let expected = image();
 
     assert_eq!(result.data, expected.data);
@@ -88,8 +90,335 @@ fn test_load_file_yields_correct_data_block() {
 /// The file loader yields the expected texture image.
 #[test]
 fn test_load_file_yields_correct_texture_image() {
-    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image;
+    let result = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
     let expected = image();
 
     assert_eq!(result, expected);
 }
+
+/// The default load options yield the same texture image as the default loader.
+#[test]
+fn test_load_file_with_default_options_yields_correct_texture_image() {
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &LoadOptions::default()).unwrap().image.into_rgba8().unwrap();
+    let expected = image();
+
+    assert_eq!(result, expected);
+}
+
+/// Disabling the vertical flip yields the rows in top to bottom order.
+#[test]
+fn test_load_file_without_vertical_flip_yields_top_left_origin() {
+    let options = LoadOptions::new().flip_vertically(false);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image.into_rgba8().unwrap();
+    let expected = image();
+    let width = expected.width as usize;
+    let expected_data: Vec<Rgba> = expected.data.chunks(width).rev().flatten().cloned().collect();
+
+    assert_eq!(result.data, expected_data);
+}
+
+/// Flipping horizontally reverses the pixels in each row.
+#[test]
+fn test_load_file_with_horizontal_flip_reverses_rows() {
+    let options = LoadOptions::new().flip_horizontally(true);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image.into_rgba8().unwrap();
+    let expected = image();
+    let width = expected.width as usize;
+    let expected_data: Vec<Rgba> = expected.data.chunks(width).flat_map(|row| row.iter().rev()).cloned().collect();
+
+    assert_eq!(result.data, expected_data);
+}
+
+/// Requesting three channels yields an RGB texture image.
+#[test]
+fn test_load_file_with_three_channels_yields_rgb_texture_image() {
+    let options = LoadOptions::new().desired_channels(3);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image.into_rgb8().unwrap();
+    let expected: TexImage2D<Rgb8> = image().convert();
+
+    assert_eq!(result, expected);
+    assert_eq!(result.depth, 3);
+}
+
+/// Requesting one or two channels yields a grayscale texture image, which
+/// converts to RGBA with the gray value in every color channel.
+#[test]
+fn test_load_file_with_one_or_two_channels_round_trips_to_gray_rgba() {
+    let expected: Vec<Rgba> = image().data.iter().map(|pixel| {
+        let gray = ((pixel.r as u32 * 77 + pixel.g as u32 * 150 + pixel.b as u32 * 29) >> 8) as u8;
+        Rgba::new(gray, gray, gray, pixel.a)
+    }).collect();
+
+    let options = LoadOptions::new().desired_channels(1);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image;
+    assert!(result.as_l8().is_some());
+    assert_eq!(result.to_rgba8().data, expected);
+
+    let options = LoadOptions::new().desired_channels(2);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image;
+    assert!(result.as_la8().is_some());
+    assert_eq!(result.to_rgba8().data, expected);
+}
+
+/// Requesting the channel count stored in the file yields the stored format.
+#[test]
+fn test_load_file_with_file_channel_count() {
+    let options = LoadOptions::new().desired_channels(0);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image;
+
+    assert_eq!(result.to_rgba8(), image());
+}
+
+/// Premultiplying an opaque image leaves it unchanged.
+#[test]
+fn test_load_file_with_premultiplied_alpha_preserves_opaque_image() {
+    let options = LoadOptions::new().premultiply_alpha(true);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image.into_rgba8().unwrap();
+
+    assert_eq!(result, image());
+}
+
+/// The loader tags the result with the requested color space.
+#[test]
+fn test_load_file_with_color_space_tags_result() {
+    let options = LoadOptions::new().color_space(ColorSpace::Srgb);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap();
+
+    assert_eq!(result.color_space, ColorSpace::Srgb);
+}
+
+/// The sample image is a square power of two image without transparency.
+#[test]
+fn test_load_file_reports_opaque_alpha_channel() {
+    let result = teximage2d::load_file(SAMPLE_DATA).unwrap();
+
+    assert_eq!(result.warnings, vec![TexImage2DWarning::AlphaChannelIsFullyOpaque]);
+    assert!(result.has_warning(TexImage2DWarning::AlphaChannelIsFullyOpaque));
+}
+
+/// The maximum texture size is configurable.
+#[test]
+fn test_load_file_reports_textures_exceeding_max_texture_size() {
+    let options = LoadOptions::new().max_texture_size(8);
+    let result = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap();
+    let expected = TexImage2DWarning::TextureDimensionsExceedMaximumSize { max_texture_size: 8 };
+
+    assert!(result.has_warning(expected));
+}
+
+/// The sample image holds a red, green, blue and black quadrant, starting from
+/// the top left quadrant as the image is displayed.
+#[test]
+fn test_load_array_grid_splits_sample_into_quadrants() {
+    let array: TexImage2DArray = teximage2d::load_array_grid(SAMPLE_DATA, 2, 2, &LoadOptions::new()).unwrap();
+    let expected = [0xFF0000FF_u32, 0x00FF00FF, 0x0000FFFF, 0x000000FF];
+
+    assert_eq!(array.layer_count(), 4);
+    for (layer, color) in array.iter().zip(expected.iter()) {
+        assert_eq!((layer.width, layer.height), (8, 8));
+        assert_eq!(layer.data, vec![Rgba::from(*color); 64]);
+    }
+}
+
+/// The quadrants are found in the same place when the image is not flipped.
+#[test]
+fn test_load_array_grid_without_flipping() {
+    let options = LoadOptions::new().flip_vertically(false);
+    let array: TexImage2DArray = teximage2d::load_array_grid(SAMPLE_DATA, 2, 2, &options).unwrap();
+
+    assert_eq!(array.layers[0].data[0], Rgba::from(0xFF0000FF));
+    assert_eq!(array.layers[3].data[0], Rgba::from(0x000000FF));
+}
+
+#[test]
+fn test_load_array_layers_from_files() {
+    let array: TexImage2DArray<Rgb8> = teximage2d::load_array_layers(&[SAMPLE_DATA, SAMPLE_DATA], &LoadOptions::new()).unwrap();
+
+    assert_eq!(array.layer_count(), 2);
+    assert_eq!(array.layers[1], image().convert());
+}
+
+#[test]
+fn test_load_cube_faces_from_six_files() {
+    let paths = [SAMPLE_DATA; 6];
+    let cube: TexImageCube = teximage2d::load_cube_faces(&paths, &LoadOptions::new()).unwrap();
+
+    assert_eq!(cube.size(), 16);
+    assert_eq!(cube.face(CubeFace::NegativeZ), &image());
+}
+
+/// The sample image is square, so it is not a cube map layout.
+#[test]
+fn test_load_cube_layout_reports_invalid_layout_with_path() {
+    let result: Result<TexImageCube, _> = teximage2d::load_cube_layout(SAMPLE_DATA, CubeLayout::HorizontalCross, &LoadOptions::new());
+
+    match result {
+        Err(TexImage2DError::InvalidLayout { path, .. }) => {
+            assert_eq!(path.as_deref(), Some(std::path::Path::new(SAMPLE_DATA)));
+        }
+        other => panic!("Expected an invalid layout error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_resize_with_nearest_filter_round_trips() {
+    let image = image();
+    let result = image.resize(32, 32, Filter::Nearest).resize(16, 16, Filter::Nearest);
+
+    assert_eq!(result, image);
+}
+
+#[test]
+fn test_resize_to_power_of_two_clears_warning() {
+    let image = image().resize(12, 10, Filter::Triangle);
+    let result = image.resize_to_power_of_two(Policy::Up, Filter::Lanczos3);
+
+    assert!(image.check_warnings(4096).contains(&TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo));
+    assert_eq!((result.width, result.height), (16, 16));
+    assert!(!result.check_warnings(4096).contains(&TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo));
+}
+
+#[test]
+fn test_load_from_reader_yields_correct_texture_image() {
+    let file = std::fs::File::open(SAMPLE_DATA).unwrap();
+    let result = teximage2d::load_from_reader(file).unwrap();
+
+    assert_eq!(result.image.as_rgba8(), Some(&image()));
+}
+
+#[test]
+fn test_load_with_png_decoder_yields_correct_texture_image() {
+    let buffer = std::fs::read(SAMPLE_DATA).unwrap();
+    let result = teximage2d::load_with_decoder(&teximage2d::PngDecoder, &buffer, &LoadOptions::new()).unwrap();
+
+    assert_eq!(result.image.as_rgba8(), Some(&image()));
+}
+
+#[test]
+fn test_compress_sample_to_bc1_encodes_solid_blocks_exactly() {
+    let blocks = image().compress(CompressedFormat::Bc1, Quality::Normal);
+
+    assert_eq!(blocks.len(), CompressedFormat::Bc1.compressed_size(16, 16));
+    assert_eq!(&blocks[0..8], &[0x1F, 0x00, 0x1F, 0x00, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_compress_pads_partial_blocks() {
+    let image = image().resize(10, 6, Filter::Triangle);
+    let blocks = image.compress(CompressedFormat::Bc7, Quality::Best);
+
+    assert_eq!(blocks.len(), 3 * 2 * 16);
+    assert_eq!(blocks, image.compress(CompressedFormat::Bc7, Quality::Best));
+}
+
+/// The peak signal to noise ratio of the channels of a decompressed texture
+/// image, in decibels.
+fn psnr(image: &TexImage2D, result: &TexImage2D, channels: usize) -> f64 {
+    let squared_error: f64 = image.data.iter().zip(&result.data).map(|(pixel, decoded)| {
+        let pixel = [pixel.r, pixel.g, pixel.b, pixel.a];
+        let decoded = [decoded.r, decoded.g, decoded.b, decoded.a];
+        (0..channels).map(|channel| (pixel[channel] as f64 - decoded[channel] as f64).powi(2)).sum::<f64>()
+    }).sum();
+    let mean = squared_error / (image.pixel_count() * channels) as f64;
+
+    if mean == 0.0 { f64::INFINITY } else { 10.0 * (255.0 * 255.0 / mean).log10() }
+}
+
+/// The sample image scaled up smoothly, so its blocks hold gradients as well
+/// as solid colors, with alpha fading across it.
+fn smooth_image() -> TexImage2D {
+    let mut image = image().resize(37, 29, Filter::Bicubic);
+    for (i, pixel) in image.data.iter_mut().enumerate() {
+        pixel.a = (255 * (i % 37) / 36) as u8;
+    }
+
+    image
+}
+
+#[test]
+fn test_etc2_and_eac_round_trip_above_psnr_thresholds() {
+    // ETC2 colors vary mostly in brightness within a block, so the blocks
+    // where three of the saturated sample colors meet hold the RGB formats
+    // to a lower threshold.
+    let image = smooth_image();
+    for &(format, channels, threshold) in &[
+        (CompressedFormat::Etc2Rgb, 3, 24.0),
+        (CompressedFormat::Etc2Rgba8, 4, 25.0),
+        (CompressedFormat::EacR11, 1, 44.0),
+        (CompressedFormat::EacRg11, 2, 44.0),
+    ] {
+        for &quality in &[Quality::Normal, Quality::Best] {
+            let blocks = image.compress(format, quality);
+            let result = teximage2d::decompress(&blocks, format, image.width, image.height).unwrap();
+
+            assert!(psnr(&image, &result, channels) >= threshold, "{} {:?}", format, quality);
+        }
+    }
+}
+
+#[test]
+fn test_astc_round_trips_above_psnr_thresholds() {
+    // Every ASTC block size takes up sixteen bytes, so the threshold drops
+    // as the blocks grow.
+    let image = smooth_image();
+    for &(format, threshold) in &[
+        (CompressedFormat::Astc4x4, 30.0),
+        (CompressedFormat::Astc6x6, 30.0),
+        (CompressedFormat::Astc12x12, 20.0),
+    ] {
+        for &quality in &[Quality::Normal, Quality::Best] {
+            let blocks = image.compress(format, quality);
+            let result = teximage2d::decompress(&blocks, format, image.width, image.height).unwrap();
+
+            assert_eq!(blocks.len(), format.compressed_size(image.width, image.height));
+            assert!(psnr(&image, &result, 4) >= threshold, "{} {:?}", format, quality);
+        }
+    }
+}
+
+#[test]
+fn test_bc_round_trips_above_psnr_thresholds() {
+    // BC1 turns pixels that are less than half opaque into transparent black,
+    // so it is measured on an opaque image.
+    let image = smooth_image();
+    let mut opaque = image.clone();
+    for pixel in opaque.data.iter_mut() {
+        pixel.a = 255;
+    }
+    for &(format, channels, threshold) in &[
+        (CompressedFormat::Bc1, 4, 28.0),
+        (CompressedFormat::Bc3, 4, 28.0),
+        (CompressedFormat::Bc4, 1, 38.0),
+        (CompressedFormat::Bc5, 2, 38.0),
+        (CompressedFormat::Bc7, 4, 30.0),
+    ] {
+        let image = if format == CompressedFormat::Bc1 { &opaque } else { &image };
+        for &quality in &[Quality::Fast, Quality::Normal, Quality::Best] {
+            let blocks = image.compress(format, quality);
+            let result = teximage2d::decompress(&blocks, format, image.width, image.height).unwrap();
+
+            assert!(psnr(image, &result, channels) >= threshold, "{} {:?}", format, quality);
+        }
+    }
+}
+
+#[test]
+fn test_etc2_rgb_round_trips_solid_sample_closely() {
+    let image = image();
+    let blocks = image.compress(CompressedFormat::Etc2Rgb, Quality::Fast);
+    let result = teximage2d::decompress(&blocks, CompressedFormat::Etc2Rgb, 16, 16).unwrap();
+
+    assert!(psnr(&image, &result, 3) >= 40.0);
+}
+
+#[test]
+fn test_decompress_rejects_mismatched_block_data() {
+    let blocks = image().compress(CompressedFormat::EacR11, Quality::Fast);
+    let result = teximage2d::decompress(&blocks, CompressedFormat::EacR11, 16, 20);
+
+    match result {
+        Err(TexImage2DError::Decode { .. }) => {}
+        other => panic!("expected a decode error, got {:?}", other.map(|image| image.width)),
+    }
+}
+
@@ -0,0 +1,22 @@
+use teximage2d::{include_texture, LoadOptions, Rgb8, TexImage2D};
+
+const SAMPLE_DATA: &str = "assets/sample.png";
+
+
+#[test]
+fn test_include_texture_matches_loaded_texture() {
+    let expected = teximage2d::load_file(SAMPLE_DATA).unwrap().image.into_rgba8().unwrap();
+    let result = include_texture!("assets/sample.png");
+
+    assert_eq!(result, expected.as_view());
+}
+
+
+#[test]
+fn test_include_texture_with_options() {
+    let options = LoadOptions::new().flip_vertically(false);
+    let expected: TexImage2D<Rgb8> = teximage2d::load_file_with(SAMPLE_DATA, &options).unwrap().image.convert();
+    let result = include_texture!("assets/sample.png", flip = false, format = "rgb8");
+
+    assert_eq!(result, expected.as_view());
+}
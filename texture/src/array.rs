@@ -0,0 +1,242 @@
+use crate::{load_file_with, LoadOptions, Pixel, TexImage2D, TexImage2DError};
+use std::path::Path;
+
+
+/// An array of texture images with identical dimensions, such as the layers
+/// of a two dimensional array texture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TexImage2DArray<P = crate::Rgba> {
+    pub layers: Vec<TexImage2D<P>>,
+}
+
+impl<P: Pixel> TexImage2DArray<P> {
+    /// Construct a texture array from its layers. Every layer must have the
+    /// same dimensions, and there must be at least one layer.
+    pub fn from_layers(layers: Vec<TexImage2D<P>>) -> Result<TexImage2DArray<P>, TexImage2DError> {
+        if layers.is_empty() {
+            return Err(invalid_layout(String::from("a texture array needs at least one layer")));
+        }
+        for (index, layer) in layers.iter().enumerate() {
+            check_layer(index, layer, layers[0].width, layers[0].height).map_err(invalid_layout)?;
+        }
+
+        Ok(TexImage2DArray {
+            layers: layers,
+        })
+    }
+
+    /// Split a texture image into a grid of equally sized tiles, one layer per
+    /// tile. The layers are ordered row by row from the top left tile of the
+    /// image, assuming the image is stored bottom row first the way the
+    /// loaders store it by default.
+    pub fn from_grid(image: &TexImage2D<P>, columns: u32, rows: u32) -> Result<TexImage2DArray<P>, TexImage2DError> {
+        grid_layers(image, columns, rows, true, false).map_err(invalid_layout)
+    }
+
+    /// The width of every layer.
+    pub fn width(&self) -> u32 {
+        self.layers[0].width
+    }
+
+    /// The height of every layer.
+    pub fn height(&self) -> u32 {
+        self.layers[0].height
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn layer(&self, layer: usize) -> Option<&TexImage2D<P>> {
+        self.layers.get(layer)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, TexImage2D<P>> {
+        self.layers.iter()
+    }
+}
+
+impl<'a, P: Pixel> IntoIterator for &'a TexImage2DArray<P> {
+    type Item = &'a TexImage2D<P>;
+    type IntoIter = std::slice::Iter<'a, TexImage2D<P>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.layers.iter()
+    }
+}
+
+fn invalid_layout(reason: String) -> TexImage2DError {
+    TexImage2DError::InvalidLayout { path: None, reason: reason }
+}
+
+fn check_layer<P: Pixel>(index: usize, layer: &TexImage2D<P>, width: u32, height: u32) -> Result<(), String> {
+    if layer.width == 0 || layer.height == 0 {
+        return Err(format!("texture array layer {} has no pixels", index));
+    }
+    if layer.pixel_count() != (layer.width * layer.height) as usize {
+        return Err(format!("texture array layer {} data does not match its dimensions", index));
+    }
+    if layer.width != width || layer.height != height {
+        return Err(format!(
+            "texture array layer {} is {} x {}, but the first layer is {} x {}",
+            index, layer.width, layer.height, width, height
+        ));
+    }
+
+    Ok(())
+}
+
+/// Copy one tile out of an image divided into a grid of equally sized tiles.
+/// Tiles are addressed by column from the left and row from the top of the
+/// image as it is displayed, so the location of a tile in the pixel data
+/// depends on which way the image has been flipped.
+pub(crate) fn grid_tile<P: Pixel>(
+    image: &TexImage2D<P>,
+    columns: u32,
+    rows: u32,
+    column: u32,
+    row: u32,
+    flipped_vertically: bool,
+    flipped_horizontally: bool) -> TexImage2D<P>
+{
+    let tile_width = image.width / columns;
+    let tile_height = image.height / rows;
+    let x = if flipped_horizontally { columns - 1 - column } else { column } * tile_width;
+    let y = if flipped_vertically { rows - 1 - row } else { row } * tile_height;
+
+    image.crop(x, y, tile_width, tile_height)
+}
+
+/// Check that an image divides evenly into a grid of tiles.
+pub(crate) fn check_grid<P: Pixel>(image: &TexImage2D<P>, columns: u32, rows: u32) -> Result<(), String> {
+    if columns == 0 || rows == 0 {
+        return Err(format!("a grid of {} x {} tiles has no tiles", columns, rows));
+    }
+    if image.width == 0
+        || image.height == 0
+        || !image.width.is_multiple_of(columns)
+        || !image.height.is_multiple_of(rows)
+    {
+        return Err(format!(
+            "a {} x {} image does not divide evenly into {} x {} tiles",
+            image.width, image.height, columns, rows
+        ));
+    }
+    if image.pixel_count() != (image.width * image.height) as usize {
+        return Err(String::from("image data does not match its dimensions"));
+    }
+
+    Ok(())
+}
+
+fn grid_layers<P: Pixel>(
+    image: &TexImage2D<P>,
+    columns: u32,
+    rows: u32,
+    flipped_vertically: bool,
+    flipped_horizontally: bool) -> Result<TexImage2DArray<P>, String>
+{
+    check_grid(image, columns, rows)?;
+    let mut layers = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            layers.push(grid_tile(image, columns, rows, column, row, flipped_vertically, flipped_horizontally));
+        }
+    }
+
+    Ok(TexImage2DArray {
+        layers: layers,
+    })
+}
+
+/// Load a texture array from one image file per layer. Every image is
+/// converted to the pixel format of the texture array.
+pub fn load_array_layers<P, Q>(file_paths: &[Q], options: &LoadOptions) -> Result<TexImage2DArray<P>, TexImage2DError>
+where
+    P: Pixel,
+    Q: AsRef<Path>,
+{
+    let mut layers: Vec<TexImage2D<P>> = Vec::with_capacity(file_paths.len());
+    for (index, file_path) in file_paths.iter().enumerate() {
+        let file_path = file_path.as_ref();
+        let layer = load_file_with(file_path, options)?.image.convert();
+        let (width, height) = layers.first().map_or((layer.width, layer.height), |first| (first.width, first.height));
+        check_layer(index, &layer, width, height).map_err(|reason| invalid_layout(reason).with_path(file_path))?;
+        layers.push(layer);
+    }
+
+    TexImage2DArray::from_layers(layers)
+}
+
+/// Load a texture array from an image file holding a grid of equally sized
+/// tiles, one layer per tile. The layers are ordered row by row from the top
+/// left tile of the image.
+pub fn load_array_grid<P, Q>(file_path: Q, columns: u32, rows: u32, options: &LoadOptions) -> Result<TexImage2DArray<P>, TexImage2DError>
+where
+    P: Pixel,
+    Q: AsRef<Path>,
+{
+    let file_path = file_path.as_ref();
+    let image: TexImage2D<P> = load_file_with(file_path, options)?.image.convert();
+
+    grid_layers(&image, columns, rows, options.flip_vertically, options.flip_horizontally)
+        .map_err(|reason| invalid_layout(reason).with_path(file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::R8;
+
+
+    /// A grid image whose pixels hold the index of their tile, counting row by
+    /// row from the top left tile, stored bottom row first.
+    fn grid_image(columns: u32, rows: u32, tile_size: u32) -> TexImage2D<R8> {
+        let width = columns * tile_size;
+        let height = rows * tile_size;
+        let mut data = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                let row_from_top = rows - 1 - y / tile_size;
+                data.push(R8::new((row_from_top * columns + x / tile_size) as u8));
+            }
+        }
+
+        TexImage2D::from_data(width, height, data)
+    }
+
+    #[test]
+    fn test_from_grid_orders_layers_from_top_left() {
+        let image = grid_image(3, 2, 2);
+        let array = TexImage2DArray::from_grid(&image, 3, 2).unwrap();
+
+        assert_eq!(array.layer_count(), 6);
+        assert_eq!((array.width(), array.height()), (2, 2));
+        for (index, layer) in array.iter().enumerate() {
+            assert_eq!(layer.data, vec![R8::new(index as u8); 4]);
+        }
+    }
+
+    #[test]
+    fn test_from_grid_rejects_uneven_grid() {
+        let image = grid_image(3, 2, 2);
+        let error = TexImage2DArray::from_grid(&image, 4, 2).unwrap_err();
+
+        assert!(format!("{}", error).contains("does not divide evenly into 4 x 2 tiles"));
+    }
+
+    #[test]
+    fn test_from_layers_rejects_mismatched_layers() {
+        let layers = vec![TexImage2D::<R8>::new(2, 2), TexImage2D::new(2, 2), TexImage2D::new(2, 1)];
+        let error = TexImage2DArray::from_layers(layers).unwrap_err();
+
+        assert!(format!("{}", error).contains("layer 2 is 2 x 1, but the first layer is 2 x 2"));
+    }
+
+    #[test]
+    fn test_from_layers_rejects_empty_array() {
+        let layers: Vec<TexImage2D<R8>> = vec![];
+
+        assert!(TexImage2DArray::from_layers(layers).is_err());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::{Pixel, RgbaF32, TexImage2D, DEFAULT_MAX_TEXTURE_SIZE};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+
+/// A rectangle in normalized texture coordinates. Texture coordinates start
+/// at the bottom left corner of the atlas, matching the bottom up row order
+/// of texture images.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+    pub u_min: f32,
+    pub v_min: f32,
+    pub u_max: f32,
+    pub v_max: f32,
+}
+
+/// The location of a sprite inside a texture atlas. The pixel rectangle and
+/// the texture coordinates cover the sprite itself, without the padding and
+/// the extruded edges around it. When a sprite is rotated, it is stored
+/// rotated 90 degrees counterclockwise, so its width and height in the atlas
+/// are swapped relative to the original sprite.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rotated: bool,
+    pub uv: UvRect,
+}
+
+/// Find a region by name in a region table sorted by name, such as the tables
+/// emitted by the code generator.
+pub fn find_atlas_region<'a>(regions: &'a [(&str, AtlasRegion)], name: &str) -> Option<&'a AtlasRegion> {
+    regions
+        .binary_search_by(|&(region_name, _)| region_name.cmp(name))
+        .ok()
+        .map(|index| &regions[index].1)
+}
+
+/// The errors that can occur when packing a texture atlas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AtlasError {
+    /// Two sprites were given the same name.
+    DuplicateSpriteName(String),
+    /// A sprite has no pixels, or its data does not match its dimensions.
+    InvalidSprite(String),
+    /// The sprites do not fit into an atlas of the maximum size.
+    SpritesDoNotFit {
+        max_size: u32,
+    },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AtlasError::DuplicateSpriteName(ref name) => {
+                write!(f, "Duplicate sprite name in texture atlas: {}", name)
+            }
+            AtlasError::InvalidSprite(ref name) => {
+                write!(f, "Sprite has no pixels or its data does not match its dimensions: {}", name)
+            }
+            AtlasError::SpritesDoNotFit { max_size } => {
+                write!(f, "Sprites do not fit into a {} x {} texture atlas.", max_size, max_size)
+            }
+        }
+    }
+}
+
+impl Error for AtlasError {}
+
+/// The options controlling how sprites are packed into a texture atlas.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AtlasOptions {
+    padding: u32,
+    extrusion: u32,
+    allow_rotation: bool,
+    max_size: u32,
+}
+
+impl AtlasOptions {
+    pub fn new() -> AtlasOptions {
+        AtlasOptions {
+            padding: 0,
+            extrusion: 0,
+            allow_rotation: false,
+            max_size: DEFAULT_MAX_TEXTURE_SIZE,
+        }
+    }
+
+    /// The number of empty pixels between neighboring sprites.
+    pub fn padding(mut self, padding: u32) -> AtlasOptions {
+        self.padding = padding;
+        self
+    }
+
+    /// The number of times the edge pixels of each sprite are repeated around
+    /// the sprite, so filtering at the edge of a sprite does not sample its
+    /// neighbors.
+    pub fn extrusion(mut self, extrusion: u32) -> AtlasOptions {
+        self.extrusion = extrusion;
+        self
+    }
+
+    /// Allow sprites to be rotated by 90 degrees when that packs them tighter.
+    pub fn allow_rotation(mut self, allow: bool) -> AtlasOptions {
+        self.allow_rotation = allow;
+        self
+    }
+
+    /// The maximum width and height of the atlas.
+    pub fn max_size(mut self, max_size: u32) -> AtlasOptions {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Default for AtlasOptions {
+    fn default() -> AtlasOptions {
+        AtlasOptions::new()
+    }
+}
+
+/// A set of sprites packed into a single power of two texture image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureAtlas<P = crate::Rgba> {
+    pub image: TexImage2D<P>,
+    pub regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl<P: Pixel> TextureAtlas<P> {
+    /// Pack sprites into a texture atlas. The atlas is the smallest power of two
+    /// texture image, favoring square images, the sprites fit into.
+    pub fn pack<'a, N, I>(sprites: I, options: &AtlasOptions) -> Result<TextureAtlas<P>, AtlasError>
+    where
+        N: Into<String>,
+        I: IntoIterator<Item = (N, &'a TexImage2D<P>)>,
+        P: 'a,
+    {
+        let mut named_sprites: Vec<(String, &TexImage2D<P>)> = vec![];
+        for (name, sprite) in sprites {
+            let name = name.into();
+            if sprite.width == 0 || sprite.height == 0 || sprite.pixel_count() != (sprite.width * sprite.height) as usize {
+                return Err(AtlasError::InvalidSprite(name));
+            }
+            if named_sprites.iter().any(|(other, _)| *other == name) {
+                return Err(AtlasError::DuplicateSpriteName(name));
+            }
+            named_sprites.push((name, sprite));
+        }
+
+        // Pack the tallest sprites first, since a skyline packer wastes the least
+        // space that way. Ties are broken by name so packing is deterministic.
+        named_sprites.sort_by(|a, b| {
+            let a_size = a.1.width.max(a.1.height);
+            let b_size = b.1.width.max(b.1.height);
+            b_size.cmp(&a_size).then_with(|| b.1.height.cmp(&a.1.height)).then_with(|| a.0.cmp(&b.0))
+        });
+        let cells: Vec<(u32, u32)> = named_sprites.iter().map(|&(_, sprite)| {
+            (sprite.width + 2 * options.extrusion + options.padding, sprite.height + 2 * options.extrusion + options.padding)
+        }).collect();
+
+        for (width, height) in atlas_sizes(options.max_size) {
+            // Padding is only needed between sprites, so the padding of the sprites
+            // at the top and right edges may hang over the edge of the atlas.
+            let mut packer = Skyline::new(width + options.padding, height + options.padding);
+            let placements: Option<Vec<Placement>> = cells.iter().map(|&(cell_width, cell_height)| {
+                packer.insert(cell_width, cell_height, options.allow_rotation)
+            }).collect();
+
+            if let Some(placements) = placements {
+                return Ok(TextureAtlas::build(width, height, &named_sprites, &placements, options));
+            }
+        }
+
+        Err(AtlasError::SpritesDoNotFit { max_size: options.max_size })
+    }
+
+    /// Get the region of a sprite by name.
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    fn build(
+        width: u32,
+        height: u32,
+        sprites: &[(String, &TexImage2D<P>)],
+        placements: &[Placement],
+        options: &AtlasOptions) -> TextureAtlas<P>
+    {
+        let transparent = P::from_rgba_f32(RgbaF32::new(0.0, 0.0, 0.0, 0.0));
+        let mut image = TexImage2D::from_data(width, height, vec![transparent; (width * height) as usize]);
+        let mut regions = BTreeMap::new();
+        let extrusion = options.extrusion as i64;
+        for (&(ref name, sprite), placement) in sprites.iter().zip(placements.iter()) {
+            let (region_width, region_height) = if placement.rotated {
+                (sprite.height, sprite.width)
+            } else {
+                (sprite.width, sprite.height)
+            };
+            // Copy the sprite with its edge pixels clamped outward into the extruded border.
+            for cell_y in 0..(region_height + 2 * options.extrusion) {
+                for cell_x in 0..(region_width + 2 * options.extrusion) {
+                    let x = (cell_x as i64 - extrusion).max(0).min(region_width as i64 - 1) as u32;
+                    let y = (cell_y as i64 - extrusion).max(0).min(region_height as i64 - 1) as u32;
+                    let (sprite_x, sprite_y) = if placement.rotated {
+                        (y, sprite.height - 1 - x)
+                    } else {
+                        (x, y)
+                    };
+                    let pixel = sprite.data[(sprite_y * sprite.width + sprite_x) as usize];
+                    let atlas_x = placement.x + cell_x;
+                    let atlas_y = placement.y + cell_y;
+                    image.data[(atlas_y * width + atlas_x) as usize] = pixel;
+                }
+            }
+
+            let x = placement.x + options.extrusion;
+            let y = placement.y + options.extrusion;
+            let region = AtlasRegion {
+                x: x,
+                y: y,
+                width: region_width,
+                height: region_height,
+                rotated: placement.rotated,
+                uv: UvRect {
+                    u_min: x as f32 / width as f32,
+                    v_min: y as f32 / height as f32,
+                    u_max: (x + region_width) as f32 / width as f32,
+                    v_max: (y + region_height) as f32 / height as f32,
+                },
+            };
+            regions.insert(name.clone(), region);
+        }
+
+        TextureAtlas {
+            image: image,
+            regions: regions,
+        }
+    }
+}
+
+/// The power of two atlas sizes up to the maximum size, from smallest to
+/// largest area, with square sizes before rectangular sizes of the same area.
+fn atlas_sizes(max_size: u32) -> Vec<(u32, u32)> {
+    let mut sizes = vec![];
+    let mut width = 1;
+    while width <= max_size {
+        let mut height = 1;
+        while height <= max_size {
+            sizes.push((width, height));
+            if height > max_size / 2 {
+                break;
+            }
+            height *= 2;
+        }
+        if width > max_size / 2 {
+            break;
+        }
+        width *= 2;
+    }
+    sizes.sort_by_key(|&(width, height)| {
+        let area = width as u64 * height as u64;
+        let aspect = if width > height { width / height } else { height / width };
+        (area, aspect, height)
+    });
+
+    sizes
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Placement {
+    x: u32,
+    y: u32,
+    rotated: bool,
+}
+
+/// A horizontal segment of the skyline at a given height.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// A skyline bottom left rectangle packer. The skyline is the upper boundary
+/// of the rectangles packed so far, and each rectangle is placed on the
+/// skyline where its top edge ends up lowest.
+struct Skyline {
+    width: u32,
+    height: u32,
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Skyline {
+        Skyline {
+            width: width,
+            height: height,
+            segments: vec![Segment { x: 0, y: 0, width: width }],
+        }
+    }
+
+    /// The height at which a rectangle starting at the given segment rests on
+    /// the skyline, if it fits.
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.segments[index].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        let mut i = index;
+        while remaining > 0 {
+            let segment = self.segments[i];
+            y = y.max(segment.y);
+            if y + height > self.height {
+                return None;
+            }
+            remaining -= segment.width as i64;
+            i += 1;
+        }
+
+        Some(y)
+    }
+
+    fn best_fit(&self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        for index in 0..self.segments.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                let x = self.segments[index].x;
+                let is_better = match best {
+                    Some((_, best_x, best_y)) => (y + height, x) < (best_y + height, best_x),
+                    None => true,
+                };
+                if is_better {
+                    best = Some((index, x, y));
+                }
+            }
+        }
+
+        best
+    }
+
+    fn insert(&mut self, width: u32, height: u32, allow_rotation: bool) -> Option<Placement> {
+        let upright = self.best_fit(width, height).map(|(index, x, y)| (index, x, y, false));
+        let rotated = if allow_rotation && width != height {
+            self.best_fit(height, width).map(|(index, x, y)| (index, x, y, true))
+        } else {
+            None
+        };
+
+        let (index, x, y, is_rotated) = match (upright, rotated) {
+            (Some(a), Some(b)) => {
+                if (b.2 + width, b.1) < (a.2 + height, a.1) { b } else { a }
+            }
+            (Some(a), None) => a,
+            (None, Some(b)) => b,
+            (None, None) => return None,
+        };
+        let (placed_width, placed_height) = if is_rotated { (height, width) } else { (width, height) };
+        self.add_segment(index, x, y + placed_height, placed_width);
+
+        Some(Placement { x: x, y: y, rotated: is_rotated })
+    }
+
+    fn add_segment(&mut self, index: usize, x: u32, y: u32, width: u32) {
+        self.segments.insert(index, Segment { x: x, y: y, width: width });
+
+        // Shrink or remove the segments the new segment covers.
+        let right = x + width;
+        let i = index + 1;
+        while i < self.segments.len() {
+            let segment = self.segments[i];
+            if segment.x >= right {
+                break;
+            }
+            let segment_right = segment.x + segment.width;
+            if segment_right <= right {
+                self.segments.remove(i);
+            } else {
+                self.segments[i].x = right;
+                self.segments[i].width = segment_right - right;
+                break;
+            }
+        }
+
+        // Merge neighboring segments at the same height.
+        let mut i = 0;
+        while i + 1 < self.segments.len() {
+            if self.segments[i].y == self.segments[i + 1].y {
+                self.segments[i].width += self.segments[i + 1].width;
+                self.segments.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rgba;
+
+
+    fn sprite(width: u32, height: u32, color: Rgba) -> TexImage2D {
+        TexImage2D::from_data(width, height, vec![color; (width * height) as usize])
+    }
+
+    fn overlaps(a: &AtlasRegion, b: &AtlasRegion, margin: u32) -> bool {
+        a.x < b.x + b.width + margin && b.x < a.x + a.width + margin &&
+        a.y < b.y + b.height + margin && b.y < a.y + a.height + margin
+    }
+
+    #[test]
+    fn test_atlas_sizes_are_ordered_by_area() {
+        let sizes = atlas_sizes(4);
+
+        assert_eq!(&sizes[0..4], &[(1, 1), (2, 1), (1, 2), (2, 2)]);
+        assert_eq!(sizes.last(), Some(&(4, 4)));
+        assert_eq!(sizes.len(), 9);
+    }
+
+    #[test]
+    fn test_four_equal_sprites_fill_a_square_atlas() {
+        let sprites: Vec<(String, TexImage2D)> = (0..4).map(|i| {
+            (format!("sprite{}", i), sprite(8, 8, Rgba::new(i as u8, 0, 0, 255)))
+        }).collect();
+        let atlas = TextureAtlas::pack(sprites.iter().map(|(name, image)| (name.as_str(), image)), &AtlasOptions::new()).unwrap();
+
+        assert_eq!((atlas.image.width, atlas.image.height), (16, 16));
+        assert_eq!(atlas.regions.len(), 4);
+        for (name, image) in sprites.iter() {
+            let region = atlas.region(name).unwrap();
+            for y in 0..region.height {
+                for x in 0..region.width {
+                    let pixel = atlas.image.data[((region.y + y) * 16 + region.x + x) as usize];
+                    assert_eq!(pixel, image.data[0]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_padded_sprites_do_not_touch() {
+        let a = sprite(5, 3, Rgba::new(255, 0, 0, 255));
+        let b = sprite(4, 4, Rgba::new(0, 255, 0, 255));
+        let c = sprite(7, 2, Rgba::new(0, 0, 255, 255));
+        let options = AtlasOptions::new().padding(2).extrusion(1);
+        let atlas = TextureAtlas::pack(vec![("a", &a), ("b", &b), ("c", &c)], &options).unwrap();
+        let regions: Vec<&AtlasRegion> = atlas.regions.values().collect();
+        for i in 0..regions.len() {
+            for j in (i + 1)..regions.len() {
+                // Each region is surrounded by one extruded pixel plus two pixels of padding.
+                assert!(!overlaps(regions[i], regions[j], 2 + 2));
+            }
+        }
+        assert!(atlas.image.width.is_power_of_two() && atlas.image.height.is_power_of_two());
+    }
+
+    #[test]
+    fn test_extrusion_repeats_edge_pixels() {
+        let data = vec![
+            Rgba::new(1, 0, 0, 255), Rgba::new(2, 0, 0, 255),
+            Rgba::new(3, 0, 0, 255), Rgba::new(4, 0, 0, 255),
+        ];
+        let image = TexImage2D::from_data(2, 2, data);
+        let atlas = TextureAtlas::pack(vec![("a", &image)], &AtlasOptions::new().extrusion(1)).unwrap();
+        let width = atlas.image.width;
+        let row = |y: u32| -> Vec<u8> { (0..4).map(|x| atlas.image.data[(y * width + x) as usize].r).collect() };
+
+        assert_eq!(row(0), vec![1, 1, 2, 2]);
+        assert_eq!(row(1), vec![1, 1, 2, 2]);
+        assert_eq!(row(2), vec![3, 3, 4, 4]);
+        assert_eq!(row(3), vec![3, 3, 4, 4]);
+        assert_eq!(atlas.region("a").unwrap().x, 1);
+    }
+
+    #[test]
+    fn test_rotation_rotates_sprite_counterclockwise() {
+        let data = vec![
+            Rgba::new(1, 0, 0, 255), Rgba::new(2, 0, 0, 255),
+            Rgba::new(3, 0, 0, 255), Rgba::new(4, 0, 0, 255),
+            Rgba::new(5, 0, 0, 255), Rgba::new(6, 0, 0, 255),
+        ];
+        let tall = TexImage2D::from_data(2, 3, data);
+        // A 4 x 2 atlas is tried before a 2 x 4 atlas, and only fits the sprite rotated.
+        let options = AtlasOptions::new().allow_rotation(true);
+        let atlas = TextureAtlas::pack(vec![("tall", &tall)], &options).unwrap();
+        let region = atlas.region("tall").unwrap();
+
+        assert_eq!((atlas.image.width, atlas.image.height), (4, 2));
+        assert!(region.rotated);
+        assert_eq!((region.width, region.height), (3, 2));
+        let row = |y: u32| -> Vec<u8> { (0..3).map(|x| atlas.image.data[(y * 4 + x) as usize].r).collect() };
+        // The bottom row of the rotated sprite is the leftmost column of the original,
+        // and its top row is the rightmost column.
+        assert_eq!(row(0), vec![5, 3, 1]);
+        assert_eq!(row(1), vec![6, 4, 2]);
+    }
+
+    #[test]
+    fn test_sprites_that_do_not_fit_are_reported() {
+        let image = sprite(8, 8, Rgba::default());
+        let result = TextureAtlas::pack(vec![("a", &image), ("b", &image)], &AtlasOptions::new().max_size(8));
+
+        assert_eq!(result, Err(AtlasError::SpritesDoNotFit { max_size: 8 }));
+    }
+
+    #[test]
+    fn test_duplicate_sprite_names_are_reported() {
+        let image = sprite(1, 1, Rgba::default());
+        let result = TextureAtlas::pack(vec![("a", &image), ("a", &image)], &AtlasOptions::new());
+
+        assert_eq!(result, Err(AtlasError::DuplicateSpriteName(String::from("a"))));
+    }
+
+    #[test]
+    fn test_find_atlas_region_in_sorted_table() {
+        let region = |x| AtlasRegion {
+            x: x, y: 0, width: 1, height: 1, rotated: false,
+            uv: UvRect { u_min: 0.0, v_min: 0.0, u_max: 1.0, v_max: 1.0 },
+        };
+        let table = [("alpha", region(0)), ("beta", region(1)), ("gamma", region(2))];
+
+        assert_eq!(find_atlas_region(&table, "beta").map(|region| region.x), Some(1));
+        assert_eq!(find_atlas_region(&table, "delta"), None);
+    }
+}
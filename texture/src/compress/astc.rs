@@ -0,0 +1,1043 @@
+use super::{least_squares_endpoints, principal_endpoints, to_point, Quality};
+use crate::Rgba;
+use std::convert::TryInto;
+use std::sync::OnceLock;
+
+
+/// The number of values of each weight range a block mode can select, in
+/// the order of the range field of the block mode.
+const WEIGHT_LEVELS: [u32; 12] = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32];
+
+/// The number of values of each range the endpoint values can be quantized
+/// to. A block uses the largest range that fits in the bits its weights leave.
+const COLOR_LEVELS: [u32; 21] = [
+    2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256
+];
+
+/// The color of a block that is invalid, or uses features the decoder does
+/// not support.
+const ERROR_COLOR: Rgba = Rgba::new(255, 0, 255, 255);
+
+/// The color endpoint mode for RGB endpoints stored directly.
+const RGB_DIRECT: u32 = 8;
+
+/// The color endpoint mode for RGBA endpoints stored directly.
+const RGBA_DIRECT: u32 = 12;
+
+/// The number of bits of every value of a range, and whether each value
+/// also has a trit (3) or a quint (5) stored alongside the bits of other
+/// values, or neither (1).
+fn ise_shape(levels: u32) -> (u32, u32) {
+    let kind = if levels.is_multiple_of(3) { 3 } else if levels.is_multiple_of(5) { 5 } else { 1 };
+
+    ((levels / kind).trailing_zeros(), kind)
+}
+
+/// The number of bits a sequence of values of a range takes up when
+/// encoded with integer sequence encoding.
+fn ise_bit_count(count: usize, levels: u32) -> usize {
+    let (bits, kind) = ise_shape(levels);
+    let packed = match kind {
+        3 => (8 * count).div_ceil(5),
+        5 => (7 * count).div_ceil(3),
+        _ => 0,
+    };
+
+    count * (bits as usize) + packed
+}
+
+/// Unpack the five trits stored in eight bits.
+fn decode_trits(packed: u32) -> [u32; 5] {
+    let bit = |index: u32| (packed >> index) & 1;
+    let (c, t3, t4);
+    if (packed >> 2) & 7 == 7 {
+        c = ((packed >> 5) << 2) | (packed & 3);
+        t4 = 2;
+        t3 = 2;
+    } else {
+        c = packed & 0x1F;
+        if (packed >> 5) & 3 == 3 {
+            t4 = 2;
+            t3 = bit(7);
+        } else {
+            t4 = bit(7);
+            t3 = (packed >> 5) & 3;
+        }
+    }
+
+    let c_bit = |index: u32| (c >> index) & 1;
+    let (t0, t1, t2);
+    if c & 3 == 3 {
+        t2 = 2;
+        t1 = c_bit(4);
+        t0 = (c_bit(3) << 1) | (c_bit(2) & !c_bit(3) & 1);
+    } else if (c >> 2) & 3 == 3 {
+        t2 = 2;
+        t1 = 2;
+        t0 = c & 3;
+    } else {
+        t2 = c_bit(4);
+        t1 = (c >> 2) & 3;
+        t0 = (c_bit(1) << 1) | (c_bit(0) & !c_bit(1) & 1);
+    }
+
+    [t0, t1, t2, t3, t4]
+}
+
+/// Unpack the three quints stored in seven bits.
+fn decode_quints(packed: u32) -> [u32; 3] {
+    let bit = |index: u32| (packed >> index) & 1;
+    if (packed >> 1) & 3 == 3 && (packed >> 5) & 3 == 0 {
+        let q2 = (bit(0) << 2) | ((bit(4) & !bit(0) & 1) << 1) | (bit(3) & !bit(0) & 1);
+        return [4, 4, q2];
+    }
+
+    let (q2, c) = if (packed >> 1) & 3 == 3 {
+        (4, (((packed >> 3) & 3) << 3) | ((!(packed >> 5) & 3) << 1) | bit(0))
+    } else {
+        ((packed >> 5) & 3, packed & 0x1F)
+    };
+    if c & 7 == 5 {
+        [c >> 3, 4, q2]
+    } else {
+        [c & 7, c >> 3, q2]
+    }
+}
+
+/// The smallest packing of every combination of five trits, indexed by the
+/// trits as the digits of a base three number with the first trit lowest.
+/// Choosing the smallest packing keeps the bits of trailing zero trits zero,
+/// so a sequence can stop partway through its last group of values.
+fn trit_packings() -> &'static [u8; 243] {
+    static PACKINGS: OnceLock<[u8; 243]> = OnceLock::new();
+    PACKINGS.get_or_init(|| {
+        let mut packings = [0; 243];
+        for packed in (0..256).rev() {
+            let index = decode_trits(packed).iter().rev().fold(0, |index, &trit| index * 3 + trit);
+            packings[index as usize] = packed as u8;
+        }
+
+        packings
+    })
+}
+
+/// The smallest packing of every combination of three quints, indexed as in
+/// `trit_packings`.
+fn quint_packings() -> &'static [u8; 125] {
+    static PACKINGS: OnceLock<[u8; 125]> = OnceLock::new();
+    PACKINGS.get_or_init(|| {
+        let mut packings = [0; 125];
+        for packed in (0..128).rev() {
+            let index = decode_quints(packed).iter().rev().fold(0, |index, &quint| index * 5 + quint);
+            packings[index as usize] = packed as u8;
+        }
+
+        packings
+    })
+}
+
+/// Encode values of a range with integer sequence encoding, starting from
+/// the least significant bit. Bits past the end of the sequence are zero.
+fn ise_encode(values: &[u32], levels: u32) -> u128 {
+    let (bits, kind) = ise_shape(levels);
+    let mut stream = 0u128;
+    let mut position = 0;
+    let mut write = |value: u32, count: u32| {
+        if position < 128 {
+            stream |= (value as u128) << position;
+        }
+        position += count;
+    };
+
+    let mask = (1 << bits) - 1;
+    match kind {
+        3 => for group in values.chunks(5) {
+            let mut digits = [0; 5];
+            for (digit, &value) in digits.iter_mut().zip(group) {
+                *digit = value >> bits;
+            }
+            let index = digits.iter().rev().fold(0, |index, &trit| index * 3 + trit);
+            let packed = trit_packings()[index as usize] as u32;
+            let low = |i: usize| group.get(i).map_or(0, |&value| value & mask);
+            write(low(0), bits);
+            write(packed & 3, 2);
+            write(low(1), bits);
+            write((packed >> 2) & 3, 2);
+            write(low(2), bits);
+            write((packed >> 4) & 1, 1);
+            write(low(3), bits);
+            write((packed >> 5) & 3, 2);
+            write(low(4), bits);
+            write(packed >> 7, 1);
+        },
+        5 => for group in values.chunks(3) {
+            let mut digits = [0; 3];
+            for (digit, &value) in digits.iter_mut().zip(group) {
+                *digit = value >> bits;
+            }
+            let index = digits.iter().rev().fold(0, |index, &quint| index * 5 + quint);
+            let packed = quint_packings()[index as usize] as u32;
+            let low = |i: usize| group.get(i).map_or(0, |&value| value & mask);
+            write(low(0), bits);
+            write(packed & 7, 3);
+            write(low(1), bits);
+            write((packed >> 3) & 3, 2);
+            write(low(2), bits);
+            write(packed >> 5, 2);
+        },
+        _ => for &value in values {
+            write(value, bits);
+        },
+    }
+
+    let count = ise_bit_count(values.len(), levels);
+    if count >= 128 { stream } else { stream & ((1 << count) - 1) }
+}
+
+/// Decode a sequence of values of a range. The stream must be zero past the
+/// end of the sequence.
+fn ise_decode(stream: u128, count: usize, levels: u32) -> Vec<u32> {
+    let (bits, kind) = ise_shape(levels);
+    let mut position = 0;
+    let mut read = |count: u32| {
+        let value = if position < 128 { ((stream >> position) as u32) & ((1 << count) - 1) } else { 0 };
+        position += count;
+        value
+    };
+
+    let mut values = Vec::with_capacity(count + 4);
+    while values.len() < count {
+        match kind {
+            3 => {
+                let mut low = [0; 5];
+                low[0] = read(bits);
+                let mut packed = read(2);
+                low[1] = read(bits);
+                packed |= read(2) << 2;
+                low[2] = read(bits);
+                packed |= read(1) << 4;
+                low[3] = read(bits);
+                packed |= read(2) << 5;
+                low[4] = read(bits);
+                packed |= read(1) << 7;
+                for (&trit, &low) in decode_trits(packed).iter().zip(&low) {
+                    values.push((trit << bits) | low);
+                }
+            }
+            5 => {
+                let mut low = [0; 3];
+                low[0] = read(bits);
+                let mut packed = read(3);
+                low[1] = read(bits);
+                packed |= read(2) << 3;
+                low[2] = read(bits);
+                packed |= read(2) << 5;
+                for (&quint, &low) in decode_quints(packed).iter().zip(&low) {
+                    values.push((quint << bits) | low);
+                }
+            }
+            _ => values.push(read(bits)),
+        }
+    }
+    values.truncate(count);
+
+    values
+}
+
+/// Widen a value by repeating its bits.
+fn replicate(value: u32, bits: u32, target: u32) -> u32 {
+    if bits == 0 {
+        return 0;
+    }
+
+    let mut result = 0;
+    let mut shift = target as i32;
+    while shift > 0 {
+        shift -= bits as i32;
+        result |= if shift >= 0 { value << shift } else { value >> -shift };
+    }
+
+    result
+}
+
+/// The weight of the second endpoint, out of 64, a quantized weight stands for.
+fn unquantize_weight(value: u32, levels: u32) -> u32 {
+    let (bits, kind) = ise_shape(levels);
+    let weight = if kind == 1 {
+        replicate(value, bits, 6)
+    } else if bits == 0 {
+        if kind == 3 { [0, 32, 63][value as usize] } else { [0, 16, 32, 47, 63][value as usize] }
+    } else {
+        let digit = value >> bits;
+        let low = value & ((1 << bits) - 1);
+        let a = if low & 1 == 1 { 0x7F } else { 0 };
+        let high = low >> 1;
+        let (b, c) = match (kind, bits) {
+            (3, 1) => (0, 50),
+            (5, 1) => (0, 28),
+            (3, 2) => ((high << 6) | (high << 2) | high, 23),
+            (5, 2) => ((high << 6) | (high << 1), 13),
+            _ => ((high << 5) | high, 11),
+        };
+        let t = (digit * c + b) ^ a;
+
+        (a & 0x20) | (t >> 2)
+    };
+
+    if weight > 32 { weight + 1 } else { weight }
+}
+
+/// The endpoint channel value a quantized endpoint value stands for.
+fn unquantize_color(value: u32, levels: u32) -> u8 {
+    let (bits, kind) = ise_shape(levels);
+    if kind == 1 {
+        return replicate(value, bits, 8) as u8;
+    }
+
+    let digit = value >> bits;
+    let low = value & ((1 << bits) - 1);
+    let a = if low & 1 == 1 { 0x1FF } else { 0 };
+    let high = low >> 1;
+    let (b, c) = match (kind, bits) {
+        (3, 1) => (0, 204),
+        (5, 1) => (0, 113),
+        (3, 2) => (high * 0x116, 93),
+        (5, 2) => (high * 0x10C, 54),
+        (3, 3) => ((high << 7) | (high << 2) | high, 44),
+        (5, 3) => ((high << 7) | (high << 1) | (high >> 1), 26),
+        (3, 4) => ((high << 6) | high, 22),
+        (5, 4) => ((high << 6) | (high >> 1), 13),
+        (3, 5) => ((high << 5) | (high >> 2), 11),
+        (5, 5) => ((high << 5) | (high >> 3), 6),
+        _ => ((high << 4) | (high >> 4), 5),
+    };
+    let t = (digit * c + b) ^ a;
+
+    ((a & 0x80) | (t >> 2)) as u8
+}
+
+/// The weight grid a block mode selects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct BlockMode {
+    grid_width: u32,
+    grid_height: u32,
+    weight_levels: u32,
+    dual_plane: bool,
+}
+
+/// Read the eleven bit block mode field. Returns `None` for reserved modes
+/// and for modes whose weights do not fit in a block.
+fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+    let a = (mode >> 5) & 3;
+    let mut high_precision = (mode >> 9) & 1;
+    let mut dual_plane = (mode >> 10) & 1;
+    let mut range = (mode >> 4) & 1;
+    let (grid_width, grid_height);
+    if mode & 3 != 0 {
+        range |= (mode & 3) << 1;
+        let b = (mode >> 7) & 3;
+        match (mode >> 2) & 3 {
+            0 => { grid_width = b + 4; grid_height = a + 2; }
+            1 => { grid_width = b + 8; grid_height = a + 2; }
+            2 => { grid_width = a + 2; grid_height = b + 8; }
+            _ if mode & 0x100 != 0 => { grid_width = (b & 1) + 2; grid_height = a + 2; }
+            _ => { grid_width = a + 2; grid_height = (b & 1) + 6; }
+        }
+    } else {
+        range |= ((mode >> 2) & 3) << 1;
+        if range < 2 {
+            return None;
+        }
+        match (mode >> 7) & 3 {
+            0 => { grid_width = 12; grid_height = a + 2; }
+            1 => { grid_width = a + 2; grid_height = 12; }
+            2 => {
+                grid_width = a + 6;
+                grid_height = ((mode >> 9) & 3) + 6;
+                high_precision = 0;
+                dual_plane = 0;
+            }
+            _ => match a {
+                0 => { grid_width = 6; grid_height = 10; }
+                1 => { grid_width = 10; grid_height = 6; }
+                _ => return None,
+            },
+        }
+    }
+
+    let weight_levels = WEIGHT_LEVELS[(range - 2 + 6 * high_precision) as usize];
+    let weight_count = (grid_width * grid_height * (dual_plane + 1)) as usize;
+    let weight_bits = ise_bit_count(weight_count, weight_levels);
+    if weight_count > 64 || !(24..=96).contains(&weight_bits) {
+        return None;
+    }
+
+    Some(BlockMode {
+        grid_width: grid_width,
+        grid_height: grid_height,
+        weight_levels: weight_levels,
+        dual_plane: dual_plane == 1,
+    })
+}
+
+/// Build the block mode field for a single plane of weights, if there is a
+/// layout for the weight grid and range.
+fn encode_block_mode(grid_width: u32, grid_height: u32, weight_levels: u32) -> Option<u32> {
+    let index = WEIGHT_LEVELS.iter().position(|&levels| levels == weight_levels)? as u32;
+    let range = index % 6 + 2;
+    let high_precision = index / 6;
+    let low = ((range & 1) << 4) | (high_precision << 9);
+    let (n, m) = (grid_width, grid_height);
+
+    let mode = match (n, m) {
+        (4..=7, 2..=5) => (range >> 1) | low | ((m - 2) << 5) | ((n - 4) << 7),
+        (8..=11, 2..=5) => (range >> 1) | (1 << 2) | low | ((m - 2) << 5) | ((n - 8) << 7),
+        (2..=5, 8..=11) => (range >> 1) | (2 << 2) | low | ((n - 2) << 5) | ((m - 8) << 7),
+        (2..=5, 6..=7) => (range >> 1) | (3 << 2) | low | ((n - 2) << 5) | ((m - 6) << 7),
+        (2..=3, 2..=5) => (range >> 1) | (3 << 2) | low | ((m - 2) << 5) | ((n - 2) << 7) | (1 << 8),
+        (12, 2..=5) => ((range >> 1) << 2) | low | ((m - 2) << 5),
+        (2..=5, 12) => ((range >> 1) << 2) | low | ((n - 2) << 5) | (1 << 7),
+        (6, 10) => ((range >> 1) << 2) | low | (3 << 7),
+        (10, 6) => ((range >> 1) << 2) | low | (1 << 5) | (3 << 7),
+        (6..=9, 6..=9) if high_precision == 0 => {
+            ((range >> 1) << 2) | low | ((n - 6) << 5) | (2 << 7) | ((m - 6) << 9)
+        }
+        _ => return None,
+    };
+
+    match decode_block_mode(mode) {
+        Some(decoded) if decoded.grid_width == n && decoded.grid_height == m => Some(mode),
+        _ => None,
+    }
+}
+
+/// The largest endpoint range whose values fit in the given number of bits.
+fn color_levels(value_count: usize, bits: usize) -> Option<u32> {
+    COLOR_LEVELS.iter().rev().cloned().find(|&levels| ise_bit_count(value_count, levels) <= bits)
+}
+
+/// The grid weights that contribute to each pixel of a block, with their
+/// contributions out of 16, when the grid is stretched over the block.
+fn infill_factors(
+    grid_width: u32,
+    grid_height: u32,
+    block_width: u32,
+    block_height: u32) -> Vec<[(usize, u32); 4]>
+{
+    let ds = (1024 + block_width / 2) / (block_width - 1);
+    let dt = (1024 + block_height / 2) / (block_height - 1);
+
+    let mut factors = Vec::with_capacity((block_width * block_height) as usize);
+    for t in 0..block_height {
+        for s in 0..block_width {
+            let gs = (ds * s * (grid_width - 1) + 32) >> 6;
+            let gt = (dt * t * (grid_height - 1) + 32) >> 6;
+            let (fs, ft) = (gs & 0xF, gt & 0xF);
+            let base = ((gs >> 4) + (gt >> 4) * grid_width) as usize;
+            let w11 = (fs * ft + 8) >> 4;
+            factors.push([
+                (base, 16 + w11 - fs - ft),
+                (base + 1, fs - w11),
+                (base + grid_width as usize, ft - w11),
+                (base + grid_width as usize + 1, w11),
+            ]);
+        }
+    }
+
+    factors
+}
+
+/// The weight of every pixel of a block, from the weights of the grid.
+fn infill(grid: &[u32], factors: &[[(usize, u32); 4]]) -> Vec<u32> {
+    factors.iter().map(|factors| {
+        let sum = factors.iter()
+            .filter(|&&(_, factor)| factor > 0)
+            .map(|&(index, factor)| grid.get(index).map_or(0, |&weight| weight * factor))
+            .sum::<u32>();
+        (sum + 8) >> 4
+    }).collect()
+}
+
+/// Move an endpoint towards blue, undoing the blue contraction encoders use
+/// to gain precision in the red and green channels.
+fn blue_contract(endpoint: [i32; 4]) -> [i32; 4] {
+    let [r, g, b, a] = endpoint;
+
+    [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+/// Move the top bit of a value into its base, leaving a signed six bit
+/// offset.
+fn bit_transfer_signed(offset: i32, base: i32) -> (i32, i32) {
+    let base = (base >> 1) | (offset & 0x80);
+    let offset = (offset >> 1) & 0x3F;
+
+    (if offset & 0x20 != 0 { offset - 0x40 } else { offset }, base)
+}
+
+/// The endpoints of a low dynamic range color endpoint mode, from the
+/// unquantized endpoint values. Returns `None` for high dynamic range modes.
+fn decode_endpoints(mode: u32, values: &[u8]) -> Option<[[u8; 4]; 2]> {
+    let mut v: Vec<i32> = values.iter().map(|&value| value as i32).collect();
+    let endpoints = match mode {
+        0 => [[v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]],
+        1 => {
+            let start = (v[0] >> 2) | (v[1] & 0xC0);
+            let end = (start + (v[1] & 0x3F)).min(255);
+            [[start, start, start, 255], [end, end, end, 255]]
+        }
+        4 => [[v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]],
+        5 => {
+            let (l, l0) = bit_transfer_signed(v[1], v[0]);
+            let (a, a0) = bit_transfer_signed(v[3], v[2]);
+            [[l0, l0, l0, a0], [l0 + l, l0 + l, l0 + l, a0 + a]]
+        }
+        6 | 10 => {
+            let (a0, a1) = if mode == 10 { (v[4], v[5]) } else { (255, 255) };
+            [
+                [(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, a0],
+                [v[0], v[1], v[2], a1],
+            ]
+        }
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                [[v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1]]
+            } else {
+                [blue_contract([v[1], v[3], v[5], a1]), blue_contract([v[0], v[2], v[4], a0])]
+            }
+        }
+        9 | 13 => {
+            for pair in 0..(v.len() / 2) {
+                let (offset, base) = bit_transfer_signed(v[2 * pair + 1], v[2 * pair]);
+                v[2 * pair] = base;
+                v[2 * pair + 1] = offset;
+            }
+            let (a0, a) = if mode == 13 { (v[6], v[7]) } else { (255, 0) };
+            let start = [v[0], v[2], v[4], a0];
+            let end = [v[0] + v[1], v[2] + v[3], v[4] + v[5], a0 + a];
+            if v[1] + v[3] + v[5] >= 0 {
+                [start, end]
+            } else {
+                [blue_contract(end), blue_contract(start)]
+            }
+        }
+        _ => return None,
+    };
+
+    let clamp = |endpoint: [i32; 4]| {
+        let mut clamped = [0; 4];
+        for (channel, &value) in clamped.iter_mut().zip(&endpoint) {
+            *channel = value.clamp(0, 255) as u8;
+        }
+        clamped
+    };
+
+    Some([clamp(endpoints[0]), clamp(endpoints[1])])
+}
+
+/// Interpolate between two endpoint channel values with a weight out of 64,
+/// at the sixteen bit precision of the format.
+#[inline]
+fn interpolate(start: u8, end: u8, weight: u32) -> u8 {
+    let start = start as u32 * 257;
+    let end = end as u32 * 257;
+
+    ((((64 - weight) * start + weight * end + 32) >> 6) >> 8) as u8
+}
+
+/// Decode a 128 bit block into its pixels, row by row.
+fn decode_block(bits: u128, block_width: u32, block_height: u32, pixels: &mut [Rgba]) {
+    let field = |start: u32, count: u32| ((bits >> start) as u32) & ((1 << count) - 1);
+    let fill = |pixels: &mut [Rgba], color: Rgba| {
+        for pixel in pixels.iter_mut() {
+            *pixel = color;
+        }
+    };
+
+    if field(0, 9) == 0x1FC {
+        let color = if field(9, 1) == 1 {
+            ERROR_COLOR
+        } else {
+            Rgba::new(
+                (field(64, 16) >> 8) as u8, (field(80, 16) >> 8) as u8,
+                (field(96, 16) >> 8) as u8, (field(112, 16) >> 8) as u8,
+            )
+        };
+        return fill(pixels, color);
+    }
+
+    let mode = match decode_block_mode(field(0, 11)) {
+        Some(mode) if mode.grid_width <= block_width && mode.grid_height <= block_height => mode,
+        _ => return fill(pixels, ERROR_COLOR),
+    };
+    if field(11, 2) != 0 {
+        return fill(pixels, ERROR_COLOR);
+    }
+
+    let endpoint_mode = field(13, 4);
+    let planes = if mode.dual_plane { 2 } else { 1 };
+    let weight_count = (mode.grid_width * mode.grid_height) as usize * planes;
+    let weight_bits = ise_bit_count(weight_count, mode.weight_levels);
+    let selector_bits = if mode.dual_plane { 2 } else { 0 };
+    let value_count = 2 * (endpoint_mode as usize / 4 + 1);
+    let color_bits = 128 - 17 - weight_bits - selector_bits;
+    let levels = match color_levels(value_count, color_bits) {
+        Some(levels) if levels >= 6 => levels,
+        _ => return fill(pixels, ERROR_COLOR),
+    };
+
+    let color_stream = (bits >> 17) & ((1 << ise_bit_count(value_count, levels)) - 1);
+    let values: Vec<u8> = ise_decode(color_stream, value_count, levels).into_iter()
+        .map(|value| unquantize_color(value, levels))
+        .collect();
+    let endpoints = match decode_endpoints(endpoint_mode, &values) {
+        Some(endpoints) => endpoints,
+        None => return fill(pixels, ERROR_COLOR),
+    };
+
+    let weight_stream = bits.reverse_bits() & ((1 << weight_bits) - 1);
+    let weights: Vec<u32> = ise_decode(weight_stream, weight_count, mode.weight_levels).into_iter()
+        .map(|weight| unquantize_weight(weight, mode.weight_levels))
+        .collect();
+    let factors = infill_factors(mode.grid_width, mode.grid_height, block_width, block_height);
+    let plane: Vec<u32> = weights.iter().step_by(planes).cloned().collect();
+    let first = infill(&plane, &factors);
+    let (second, selected) = if mode.dual_plane {
+        let plane: Vec<u32> = weights.iter().skip(1).step_by(2).cloned().collect();
+        (infill(&plane, &factors), field((128 - weight_bits - 2) as u32, 2) as usize)
+    } else {
+        (first.clone(), 4)
+    };
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let channel = |channel: usize| {
+            let weight = if channel == selected { second[i] } else { first[i] };
+            interpolate(endpoints[0][channel], endpoints[1][channel], weight)
+        };
+        *pixel = Rgba::new(channel(0), channel(1), channel(2), channel(3));
+    }
+}
+
+/// Decode an ASTC block of the given block size into its pixels, row by row.
+/// Void extent blocks and single partition blocks with low dynamic range
+/// endpoints decode as the format specifies. Blocks with several partitions
+/// or high dynamic range endpoints, and invalid blocks, decode as magenta.
+pub(crate) fn decode_astc<const W: u32, const H: u32>(block: &[u8], pixels: &mut [Rgba]) {
+    let bits = u128::from_le_bytes(block.try_into().unwrap());
+    decode_block(bits, W, H, pixels);
+}
+
+/// A weight grid for a single partition block with one plane of weights,
+/// with the ranges of its weights and of its endpoint values.
+#[derive(Copy, Clone, Debug)]
+struct Layout {
+    mode: u32,
+    grid_width: u32,
+    grid_height: u32,
+    weight_levels: u32,
+    color_levels: u32,
+}
+
+/// Every layout of a block whose endpoints take the given number of values,
+/// with at least six levels per endpoint value.
+fn layouts(block_width: u32, block_height: u32, value_count: usize) -> Vec<Layout> {
+    let mut layouts = vec![];
+    for grid_height in 2..=block_height {
+        for grid_width in 2..=block_width {
+            for &weight_levels in WEIGHT_LEVELS.iter() {
+                let mode = match encode_block_mode(grid_width, grid_height, weight_levels) {
+                    Some(mode) => mode,
+                    None => continue,
+                };
+                let weight_bits = ise_bit_count((grid_width * grid_height) as usize, weight_levels);
+                match color_levels(value_count, 128 - 17 - weight_bits) {
+                    Some(color_levels) if color_levels >= 6 => layouts.push(Layout {
+                        mode: mode,
+                        grid_width: grid_width,
+                        grid_height: grid_height,
+                        weight_levels: weight_levels,
+                        color_levels: color_levels,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    layouts
+}
+
+/// The quantized endpoints and weights chosen for a layout, with the
+/// squared error of the colors they reproduce.
+#[derive(Clone, Debug)]
+struct Fit {
+    layout: Layout,
+    colors: Vec<u32>,
+    weights: Vec<u32>,
+    error: u32,
+}
+
+/// Fits endpoints and weights to the pixels of a block for one layout.
+struct Fitter<'a> {
+    pixels: &'a [Rgba],
+    points: &'a [[f32; 4]],
+    channels: usize,
+    layout: Layout,
+    factors: Vec<[(usize, u32); 4]>,
+    color_table: Vec<u8>,
+    weight_table: Vec<u32>,
+}
+
+impl<'a> Fitter<'a> {
+    fn new(pixels: &'a [Rgba], points: &'a [[f32; 4]], channels: usize, layout: Layout, block_width: u32) -> Self {
+        let block_height = pixels.len() as u32 / block_width;
+
+        Fitter {
+            pixels: pixels,
+            points: points,
+            channels: channels,
+            layout: layout,
+            factors: infill_factors(layout.grid_width, layout.grid_height, block_width, block_height),
+            color_table: (0..layout.color_levels).map(|value| unquantize_color(value, layout.color_levels)).collect(),
+            weight_table: (0..layout.weight_levels).map(|value| unquantize_weight(value, layout.weight_levels)).collect(),
+        }
+    }
+
+    /// The endpoints the quantized endpoint values stand for.
+    fn endpoints(&self, colors: &[u32]) -> [[u8; 4]; 2] {
+        let mut endpoints = [[255; 4]; 2];
+        for channel in 0..self.channels {
+            for endpoint in 0..2 {
+                endpoints[endpoint][channel] = self.color_table[colors[2 * channel + endpoint] as usize];
+            }
+        }
+
+        endpoints
+    }
+
+    /// The squared error of the colors the endpoints and grid weights reproduce.
+    fn error(&self, endpoints: [[u8; 4]; 2], weights: &[u32]) -> u32 {
+        let grid: Vec<u32> = weights.iter().map(|&weight| self.weight_table[weight as usize]).collect();
+        let texel_weights = infill(&grid, &self.factors);
+
+        self.pixels.iter().zip(&texel_weights).map(|(pixel, &weight)| {
+            let pixel = [pixel.r, pixel.g, pixel.b, pixel.a];
+            (0..4).map(|channel| {
+                let color = interpolate(endpoints[0][channel], endpoints[1][channel], weight);
+                (color as i32 - pixel[channel] as i32).pow(2) as u32
+            }).sum::<u32>()
+        }).sum()
+    }
+
+    /// Quantize the endpoints, ordered so the decoder does not blue contract
+    /// them, and fit the weights of the grid to the pixels.
+    fn fit(&self, start: [f32; 4], end: [f32; 4]) -> Fit {
+        let nearest = |value: f32| {
+            (0..self.color_table.len()).fold(0, |best, candidate| {
+                let distance = |index: usize| (self.color_table[index] as f32 - value).abs();
+                if distance(candidate) < distance(best) { candidate } else { best }
+            }) as u32
+        };
+        let mut colors = vec![0; 2 * self.channels];
+        for channel in 0..self.channels {
+            colors[2 * channel] = nearest(start[channel]);
+            colors[2 * channel + 1] = nearest(end[channel]);
+        }
+        let mut endpoints = self.endpoints(&colors);
+        let sum = |endpoint: [u8; 4]| endpoint[..3].iter().map(|&value| value as u32).sum::<u32>();
+        if sum(endpoints[1]) < sum(endpoints[0]) {
+            for pair in colors.chunks_mut(2) {
+                pair.swap(0, 1);
+            }
+            endpoints.swap(0, 1);
+        }
+
+        let axis: Vec<f32> = (0..self.channels)
+            .map(|channel| endpoints[1][channel] as f32 - endpoints[0][channel] as f32)
+            .collect();
+        let length = axis.iter().map(|value| value * value).sum::<f32>();
+        let ideal: Vec<f32> = self.points.iter().map(|point| {
+            if length == 0.0 {
+                return 0.0;
+            }
+            let projection = (0..self.channels)
+                .map(|channel| (point[channel] - endpoints[0][channel] as f32) * axis[channel])
+                .sum::<f32>();
+            (64.0 * projection / length).clamp(0.0, 64.0)
+        }).collect();
+
+        let grid_size = (self.layout.grid_width * self.layout.grid_height) as usize;
+        let mut sums = vec![0.0; grid_size];
+        let mut totals = vec![0.0; grid_size];
+        for (factors, &weight) in self.factors.iter().zip(&ideal) {
+            for &(index, factor) in factors.iter().filter(|&&(_, factor)| factor > 0) {
+                sums[index] += factor as f32 * weight;
+                totals[index] += factor as f32;
+            }
+        }
+        let weights: Vec<u32> = sums.iter().zip(&totals).map(|(&sum, &total)| {
+            let target = if total > 0.0 { sum / total } else { 0.0 };
+            (0..self.weight_table.len()).fold(0, |best, candidate| {
+                let distance = |index: usize| (self.weight_table[index] as f32 - target).abs();
+                if distance(candidate) < distance(best) { candidate } else { best }
+            }) as u32
+        }).collect();
+
+        Fit {
+            layout: self.layout,
+            error: self.error(endpoints, &weights),
+            colors: colors,
+            weights: weights,
+        }
+    }
+
+    /// Fit the endpoints along the principal axis, then refine them by least
+    /// squares as long as that lowers the error.
+    fn fit_refined(&self, start: [f32; 4], end: [f32; 4], iterations: usize) -> Fit {
+        let mut best = self.fit(start, end);
+        for _ in 0..iterations {
+            let grid: Vec<u32> = best.weights.iter().map(|&weight| self.weight_table[weight as usize]).collect();
+            let weights: Vec<f32> = infill(&grid, &self.factors).iter().map(|&weight| weight as f32 / 64.0).collect();
+            let (start, end) = match least_squares_endpoints(self.points, &weights) {
+                Some(endpoints) => endpoints,
+                None => break,
+            };
+
+            let candidate = self.fit(start, end);
+            if candidate.error >= best.error {
+                break;
+            }
+            best = candidate;
+        }
+
+        best
+    }
+
+    /// Step each grid weight to the neighbouring weight values, keeping every
+    /// step that lowers the error.
+    fn refine_weights(&self, fit: &mut Fit) {
+        let mut order: Vec<u32> = (0..self.weight_table.len() as u32).collect();
+        order.sort_by_key(|&value| self.weight_table[value as usize]);
+        let endpoints = self.endpoints(&fit.colors);
+
+        for _ in 0..2 {
+            let mut improved = false;
+            for index in 0..fit.weights.len() {
+                let rank = order.iter().position(|&value| value == fit.weights[index]).unwrap();
+                let original = fit.weights[index];
+                for &neighbour in &[rank.wrapping_sub(1), rank + 1] {
+                    if neighbour >= order.len() {
+                        continue;
+                    }
+                    fit.weights[index] = order[neighbour];
+                    let error = self.error(endpoints, &fit.weights);
+                    if error < fit.error {
+                        fit.error = error;
+                        improved = true;
+                        break;
+                    }
+                    fit.weights[index] = original;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+}
+
+/// A void extent block filling the whole block with one color.
+fn void_extent(color: Rgba) -> u128 {
+    let channel = |value: u8| value as u128 * 257;
+
+    0xDFC | (((1 << 52) - 1) << 12) | (channel(color.r) << 64) | (channel(color.g) << 80) |
+        (channel(color.b) << 96) | (channel(color.a) << 112)
+}
+
+/// Encode a block as ASTC with the given block size. Solid blocks become void
+/// extent blocks, and every other block has a single partition with RGB or
+/// RGBA endpoints stored directly. `Fast` tries the densest weight grid for
+/// each weight range, while `Normal` and `Best` try every weight grid that
+/// fits in a block, and `Best` also refines the chosen weights one by one.
+pub(crate) fn encode_astc(
+    pixels: &[Rgba],
+    block_width: u32,
+    block_height: u32,
+    quality: Quality,
+    blocks: &mut Vec<u8>)
+{
+    if pixels.iter().all(|&pixel| pixel == pixels[0]) {
+        blocks.extend_from_slice(&void_extent(pixels[0]).to_le_bytes());
+        return;
+    }
+
+    let opaque = pixels.iter().all(|pixel| pixel.a == 255);
+    let (endpoint_mode, channels) = if opaque { (RGB_DIRECT, 3) } else { (RGBA_DIRECT, 4) };
+    let points: Vec<[f32; 4]> = pixels.iter().cloned().map(to_point).collect();
+    let (start, end) = principal_endpoints(&points);
+
+    let mut layouts = layouts(block_width, block_height, 2 * channels);
+    if quality == Quality::Fast {
+        let all = layouts.clone();
+        let densest = |levels: u32| {
+            all.iter().filter(|layout| layout.weight_levels == levels)
+                .map(|layout| layout.grid_width * layout.grid_height).max()
+        };
+        layouts.retain(|layout| densest(layout.weight_levels) == Some(layout.grid_width * layout.grid_height));
+    }
+    let iterations = match quality {
+        Quality::Fast => 0,
+        Quality::Normal => 1,
+        Quality::Best => 2,
+    };
+
+    let mut best: Option<(Fit, Fitter)> = None;
+    for &layout in layouts.iter() {
+        let fitter = Fitter::new(pixels, &points, channels, layout, block_width);
+        let fit = fitter.fit_refined(start, end, iterations);
+        if best.as_ref().is_none_or(|best| fit.error < best.0.error) {
+            best = Some((fit, fitter));
+        }
+    }
+
+    let (mut fit, fitter) = best.unwrap();
+    if quality == Quality::Best {
+        fitter.refine_weights(&mut fit);
+    }
+
+    let bits = fit.layout.mode as u128 | ((endpoint_mode as u128) << 13) |
+        (ise_encode(&fit.colors, fit.layout.color_levels) << 17) |
+        ise_encode(&fit.weights, fit.layout.weight_levels).reverse_bits();
+    blocks.extend_from_slice(&bits.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const BLOCK_SIZES: [(u32, u32); 14] = [
+        (4, 4), (5, 4), (5, 5), (6, 5), (6, 6), (8, 5), (8, 6), (8, 8), (10, 5), (10, 6), (10, 8), (10, 10),
+        (12, 10), (12, 12),
+    ];
+
+    fn decode(blocks: &[u8], block_width: u32, block_height: u32) -> Vec<Rgba> {
+        let mut pixels = vec![Rgba::default(); (block_width * block_height) as usize];
+        decode_block(u128::from_le_bytes(blocks.try_into().unwrap()), block_width, block_height, &mut pixels);
+        pixels
+    }
+
+    #[test]
+    fn test_integer_sequences_round_trip() {
+        for &levels in COLOR_LEVELS.iter() {
+            for count in 1..12 {
+                let values: Vec<u32> = (0..count).map(|i| (i * 7 + 3) % levels).collect();
+                let stream = ise_encode(&values, levels);
+
+                assert!(stream >> ise_bit_count(count as usize, levels) == 0);
+                assert_eq!(ise_decode(stream, count as usize, levels), values, "{} levels", levels);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unquantized_ranges_span_the_full_scale() {
+        // Blocks never quantize endpoint values to fewer than six levels.
+        for &levels in COLOR_LEVELS[4..].iter() {
+            let mut colors: Vec<u8> = (0..levels).map(|value| unquantize_color(value, levels)).collect();
+            colors.sort();
+            colors.dedup();
+            assert_eq!(colors.len() as u32, levels);
+            assert_eq!((colors[0], colors[colors.len() - 1]), (0, 255));
+        }
+        for &levels in WEIGHT_LEVELS.iter() {
+            let mut weights: Vec<u32> = (0..levels).map(|value| unquantize_weight(value, levels)).collect();
+            weights.sort();
+            weights.dedup();
+            assert_eq!(weights.len() as u32, levels);
+            assert_eq!((weights[0], weights[weights.len() - 1]), (0, 64));
+        }
+    }
+
+    #[test]
+    fn test_block_modes_round_trip() {
+        for grid_height in 2..=12 {
+            for grid_width in 2..=12 {
+                for &levels in WEIGHT_LEVELS.iter() {
+                    if let Some(mode) = encode_block_mode(grid_width, grid_height, levels) {
+                        let decoded = decode_block_mode(mode).unwrap();
+                        assert_eq!(decoded, BlockMode {
+                            grid_width: grid_width,
+                            grid_height: grid_height,
+                            weight_levels: levels,
+                            dual_plane: false,
+                        });
+                    }
+                }
+            }
+        }
+        assert!(layouts(4, 4, 8).iter().any(|layout| (layout.grid_width, layout.grid_height) == (4, 4)));
+    }
+
+    #[test]
+    fn test_solid_blocks_encode_exactly() {
+        let pixels = vec![Rgba::new(12, 34, 56, 78); 36];
+        let mut blocks = vec![];
+        encode_astc(&pixels, 6, 6, Quality::Fast, &mut blocks);
+
+        assert_eq!(decode(&blocks, 6, 6), pixels);
+    }
+
+    #[test]
+    fn test_every_block_size_encodes_a_gradient_closely() {
+        for &(block_width, block_height) in BLOCK_SIZES.iter() {
+            let pixels: Vec<Rgba> = (0..block_height)
+                .flat_map(|y| (0..block_width).map(move |x| {
+                    let t = (x + y) * 255 / (block_width + block_height - 2);
+                    Rgba::new(t as u8, (255 - t) as u8, 200, (255 - t / 2) as u8)
+                }))
+                .collect();
+            for &quality in &[Quality::Fast, Quality::Normal, Quality::Best] {
+                let mut blocks = vec![];
+                encode_astc(&pixels, block_width, block_height, quality, &mut blocks);
+                let decoded = decode(&blocks, block_width, block_height);
+
+                let tolerance = if quality == Quality::Fast { 32 } else { 24 };
+                for (pixel, color) in pixels.iter().zip(&decoded) {
+                    let close = |a: u8, b: u8| (a as i32 - b as i32).abs() <= tolerance;
+                    assert!(
+                        close(pixel.r, color.r) && close(pixel.g, color.g) && close(pixel.b, color.b) &&
+                            close(pixel.a, color.a),
+                        "{}x{} {:?}: {:?} {:?}", block_width, block_height, quality, pixel, color
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_best_quality_is_no_worse_than_fast() {
+        let pixels: Vec<Rgba> = (0..64).map(|i| Rgba::new((i * 37 % 256) as u8, (i * 4) as u8, 90, 255)).collect();
+        let error = |quality| {
+            let mut blocks = vec![];
+            encode_astc(&pixels, 8, 8, quality, &mut blocks);
+            decode(&blocks, 8, 8).iter().zip(&pixels)
+                .map(|(a, b)| (a.r as i32 - b.r as i32).pow(2) + (a.g as i32 - b.g as i32).pow(2))
+                .sum::<i32>()
+        };
+
+        assert!(error(Quality::Best) <= error(Quality::Fast));
+    }
+
+    #[test]
+    fn test_unsupported_blocks_decode_as_magenta() {
+        let mut pixels = vec![Rgba::default(); 16];
+        let block = 0x42u128 | (1 << 11);
+        decode_block(block, 4, 4, &mut pixels);
+        assert!(pixels.iter().all(|&pixel| pixel == ERROR_COLOR));
+
+        decode_block(0, 4, 4, &mut pixels);
+        assert!(pixels.iter().all(|&pixel| pixel == ERROR_COLOR));
+    }
+}
@@ -0,0 +1,538 @@
+use super::{least_squares_endpoints, principal_endpoints, Quality};
+use crate::Rgba;
+
+
+/// Expand a color stored with five bits of red, six bits of green and five
+/// bits of blue to eight bits per channel.
+pub(crate) fn unpack_565(color: u16) -> [u8; 3] {
+    let red = ((color >> 11) & 0x1F) as u8;
+    let green = ((color >> 5) & 0x3F) as u8;
+    let blue = (color & 0x1F) as u8;
+
+    [(red << 3) | (red >> 2), (green << 2) | (green >> 4), (blue << 3) | (blue >> 2)]
+}
+
+/// Round a color to five bits of red, six bits of green and five bits of blue.
+fn pack_565(color: [f32; 3]) -> u16 {
+    let quantize = |value: f32, max: f32| (value.clamp(0.0, 255.0) * max / 255.0 + 0.5) as u16;
+
+    (quantize(color[0], 31.0) << 11) | (quantize(color[1], 63.0) << 5) | quantize(color[2], 31.0)
+}
+
+/// The colors a BC1 color block interpolates between its endpoints. A block
+/// holds four opaque colors, or three opaque colors and transparent black.
+pub(crate) fn bc1_palette(color0: u16, color1: u16, four_colors: bool) -> [Rgba; 4] {
+    let start = unpack_565(color0);
+    let end = unpack_565(color1);
+    let mix = |start_weight: u32, end_weight: u32| {
+        let total = start_weight + end_weight;
+        let channel = |channel: usize| {
+            ((start[channel] as u32 * start_weight + end[channel] as u32 * end_weight) / total) as u8
+        };
+        Rgba::new(channel(0), channel(1), channel(2), 255)
+    };
+
+    if four_colors {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), Rgba::new(0, 0, 0, 0)]
+    }
+}
+
+/// The values a BC4 block interpolates between its endpoints. A block holds
+/// eight values when its first endpoint is greater than its second, and
+/// otherwise six values along with zero and 255.
+pub(crate) fn bc4_palette(value0: u8, value1: u8) -> [u8; 8] {
+    let (start, end) = (value0 as u32, value1 as u32);
+    let mut palette = [value0, value1, 0, 0, 0, 0, 0, 255];
+    if value0 > value1 {
+        for step in 1..7 {
+            palette[step as usize + 1] = (((7 - step) * start + step * end) / 7) as u8;
+        }
+    } else {
+        for step in 1..5 {
+            palette[step as usize + 1] = (((5 - step) * start + step * end) / 5) as u8;
+        }
+    }
+
+    palette
+}
+
+/// Whether a color block interpolates four colors, or three colors and
+/// transparent black.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ColorMode {
+    Four,
+    Three,
+}
+
+impl ColorMode {
+    /// The weight of the second endpoint in each color of the palette.
+    fn weights(self) -> &'static [f32] {
+        match self {
+            ColorMode::Four => &[0.0, 1.0, 1.0 / 3.0, 2.0 / 3.0],
+            ColorMode::Three => &[0.0, 1.0, 0.5],
+        }
+    }
+}
+
+/// The endpoints and indices chosen for a color block, with the squared
+/// error of the colors they reproduce.
+#[derive(Copy, Clone, Debug)]
+struct ColorFit {
+    color0: u16,
+    color1: u16,
+    indices: [u8; 16],
+    error: u32,
+}
+
+fn squared_error(pixel: Rgba, color: Rgba) -> u32 {
+    let difference = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+
+    difference(pixel.r, color.r) + difference(pixel.g, color.g) + difference(pixel.b, color.b)
+}
+
+/// Choose the nearest palette color for every pixel. Transparent pixels take
+/// the transparent black of the three color mode.
+fn fit_colors(
+    pixels: &[Rgba],
+    transparent: &[bool],
+    color0: u16,
+    color1: u16,
+    mode: ColorMode) -> ColorFit
+{
+    let palette = bc1_palette(color0, color1, mode == ColorMode::Four);
+    let colors = mode.weights().len();
+    let mut indices = [0; 16];
+    let mut error = 0;
+    for (i, (&pixel, &transparent)) in pixels.iter().zip(transparent).enumerate() {
+        if transparent {
+            indices[i] = 3;
+            continue;
+        }
+
+        let (index, pixel_error) = (0..colors)
+            .map(|index| (index, squared_error(pixel, palette[index])))
+            .fold((0, u32::MAX), |best, next| if next.1 < best.1 { next } else { best });
+        indices[i] = index as u8;
+        error += pixel_error;
+    }
+
+    ColorFit {
+        color0: color0,
+        color1: color1,
+        indices: indices,
+        error: error,
+    }
+}
+
+/// Nudge each channel of each endpoint by one step while that lowers the error.
+fn perturb_colors(pixels: &[Rgba], transparent: &[bool], mut best: ColorFit, mode: ColorMode) -> ColorFit {
+    const CHANNELS: [(u32, u16); 3] = [(11, 0x1F), (5, 0x3F), (0, 0x1F)];
+
+    for _ in 0..16 {
+        let mut improved = false;
+        for endpoint in 0..2 {
+            for &(shift, mask) in CHANNELS.iter() {
+                for &step in &[-1i32, 1] {
+                    let color = if endpoint == 0 { best.color0 } else { best.color1 };
+                    let value = ((color >> shift) & mask) as i32 + step;
+                    if value < 0 || value > mask as i32 {
+                        continue;
+                    }
+
+                    let color = (color & !(mask << shift)) | ((value as u16) << shift);
+                    let (color0, color1) = if endpoint == 0 { (color, best.color1) } else { (best.color0, color) };
+                    let candidate = fit_colors(pixels, transparent, color0, color1, mode);
+                    if candidate.error < best.error {
+                        best = candidate;
+                        improved = true;
+                    }
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    best
+}
+
+/// Fit a color block in the given mode, refining the endpoints by least
+/// squares as long as that lowers the error.
+fn fit_color_mode(
+    pixels: &[Rgba],
+    transparent: &[bool],
+    start: [f32; 3],
+    end: [f32; 3],
+    mode: ColorMode,
+    quality: Quality) -> ColorFit
+{
+    let mut best = fit_colors(pixels, transparent, pack_565(start), pack_565(end), mode);
+    let iterations = match quality {
+        Quality::Fast => 0,
+        Quality::Normal => 2,
+        Quality::Best => 8,
+    };
+
+    let points: Vec<[f32; 3]> = pixels.iter().map(|pixel| [pixel.r as f32, pixel.g as f32, pixel.b as f32]).collect();
+    for _ in 0..iterations {
+        let (points, weights): (Vec<[f32; 3]>, Vec<f32>) = points.iter()
+            .zip(best.indices.iter().zip(transparent))
+            .filter(|&(_, (_, &transparent))| !transparent)
+            .map(|(&point, (&index, _))| (point, mode.weights()[index as usize]))
+            .unzip();
+        let (start, end) = match least_squares_endpoints(&points, &weights) {
+            Some(endpoints) => endpoints,
+            None => break,
+        };
+
+        let candidate = fit_colors(pixels, transparent, pack_565(start), pack_565(end), mode);
+        if candidate.error >= best.error {
+            break;
+        }
+        best = candidate;
+    }
+
+    if quality == Quality::Best {
+        best = perturb_colors(pixels, transparent, best, mode);
+    }
+
+    best
+}
+
+/// Write the endpoints and indices of a color block, ordering the endpoints
+/// so the decoder reads the block in the mode it was fit in.
+fn pack_color_block(fit: ColorFit, mode: ColorMode) -> [u8; 8] {
+    let ColorFit { mut color0, mut color1, mut indices, .. } = fit;
+    let swap = match mode {
+        ColorMode::Four => color0 < color1,
+        ColorMode::Three => color0 > color1,
+    };
+    if swap {
+        std::mem::swap(&mut color0, &mut color1);
+        for index in indices.iter_mut() {
+            if mode == ColorMode::Four || *index < 2 {
+                *index ^= 1;
+            }
+        }
+    }
+
+    let bits = indices.iter().enumerate().fold(0u32, |bits, (i, &index)| bits | ((index as u32) << (2 * i)));
+    let mut block = [0; 8];
+    block[0..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&color1.to_le_bytes());
+    block[4..8].copy_from_slice(&bits.to_le_bytes());
+
+    block
+}
+
+/// Encode the colors of a block as a BC1 color block. With `alpha`, pixels
+/// whose alpha is below one half become transparent black, which the
+/// three color mode provides. Without it the block must be readable in four
+/// color mode, as the color block of BC3 always is.
+fn encode_color_block(pixels: &[Rgba], quality: Quality, alpha: bool) -> [u8; 8] {
+    let transparent: Vec<bool> = pixels.iter().map(|pixel| alpha && pixel.a < 128).collect();
+    let colors: Vec<[f32; 3]> = pixels.iter()
+        .zip(&transparent)
+        .filter(|&(_, &transparent)| !transparent)
+        .map(|(pixel, _)| [pixel.r as f32, pixel.g as f32, pixel.b as f32])
+        .collect();
+    if colors.is_empty() {
+        return pack_color_block(ColorFit { color0: 0, color1: 0, indices: [3; 16], error: 0 }, ColorMode::Three);
+    }
+
+    let (start, end) = principal_endpoints(&colors);
+    let modes: &[ColorMode] = if colors.len() < pixels.len() {
+        &[ColorMode::Three]
+    } else if alpha && quality == Quality::Best {
+        &[ColorMode::Four, ColorMode::Three]
+    } else {
+        &[ColorMode::Four]
+    };
+
+    let (fit, mode) = modes.iter()
+        .map(|&mode| (fit_color_mode(pixels, &transparent, start, end, mode, quality), mode))
+        .fold(None, |best: Option<(ColorFit, ColorMode)>, next| match best {
+            Some(best) if best.0.error <= next.0.error => Some(best),
+            _ => Some(next),
+        })
+        .unwrap();
+
+    pack_color_block(fit, mode)
+}
+
+/// Choose the nearest palette value for every value, and write the block.
+fn fit_values(values: &[u8], value0: u8, value1: u8) -> ([u8; 8], u32) {
+    let palette = bc4_palette(value0, value1);
+    let mut bits = 0u64;
+    let mut error = 0;
+    for (i, &value) in values.iter().enumerate() {
+        let (index, value_error) = palette.iter()
+            .map(|&entry| (entry as i32 - value as i32).pow(2) as u32)
+            .enumerate()
+            .fold((0, u32::MAX), |best, next| if next.1 < best.1 { next } else { best });
+        bits |= (index as u64) << (3 * i);
+        error += value_error;
+    }
+
+    let mut block = [0; 8];
+    block[0] = value0;
+    block[1] = value1;
+    block[2..8].copy_from_slice(&bits.to_le_bytes()[0..6]);
+
+    (block, error)
+}
+
+/// Encode single channel values as a BC4 block.
+fn encode_value_block(values: &[u8], quality: Quality) -> [u8; 8] {
+    let low = *values.iter().min().unwrap();
+    let high = *values.iter().max().unwrap();
+    let mut best = fit_values(values, high, low);
+    if quality == Quality::Fast {
+        return best.0;
+    }
+
+    // The six value mode spends its endpoints on the values strictly between
+    // zero and 255, and represents the extremes exactly.
+    let inner = values.iter().cloned().filter(|&value| value != 0 && value != 255);
+    if let (Some(inner_low), Some(inner_high)) = (inner.clone().min(), inner.max()) {
+        let candidate = fit_values(values, inner_low, inner_high);
+        if candidate.1 < best.1 {
+            best = candidate;
+        }
+    }
+
+    if quality == Quality::Best {
+        let (value0, value1) = (best.0[0] as i32, best.0[1] as i32);
+        for step0 in -4..5 {
+            for step1 in -4..5 {
+                let (candidate0, candidate1) = (value0 + step0, value1 + step1);
+                if !(0..=255).contains(&candidate0) || !(0..=255).contains(&candidate1) ||
+                    (candidate0 > candidate1) != (value0 > value1) {
+                    continue;
+                }
+
+                let candidate = fit_values(values, candidate0 as u8, candidate1 as u8);
+                if candidate.1 < best.1 {
+                    best = candidate;
+                }
+            }
+        }
+    }
+
+    best.0
+}
+
+pub(crate) fn encode_bc1(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    blocks.extend_from_slice(&encode_color_block(pixels, quality, true));
+}
+
+pub(crate) fn encode_bc3(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let alpha: Vec<u8> = pixels.iter().map(|pixel| pixel.a).collect();
+    blocks.extend_from_slice(&encode_value_block(&alpha, quality));
+    blocks.extend_from_slice(&encode_color_block(pixels, quality, false));
+}
+
+pub(crate) fn encode_bc4(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let red: Vec<u8> = pixels.iter().map(|pixel| pixel.r).collect();
+    blocks.extend_from_slice(&encode_value_block(&red, quality));
+}
+
+pub(crate) fn encode_bc5(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let red: Vec<u8> = pixels.iter().map(|pixel| pixel.r).collect();
+    let green: Vec<u8> = pixels.iter().map(|pixel| pixel.g).collect();
+    blocks.extend_from_slice(&encode_value_block(&red, quality));
+    blocks.extend_from_slice(&encode_value_block(&green, quality));
+}
+
+/// Decode a BC1 color block. Blocks of the formats with a separate alpha
+/// block always hold four colors, whatever the order of their endpoints.
+fn decode_color_block(block: &[u8], alpha: bool) -> [Rgba; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let bits = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let palette = bc1_palette(color0, color1, !alpha || color0 > color1);
+
+    let mut pixels = [Rgba::default(); 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = palette[((bits >> (2 * i)) & 0x03) as usize];
+    }
+
+    pixels
+}
+
+/// Decode a BC4 value block.
+fn decode_value_block(block: &[u8]) -> [u8; 16] {
+    let palette = bc4_palette(block[0], block[1]);
+    let mut bits = [0; 8];
+    bits[0..6].copy_from_slice(&block[2..8]);
+    let bits = u64::from_le_bytes(bits);
+
+    let mut values = [0; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[((bits >> (3 * i)) & 0x07) as usize];
+    }
+
+    values
+}
+
+pub(crate) fn decode_bc1(block: &[u8], pixels: &mut [Rgba]) {
+    pixels.copy_from_slice(&decode_color_block(block, true));
+}
+
+pub(crate) fn decode_bc2(block: &[u8], pixels: &mut [Rgba]) {
+    let mut alpha = [0; 8];
+    alpha.copy_from_slice(&block[0..8]);
+    let alpha = u64::from_le_bytes(alpha);
+    pixels.copy_from_slice(&decode_color_block(&block[8..16], false));
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        pixel.a = ((alpha >> (4 * i)) & 0x0F) as u8 * 17;
+    }
+}
+
+pub(crate) fn decode_bc3(block: &[u8], pixels: &mut [Rgba]) {
+    let alpha = decode_value_block(&block[0..8]);
+    pixels.copy_from_slice(&decode_color_block(&block[8..16], false));
+    for (pixel, &alpha) in pixels.iter_mut().zip(&alpha) {
+        pixel.a = alpha;
+    }
+}
+
+pub(crate) fn decode_bc4(block: &[u8], pixels: &mut [Rgba]) {
+    let red = decode_value_block(block);
+    for (pixel, &red) in pixels.iter_mut().zip(&red) {
+        *pixel = Rgba::new(red, 0, 0, 255);
+    }
+}
+
+pub(crate) fn decode_bc5(block: &[u8], pixels: &mut [Rgba]) {
+    let red = decode_value_block(&block[0..8]);
+    let green = decode_value_block(&block[8..16]);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = Rgba::new(red[i], green[i], 0, 255);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn gradient_block() -> Vec<Rgba> {
+        (0..16).map(|i| Rgba::new(20 + 12 * i, 200 - 10 * i, 64 + 4 * i, 255)).collect()
+    }
+
+    fn total_error(pixels: &[Rgba], decoded: &[Rgba]) -> u32 {
+        pixels.iter().zip(decoded).map(|(&pixel, &color)| squared_error(pixel, color)).sum()
+    }
+
+    #[test]
+    fn test_unpack_565_replicates_the_high_bits() {
+        assert_eq!(unpack_565(0xFFFF), [255, 255, 255]);
+        assert_eq!(unpack_565(0x0000), [0, 0, 0]);
+        assert_eq!(unpack_565(pack_565([255.0, 128.0, 8.0])), [255, 130, 8]);
+    }
+
+    #[test]
+    fn test_bc1_encodes_a_solid_block_exactly() {
+        let pixels = vec![Rgba::new(0xFF, 0x00, 0x00, 0xFF); 16];
+        for &quality in &[Quality::Fast, Quality::Normal, Quality::Best] {
+            let mut blocks = vec![];
+            encode_bc1(&pixels, quality, &mut blocks);
+
+            assert_eq!(decode_color_block(&blocks, true).to_vec(), pixels);
+        }
+    }
+
+    #[test]
+    fn test_bc1_higher_quality_lowers_the_error() {
+        let pixels = gradient_block();
+        let errors: Vec<u32> = [Quality::Fast, Quality::Normal, Quality::Best].iter().map(|&quality| {
+            let mut blocks = vec![];
+            encode_bc1(&pixels, quality, &mut blocks);
+            total_error(&pixels, &decode_color_block(&blocks, true))
+        }).collect();
+
+        assert!(errors[1] <= errors[0], "{:?}", errors);
+        assert!(errors[2] <= errors[1], "{:?}", errors);
+        assert!(errors[2] < 16 * 400, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_bc1_uses_the_three_color_mode_for_transparent_pixels() {
+        let mut pixels = gradient_block();
+        pixels[5].a = 0;
+        pixels[9].a = 100;
+        let mut blocks = vec![];
+        encode_bc1(&pixels, Quality::Normal, &mut blocks);
+        let decoded = decode_color_block(&blocks, true);
+
+        assert!(u16::from_le_bytes([blocks[0], blocks[1]]) <= u16::from_le_bytes([blocks[2], blocks[3]]));
+        for (i, color) in decoded.iter().enumerate() {
+            assert_eq!(color.a == 0, i == 5 || i == 9);
+        }
+    }
+
+    #[test]
+    fn test_bc3_color_block_is_always_read_as_four_colors() {
+        let pixels = gradient_block();
+        let mut blocks = vec![];
+        encode_bc3(&pixels, Quality::Best, &mut blocks);
+
+        assert_eq!(blocks.len(), 16);
+        assert!(u16::from_le_bytes([blocks[8], blocks[9]]) >= u16::from_le_bytes([blocks[10], blocks[11]]));
+        assert_eq!(decode_value_block(&blocks[0..8]), [255; 16]);
+    }
+
+    #[test]
+    fn test_bc2_decodes_a_reference_block() {
+        // Explicit alpha rising by one step per pixel, and a color block whose
+        // first endpoint is the smaller one, which is still read as four
+        // colors, cycling through the palette from blue to red.
+        let block = [
+            0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE,
+            0x1F, 0x00, 0x00, 0xF8, 0xE4, 0xE4, 0xE4, 0xE4,
+        ];
+        let palette = [(0, 0, 255), (255, 0, 0), (85, 0, 170), (170, 0, 85)];
+        let mut decoded = [Rgba::default(); 16];
+        decode_bc2(&block, &mut decoded);
+
+        for (i, pixel) in decoded.iter().enumerate() {
+            let (r, g, b) = palette[i % 4];
+            assert_eq!(*pixel, Rgba::new(r, g, b, 17 * i as u8), "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn test_bc4_represents_extremes_exactly() {
+        let values = [0, 255, 100, 110, 120, 130, 140, 150, 0, 255, 100, 110, 120, 130, 140, 150];
+        let pixels: Vec<Rgba> = values.iter().map(|&value| Rgba::new(value, 0, 0, 255)).collect();
+        let mut blocks = vec![];
+        encode_bc4(&pixels, Quality::Normal, &mut blocks);
+        let decoded = decode_value_block(&blocks);
+
+        assert!(blocks[0] <= blocks[1]);
+        assert_eq!(decoded[0], 0);
+        assert_eq!(decoded[1], 255);
+        for (&value, &result) in values.iter().zip(&decoded) {
+            assert!((value as i32 - result as i32).abs() <= 3, "{:?}", decoded);
+        }
+    }
+
+    #[test]
+    fn test_bc5_encodes_red_and_green_separately() {
+        let pixels: Vec<Rgba> = (0..16).map(|i| Rgba::new(16 * i as u8, 255 - 16 * i as u8, 7, 255)).collect();
+        let mut blocks = vec![];
+        encode_bc5(&pixels, Quality::Best, &mut blocks);
+        let red = decode_value_block(&blocks[0..8]);
+        let green = decode_value_block(&blocks[8..16]);
+
+        for (i, pixel) in pixels.iter().enumerate() {
+            assert!((pixel.r as i32 - red[i] as i32).abs() <= 18);
+            assert!((pixel.g as i32 - green[i] as i32).abs() <= 18);
+        }
+    }
+}
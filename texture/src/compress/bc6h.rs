@@ -0,0 +1,411 @@
+use super::bc7::{read_indices, weights, ANCHORS_2, PARTITIONS_2};
+use super::BitReader;
+use crate::Rgba;
+
+
+// The endpoint values a block stores. The first endpoint of the first region
+// is stored whole, and blocks of the transformed modes store the other three
+// endpoints as differences from it.
+const RW: usize = 0;
+const GW: usize = 1;
+const BW: usize = 2;
+const RX: usize = 3;
+const GX: usize = 4;
+const BX: usize = 5;
+const RY: usize = 6;
+const GY: usize = 7;
+const BY: usize = 8;
+const RZ: usize = 9;
+const GZ: usize = 10;
+const BZ: usize = 11;
+
+/// The layout of the blocks of a mode.
+#[derive(Copy, Clone, Debug)]
+struct Mode {
+    /// The mode bits, two bits long for the first two modes and five bits
+    /// long for the rest.
+    code: u32,
+    regions: usize,
+    transformed: bool,
+    endpoint_bits: u32,
+    /// The number of bits of the red, green and blue differences.
+    delta_bits: [u32; 3],
+    /// The bits of the endpoint values following the mode bits, as the
+    /// endpoint value, its lowest bit and the number of bits.
+    layout: &'static [(usize, u32, u32)],
+}
+
+const MODES: [Mode; 14] = [
+    Mode {
+        code: 0x00, regions: 2, transformed: true, endpoint_bits: 10, delta_bits: [5, 5, 5],
+        layout: &[
+            (GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (GZ, 4, 1),
+            (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5),
+            (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x01, regions: 2, transformed: true, endpoint_bits: 7, delta_bits: [6, 6, 6],
+        layout: &[
+            (GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 7),
+            (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6),
+            (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+        ],
+    },
+    Mode {
+        code: 0x02, regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [5, 4, 4],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4), (GX, 0, 4), (GW, 10, 1),
+            (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1),
+            (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x06, regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [4, 5, 4],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5),
+            (GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 0, 1),
+            (BZ, 2, 1), (RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x0A, regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [4, 4, 5],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1), (GY, 0, 4), (GX, 0, 4),
+            (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 1, 1),
+            (BZ, 2, 1), (RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x0E, regions: 2, transformed: true, endpoint_bits: 9, delta_bits: [5, 5, 5],
+        layout: &[
+            (RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1),
+            (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5),
+            (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x12, regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [6, 5, 5],
+        layout: &[
+            (RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 3, 1),
+            (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+            (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+        ],
+    },
+    Mode {
+        code: 0x16, regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [5, 6, 5],
+        layout: &[
+            (RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8), (GZ, 5, 1),
+            (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+            (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x1A, regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [5, 5, 6],
+        layout: &[
+            (RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 5, 1),
+            (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 6),
+            (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        code: 0x1E, regions: 2, transformed: false, endpoint_bits: 6, delta_bits: [6, 6, 6],
+        layout: &[
+            (RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1), (BY, 5, 1),
+            (BZ, 2, 1), (GY, 4, 1), (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6),
+            (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+        ],
+    },
+    Mode {
+        code: 0x03, regions: 1, transformed: false, endpoint_bits: 10, delta_bits: [10, 10, 10],
+        layout: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10)],
+    },
+    Mode {
+        code: 0x07, regions: 1, transformed: true, endpoint_bits: 11, delta_bits: [9, 9, 9],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9), (GW, 10, 1), (BX, 0, 9),
+            (BW, 10, 1),
+        ],
+    },
+    // The highest bits of the first endpoint of the last two modes are
+    // stored in reverse order.
+    Mode {
+        code: 0x0B, regions: 1, transformed: true, endpoint_bits: 12, delta_bits: [8, 8, 8],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 11, 1), (RW, 10, 1), (GX, 0, 8), (GW, 11, 1),
+            (GW, 10, 1), (BX, 0, 8), (BW, 11, 1), (BW, 10, 1),
+        ],
+    },
+    Mode {
+        code: 0x0F, regions: 1, transformed: true, endpoint_bits: 16, delta_bits: [4, 4, 4],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 15, 1), (RW, 14, 1), (RW, 13, 1), (RW, 12, 1),
+            (RW, 11, 1), (RW, 10, 1), (GX, 0, 4), (GW, 15, 1), (GW, 14, 1), (GW, 13, 1), (GW, 12, 1), (GW, 11, 1),
+            (GW, 10, 1), (BX, 0, 4), (BW, 15, 1), (BW, 14, 1), (BW, 13, 1), (BW, 12, 1), (BW, 11, 1), (BW, 10, 1),
+        ],
+    },
+];
+
+/// Widen a half precision float to single precision.
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1F) as i32;
+    let mantissa = (half & 0x3FF) as f32;
+
+    sign * match exponent {
+        0 => mantissa * 2.0f32.powi(-24),
+        31 => if mantissa == 0.0 { f32::INFINITY } else { f32::NAN },
+        _ => (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    }
+}
+
+/// Extend the sign bit of a value with the given number of bits.
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+
+    (value << shift) >> shift
+}
+
+/// Widen an endpoint value with the given number of bits to sixteen bits, or
+/// fifteen bits and a sign.
+fn unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        if bits >= 15 || value == 0 {
+            value
+        } else if value == (1 << bits) - 1 {
+            0xFFFF
+        } else {
+            ((value << 16) + 0x8000) >> bits
+        }
+    } else if bits >= 16 || value == 0 {
+        value
+    } else {
+        let magnitude = value.abs();
+        let unquantized = if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+
+        if value < 0 { -unquantized } else { unquantized }
+    }
+}
+
+/// Scale an interpolated value to the bits of a half precision float.
+fn finish_unquantize(value: i32, signed: bool) -> u16 {
+    if !signed {
+        ((value * 31) >> 6) as u16
+    } else if value < 0 {
+        0x8000 | (((-value * 31) >> 5) as u16)
+    } else {
+        ((value * 31) >> 5) as u16
+    }
+}
+
+/// Clamp a half precision float to the range from zero to one, and scale it
+/// to eight bits.
+fn half_to_u8(half: u16) -> u8 {
+    let value = half_to_f32(half);
+    if value.is_nan() {
+        return 0;
+    }
+
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+/// Interpolate between two unquantized endpoint values with a weight out of 64.
+#[inline]
+fn interpolate(start: i32, end: i32, weight: u32) -> i32 {
+    ((64 - weight as i32) * start + weight as i32 * end + 32) >> 6
+}
+
+/// Decode a BC6H block in any of its fourteen modes, clamping the colors to
+/// the range an eight bit channel holds. Blocks of the reserved modes
+/// decode to opaque black.
+pub(crate) fn decode_bc6h(block: &[u8], pixels: &mut [Rgba], signed: bool) {
+    let mut bits = BitReader::new(block);
+    let mut code = bits.read(2);
+    if code >= 2 {
+        code |= bits.read(3) << 2;
+    }
+    let mode = match MODES.iter().find(|mode| mode.code == code) {
+        Some(mode) => mode,
+        None => {
+            for pixel in pixels.iter_mut() {
+                *pixel = Rgba::new(0, 0, 0, 255);
+            }
+            return;
+        }
+    };
+
+    let mut values = [0; 12];
+    for &(value, low, count) in mode.layout {
+        values[value] |= (bits.read(count) as i32) << low;
+    }
+    let partition = if mode.regions == 2 { bits.read(5) as usize } else { 0 };
+
+    let endpoint_count = 2 * mode.regions;
+    let mask = (1 << mode.endpoint_bits) - 1;
+    for channel in 0..3 {
+        if signed {
+            values[channel] = sign_extend(values[channel], mode.endpoint_bits);
+        }
+        for endpoint in 1..endpoint_count {
+            let mut value = values[3 * endpoint + channel];
+            if mode.transformed || signed {
+                value = sign_extend(value, mode.delta_bits[channel]);
+            }
+            if mode.transformed {
+                value = (values[channel] + value) & mask;
+                if signed {
+                    value = sign_extend(value, mode.endpoint_bits);
+                }
+            }
+            values[3 * endpoint + channel] = value;
+        }
+    }
+    for value in values[..(3 * endpoint_count)].iter_mut() {
+        *value = unquantize(*value, mode.endpoint_bits, signed);
+    }
+
+    let (index_bits, anchors, regions): (u32, &[usize], &[u8; 16]) = if mode.regions == 2 {
+        (3, &ANCHORS_2[partition..(partition + 1)], &PARTITIONS_2[partition])
+    } else {
+        (4, &[], &[0; 16])
+    };
+    let indices = read_indices(&mut bits, index_bits, anchors);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let region = regions[i] as usize;
+        let weight = weights(index_bits)[indices[i] as usize];
+        let channel = |channel: usize| {
+            let start = values[6 * region + channel];
+            let end = values[6 * region + 3 + channel];
+            half_to_u8(finish_unquantize(interpolate(start, end, weight), signed))
+        };
+        *pixel = Rgba::new(channel(0), channel(1), channel(2), 255);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress::BitWriter;
+
+
+    fn decode(block: &[u8], signed: bool) -> Vec<Rgba> {
+        let mut pixels = vec![Rgba::default(); 16];
+        decode_bc6h(block, &mut pixels, signed);
+
+        pixels
+    }
+
+    #[test]
+    fn test_layouts_store_every_endpoint_bit_once() {
+        for mode in MODES.iter() {
+            let mode_bits = if mode.code < 2 { 2 } else { 5 };
+            let index_bits = if mode.regions == 2 { 46 + 5 } else { 63 };
+            let mut stored = [0u32; 12];
+            for &(value, low, count) in mode.layout {
+                for bit in low..(low + count) {
+                    assert_eq!(stored[value] & (1 << bit), 0, "mode {:#x}", mode.code);
+                    stored[value] |= 1 << bit;
+                }
+            }
+
+            let total: u32 = mode.layout.iter().map(|&(_, _, count)| count).sum();
+            assert_eq!(mode_bits + total + index_bits, 128, "mode {:#x}", mode.code);
+            for (value, &bits) in stored.iter().enumerate() {
+                let expected = match value {
+                    RW | GW | BW => mode.endpoint_bits,
+                    _ if value >= 3 * 2 * mode.regions => 0,
+                    _ => mode.delta_bits[value % 3],
+                };
+                assert_eq!(bits, (1u32 << expected) - 1, "mode {:#x} value {}", mode.code, value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_half_conversion_widens_every_kind_of_value() {
+        assert_eq!(half_to_f32(0x3C00), 1.0);
+        assert_eq!(half_to_f32(0x3800), 0.5);
+        assert_eq!(half_to_f32(0xC000), -2.0);
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+        assert_eq!(half_to_f32(0x7C00), f32::INFINITY);
+        assert!(half_to_f32(0x7E00).is_nan());
+    }
+
+    /// A block of the single region mode with ten bit endpoints stored whole.
+    /// The first pixel takes the first endpoint and every other pixel the
+    /// second endpoint.
+    fn single_region_block(first: [u32; 3], second: [u32; 3]) -> [u8; 16] {
+        let mut bits = BitWriter::new();
+        bits.write(0x03, 5);
+        for &value in first.iter().chain(second.iter()) {
+            bits.write(value, 10);
+        }
+        bits.write(0, 3);
+        for _ in 1..16 {
+            bits.write(15, 4);
+        }
+
+        bits.into_bytes()
+    }
+
+    #[test]
+    fn test_bc6h_decodes_a_reference_unsigned_block() {
+        // The endpoint values 495 and 462 widen to the half precision floats
+        // 1.0 and just above 0.5.
+        let decoded = decode(&single_region_block([0, 0, 0], [495, 462, 0]), false);
+
+        assert_eq!(decoded[0], Rgba::new(0, 0, 0, 255));
+        assert_eq!(decoded[1..].to_vec(), vec![Rgba::new(255, 128, 0, 255); 15]);
+    }
+
+    #[test]
+    fn test_bc6h_decodes_a_reference_signed_block() {
+        // Signed endpoints store their sign in the highest bit, so 0x3FF is
+        // minus one, which is clamped to zero, and 510 is far above one.
+        let decoded = decode(&single_region_block([0, 0, 0], [0x3FF, 510, 0]), true);
+
+        assert_eq!(decoded[0], Rgba::new(0, 0, 0, 255));
+        assert_eq!(decoded[1..].to_vec(), vec![Rgba::new(0, 255, 0, 255); 15]);
+    }
+
+    #[test]
+    fn test_bc6h_applies_differences_to_the_second_region() {
+        // A block of the first mode whose second region is brighter than its
+        // first, with every index zero.
+        let partition = 13;
+        let mut bits = BitWriter::new();
+        bits.write(0x00, 2);
+        bits.write(0, 3);
+        for _ in 0..3 {
+            bits.write(400, 10);
+        }
+        bits.write(0, 30);
+        bits.write(15, 5);
+        bits.write(0, 1);
+        bits.write(0, 5);
+        bits.write(0, 1);
+        bits.write(partition as u32, 5);
+        bits.write(0, 32);
+        bits.write(0, 14);
+        let decoded = decode(&bits.into_bytes(), false);
+
+        let first = decoded[0];
+        let second = decoded[ANCHORS_2[partition]];
+        assert!(second.r > first.r && second.g == first.g, "{:?} {:?}", first, second);
+        for (i, pixel) in decoded.iter().enumerate() {
+            assert_eq!(*pixel, if PARTITIONS_2[partition][i] == 1 { second } else { first }, "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn test_bc6h_reserved_modes_decode_to_black() {
+        let mut block = [0; 16];
+        block[0] = 0x13;
+
+        assert_eq!(decode(&block, false), vec![Rgba::new(0, 0, 0, 255); 16]);
+    }
+}
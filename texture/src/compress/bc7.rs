@@ -0,0 +1,628 @@
+use super::{least_squares_endpoints, principal_endpoints, to_point, BitReader, BitWriter, Quality};
+use crate::Rgba;
+
+
+/// The weights of the second endpoint, out of 64, of the four colors a block
+/// with two bit indices interpolates.
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+
+/// The weights of the second endpoint, out of 64, of the eight colors a block
+/// with three bit indices interpolates.
+pub(crate) const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+
+/// The weights of the second endpoint, out of 64, of the sixteen colors a
+/// block with four bit indices interpolates.
+pub(crate) const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// The subset of every pixel of the partitions of blocks with two subsets.
+pub(crate) const PARTITIONS_2: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1],
+    [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0],
+    [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0],
+    [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0],
+    [0, 0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 0, 0],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0],
+    [0, 1, 1, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0],
+    [0, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 1, 0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0],
+    [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0],
+    [0, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0, 0, 1],
+    [0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 0, 1, 0, 1],
+    [0, 1, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1, 1, 1, 0, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0, 1, 1, 0],
+    [0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 1, 1],
+    [0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1],
+    [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0],
+    [0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1],
+    [0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1],
+    [0, 1, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1],
+    [0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0],
+    [0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1],
+];
+
+/// The subset of every pixel of the partitions of blocks with three subsets.
+const PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/// The anchor pixel of the second subset of each partition of blocks with two
+/// subsets. The anchor pixel of the first subset is always the first pixel.
+pub(crate) const ANCHORS_2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// The anchor pixels of the second and third subsets of each partition of
+/// blocks with three subsets.
+const ANCHORS_3: [[usize; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+/// How the endpoints of a mode store their parity bits, which become the
+/// lowest bit of every channel of an endpoint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Parity {
+    None,
+    /// Both endpoints of a subset share a parity bit.
+    Shared,
+    /// Every endpoint has a parity bit of its own.
+    Unique,
+}
+
+/// The layout of the blocks of a mode. Modes with separate alpha indices
+/// store the second set of indices after the first.
+#[derive(Copy, Clone, Debug)]
+struct Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    parity: Parity,
+    index_bits: u32,
+    alpha_index_bits: u32,
+}
+
+const MODES: [Mode; 8] = [
+    Mode {
+        subsets: 3, partition_bits: 4, rotation_bits: 0, selection_bits: 0, color_bits: 4, alpha_bits: 0,
+        parity: Parity::Unique, index_bits: 3, alpha_index_bits: 0,
+    },
+    Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 6, alpha_bits: 0,
+        parity: Parity::Shared, index_bits: 3, alpha_index_bits: 0,
+    },
+    Mode {
+        subsets: 3, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 5, alpha_bits: 0,
+        parity: Parity::None, index_bits: 2, alpha_index_bits: 0,
+    },
+    Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 7, alpha_bits: 0,
+        parity: Parity::Unique, index_bits: 2, alpha_index_bits: 0,
+    },
+    Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, selection_bits: 1, color_bits: 5, alpha_bits: 6,
+        parity: Parity::None, index_bits: 2, alpha_index_bits: 3,
+    },
+    Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, selection_bits: 0, color_bits: 7, alpha_bits: 8,
+        parity: Parity::None, index_bits: 2, alpha_index_bits: 2,
+    },
+    Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 0, selection_bits: 0, color_bits: 7, alpha_bits: 7,
+        parity: Parity::Unique, index_bits: 4, alpha_index_bits: 0,
+    },
+    Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, selection_bits: 0, color_bits: 5, alpha_bits: 5,
+        parity: Parity::Unique, index_bits: 2, alpha_index_bits: 0,
+    },
+];
+
+/// The weights of the second endpoint of indices with the given number of bits.
+pub(crate) fn weights(index_bits: u32) -> &'static [u32] {
+    match index_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+/// Interpolate between two channel values with a weight out of 64.
+#[inline]
+pub(crate) fn interpolate(start: u8, end: u8, weight: u32) -> u8 {
+    (((64 - weight) * start as u32 + weight * end as u32 + 32) >> 6) as u8
+}
+
+/// The endpoints and indices chosen for a mode 6 block, with the squared
+/// error of the colors they reproduce. The lowest bit of every endpoint
+/// channel is the endpoint's parity bit.
+#[derive(Copy, Clone, Debug)]
+struct Mode6Fit {
+    endpoints: [[u8; 4]; 2],
+    indices: [u8; 16],
+    error: u32,
+}
+
+/// Round an endpoint to seven bits per channel followed by the parity bit.
+fn quantize_endpoint(endpoint: [f32; 4], parity: u8) -> [u8; 4] {
+    let mut quantized = [0; 4];
+    for (channel, &value) in quantized.iter_mut().zip(&endpoint) {
+        let high = ((value - parity as f32) / 2.0).round().clamp(0.0, 127.0) as u8;
+        *channel = (high << 1) | parity;
+    }
+
+    quantized
+}
+
+/// The parity bit whose quantized endpoint lies nearest to the endpoint.
+fn nearest_parity(endpoint: [f32; 4]) -> u8 {
+    let error = |parity: u8| {
+        quantize_endpoint(endpoint, parity).iter().zip(&endpoint)
+            .map(|(&quantized, &value)| (quantized as f32 - value).powi(2))
+            .sum::<f32>()
+    };
+
+    if error(1) < error(0) { 1 } else { 0 }
+}
+
+/// Choose the nearest palette color for every pixel.
+fn fit_mode6(pixels: &[Rgba], endpoints: [[u8; 4]; 2]) -> Mode6Fit {
+    let mut palette = [[0; 4]; 16];
+    for (color, &weight) in palette.iter_mut().zip(WEIGHTS_4.iter()) {
+        for channel in 0..4 {
+            color[channel] = interpolate(endpoints[0][channel], endpoints[1][channel], weight);
+        }
+    }
+
+    let mut indices = [0; 16];
+    let mut error = 0;
+    for (index, &pixel) in indices.iter_mut().zip(pixels) {
+        let pixel = [pixel.r, pixel.g, pixel.b, pixel.a];
+        let (best, best_error) = palette.iter()
+            .map(|color| {
+                color.iter().zip(&pixel).map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u32).sum::<u32>()
+            })
+            .enumerate()
+            .fold((0, u32::MAX), |best, next| if next.1 < best.1 { next } else { best });
+        *index = best as u8;
+        error += best_error;
+    }
+
+    Mode6Fit {
+        endpoints: endpoints,
+        indices: indices,
+        error: error,
+    }
+}
+
+/// Fit a mode 6 block with the given parity bits, refining the endpoints by
+/// least squares as long as that lowers the error.
+fn fit_with_parity(
+    pixels: &[Rgba],
+    start: [f32; 4],
+    end: [f32; 4],
+    parity: [u8; 2],
+    iterations: usize) -> Mode6Fit
+{
+    let points: Vec<[f32; 4]> = pixels.iter().cloned().map(to_point).collect();
+    let mut best = fit_mode6(pixels, [quantize_endpoint(start, parity[0]), quantize_endpoint(end, parity[1])]);
+    for _ in 0..iterations {
+        let weights: Vec<f32> = best.indices.iter().map(|&index| WEIGHTS_4[index as usize] as f32 / 64.0).collect();
+        let (start, end) = match least_squares_endpoints(&points, &weights) {
+            Some(endpoints) => endpoints,
+            None => break,
+        };
+
+        let candidate = fit_mode6(pixels, [quantize_endpoint(start, parity[0]), quantize_endpoint(end, parity[1])]);
+        if candidate.error >= best.error {
+            break;
+        }
+        best = candidate;
+    }
+
+    best
+}
+
+/// Write a mode 6 block. The first index is stored without its highest bit,
+/// so the endpoints are swapped when that bit would be set.
+fn pack_mode6(fit: Mode6Fit) -> [u8; 16] {
+    let Mode6Fit { mut endpoints, mut indices, .. } = fit;
+    if indices[0] >= 8 {
+        endpoints.swap(0, 1);
+        for index in indices.iter_mut() {
+            *index = 15 - *index;
+        }
+    }
+
+    let mut bits = BitWriter::new();
+    bits.write(1 << 6, 7);
+    for channel in 0..4 {
+        for endpoint in endpoints.iter() {
+            bits.write((endpoint[channel] >> 1) as u32, 7);
+        }
+    }
+    for endpoint in endpoints.iter() {
+        bits.write((endpoint[0] & 1) as u32, 1);
+    }
+    for (i, &index) in indices.iter().enumerate() {
+        bits.write(index as u32, if i == 0 { 3 } else { 4 });
+    }
+
+    bits.into_bytes()
+}
+
+/// Encode a block as BC7. Every block is encoded in mode 6, which spans all
+/// four channels with a single pair of endpoints and four bit indices, and
+/// suits both opaque and transparent texture images.
+pub(crate) fn encode_bc7(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let points: Vec<[f32; 4]> = pixels.iter().cloned().map(to_point).collect();
+    let (start, end) = principal_endpoints(&points);
+
+    let fit = match quality {
+        Quality::Fast => fit_with_parity(pixels, start, end, [nearest_parity(start), nearest_parity(end)], 0),
+        Quality::Normal => fit_with_parity(pixels, start, end, [nearest_parity(start), nearest_parity(end)], 2),
+        Quality::Best => [[0, 0], [0, 1], [1, 0], [1, 1]].iter()
+            .map(|&parity| fit_with_parity(pixels, start, end, parity, 8))
+            .fold(None, |best: Option<Mode6Fit>, next| match best {
+                Some(best) if best.error <= next.error => Some(best),
+                _ => Some(next),
+            })
+            .unwrap(),
+    };
+
+    blocks.extend_from_slice(&pack_mode6(fit));
+}
+
+/// Read the indices of every pixel. Anchor pixels are stored without their
+/// highest bit, which is always zero.
+pub(crate) fn read_indices(bits: &mut BitReader, index_bits: u32, anchors: &[usize]) -> [u8; 16] {
+    let mut indices = [0; 16];
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        let anchor = pixel == 0 || anchors.contains(&pixel);
+        *index = bits.read(if anchor { index_bits - 1 } else { index_bits }) as u8;
+    }
+
+    indices
+}
+
+/// Widen a channel to eight bits by repeating its highest bits below it.
+fn expand(value: u32, bits: u32) -> u8 {
+    ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8
+}
+
+/// Decode a BC7 block in any of its eight modes. Blocks of the reserved mode
+/// decode to transparent black.
+pub(crate) fn decode_bc7(block: &[u8], pixels: &mut [Rgba]) {
+    let mut bits = BitReader::new(block);
+    let mut mode = 0;
+    while mode < MODES.len() && bits.read(1) == 0 {
+        mode += 1;
+    }
+    if mode == MODES.len() {
+        for pixel in pixels.iter_mut() {
+            *pixel = Rgba::new(0, 0, 0, 0);
+        }
+        return;
+    }
+
+    let mode = MODES[mode];
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let selection = bits.read(mode.selection_bits);
+
+    let endpoint_count = 2 * mode.subsets;
+    let mut endpoints = [[0; 4]; 6];
+    for channel in 0..4 {
+        let channel_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+        for endpoint in endpoints[..endpoint_count].iter_mut() {
+            endpoint[channel] = bits.read(channel_bits);
+        }
+    }
+
+    let mut parity = [0; 6];
+    match mode.parity {
+        Parity::None => {}
+        Parity::Shared => {
+            for subset in 0..mode.subsets {
+                let bit = bits.read(1);
+                parity[2 * subset] = bit;
+                parity[2 * subset + 1] = bit;
+            }
+        }
+        Parity::Unique => {
+            for bit in parity[..endpoint_count].iter_mut() {
+                *bit = bits.read(1);
+            }
+        }
+    }
+
+    let parity_bits = if mode.parity == Parity::None { 0 } else { 1 };
+    let mut colors = [[0; 4]; 6];
+    for (color, (endpoint, &parity)) in colors.iter_mut().zip(endpoints.iter().zip(&parity)) {
+        for channel in 0..4 {
+            let channel_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+            color[channel] = if channel_bits == 0 {
+                255
+            } else {
+                expand((endpoint[channel] << parity_bits) | parity, channel_bits + parity_bits)
+            };
+        }
+    }
+
+    let (subsets, anchors): (&[u8; 16], &[usize]) = match mode.subsets {
+        1 => (&[0; 16], &[]),
+        2 => (&PARTITIONS_2[partition], &ANCHORS_2[partition..(partition + 1)]),
+        _ => (&PARTITIONS_3[partition], &ANCHORS_3[partition]),
+    };
+    let color_indices = read_indices(&mut bits, mode.index_bits, anchors);
+    let (color_indices, color_bits, alpha_indices, alpha_bits) = if mode.alpha_index_bits == 0 {
+        (color_indices, mode.index_bits, color_indices, mode.index_bits)
+    } else {
+        let alpha_indices = read_indices(&mut bits, mode.alpha_index_bits, &[]);
+        if selection == 1 {
+            (alpha_indices, mode.alpha_index_bits, color_indices, mode.index_bits)
+        } else {
+            (color_indices, mode.index_bits, alpha_indices, mode.alpha_index_bits)
+        }
+    };
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let subset = subsets[i] as usize;
+        let (start, end) = (colors[2 * subset], colors[2 * subset + 1]);
+        let color_weight = weights(color_bits)[color_indices[i] as usize];
+        let alpha_weight = weights(alpha_bits)[alpha_indices[i] as usize];
+        let channel = |channel: usize, weight: u32| interpolate(start[channel], end[channel], weight);
+        let mut color = [
+            channel(0, color_weight), channel(1, color_weight), channel(2, color_weight), channel(3, alpha_weight)
+        ];
+        if rotation > 0 {
+            color.swap(rotation as usize - 1, 3);
+        }
+        *pixel = Rgba::new(color[0], color[1], color[2], color[3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn decode(block: &[u8]) -> Vec<Rgba> {
+        let mut pixels = vec![Rgba::default(); 16];
+        decode_bc7(block, &mut pixels);
+
+        pixels
+    }
+
+    #[test]
+    fn test_weights_are_symmetric() {
+        for (index, &weight) in WEIGHTS_4.iter().enumerate() {
+            assert_eq!(weight + WEIGHTS_4[15 - index], 64);
+        }
+    }
+
+    #[test]
+    fn test_bc7_encodes_a_gradient_closely() {
+        let pixels: Vec<Rgba> = (0..16).map(|i| Rgba::new(10 * i, 255 - 15 * i, 128, 100 + 8 * i)).collect();
+        for &quality in &[Quality::Fast, Quality::Normal, Quality::Best] {
+            let mut blocks = vec![];
+            encode_bc7(&pixels, quality, &mut blocks);
+            let decoded = decode(&blocks);
+
+            for (pixel, color) in pixels.iter().zip(&decoded) {
+                assert!((pixel.r as i32 - color.r as i32).abs() <= 8, "{:?} {:?}", pixel, color);
+                assert!((pixel.g as i32 - color.g as i32).abs() <= 8, "{:?} {:?}", pixel, color);
+                assert!((pixel.a as i32 - color.a as i32).abs() <= 8, "{:?} {:?}", pixel, color);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bc7_anchor_index_fits_in_three_bits() {
+        let mut pixels = vec![Rgba::new(0, 0, 0, 255); 16];
+        pixels[0] = Rgba::new(255, 255, 255, 255);
+        let mut blocks = vec![];
+        encode_bc7(&pixels, Quality::Normal, &mut blocks);
+        let decoded = decode(&blocks);
+
+        assert!(decoded[0].r >= 254 && decoded[0].a >= 254, "{:?}", decoded[0]);
+        assert!(decoded[1].r <= 1 && decoded[1].a >= 254, "{:?}", decoded[1]);
+    }
+
+    #[test]
+    fn test_anchor_pixels_lie_in_their_subsets() {
+        for partition in 0..64 {
+            assert_eq!(PARTITIONS_2[partition][0], 0);
+            assert_eq!(PARTITIONS_2[partition][ANCHORS_2[partition]], 1);
+            assert_eq!(PARTITIONS_3[partition][0], 0);
+            assert_eq!(PARTITIONS_3[partition][ANCHORS_3[partition][0]], 1);
+            assert_eq!(PARTITIONS_3[partition][ANCHORS_3[partition][1]], 2);
+        }
+    }
+
+    #[test]
+    fn test_bc7_modes_fill_the_whole_block() {
+        for (i, mode) in MODES.iter().enumerate() {
+            let endpoint_count = 2 * mode.subsets as u32;
+            let parity_bits = match mode.parity {
+                Parity::None => 0,
+                Parity::Shared => mode.subsets as u32,
+                Parity::Unique => endpoint_count,
+            };
+            let anchors = mode.subsets as u32;
+            let bits = i as u32 + 1 + mode.partition_bits + mode.rotation_bits + mode.selection_bits
+                + endpoint_count * (3 * mode.color_bits + mode.alpha_bits) + parity_bits
+                + 16 * mode.index_bits - anchors
+                + if mode.alpha_index_bits > 0 { 16 * mode.alpha_index_bits - 1 } else { 0 };
+
+            assert_eq!(bits, 128, "mode {}", i);
+        }
+    }
+
+    #[test]
+    fn test_bc7_decodes_each_subset_from_its_own_endpoints() {
+        // A mode 1 block whose first subset is black and whose second subset
+        // is white, with every index zero.
+        let partition = 13;
+        let mut bits = BitWriter::new();
+        bits.write(0b10, 2);
+        bits.write(partition as u32, 6);
+        for _ in 0..3 {
+            bits.write(0, 6);
+            bits.write(0, 6);
+            bits.write(63, 6);
+            bits.write(63, 6);
+        }
+        bits.write(0, 1);
+        bits.write(1, 1);
+        bits.write(0, 32);
+        bits.write(0, 14);
+        let decoded = decode(&bits.into_bytes());
+
+        for (i, pixel) in decoded.iter().enumerate() {
+            let expected = if PARTITIONS_2[partition][i] == 1 { 255 } else { 0 };
+            assert_eq!(*pixel, Rgba::new(expected, expected, expected, 255), "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn test_bc7_rotation_swaps_alpha_into_a_color_channel() {
+        // A mode 5 block with red and alpha swapped, interpolating the alpha
+        // channel from 0 to 255 and every color channel from 255 to 0.
+        let mut bits = BitWriter::new();
+        bits.write(1 << 5, 6);
+        bits.write(1, 2);
+        for _ in 0..3 {
+            bits.write(127, 7);
+            bits.write(0, 7);
+        }
+        bits.write(0, 8);
+        bits.write(255, 8);
+        bits.write(0, 31);
+        bits.write(0b11 << 29, 31);
+        let decoded = decode(&bits.into_bytes());
+
+        assert_eq!(decoded[0], Rgba::new(0, 255, 255, 255));
+        assert_eq!(decoded[15], Rgba::new(255, 255, 255, 255));
+    }
+
+    #[test]
+    fn test_bc7_reserved_mode_decodes_to_transparent_black() {
+        assert_eq!(decode(&[0; 16]), vec![Rgba::new(0, 0, 0, 0); 16]);
+    }
+}
@@ -0,0 +1,866 @@
+use super::{principal_endpoints, Quality};
+use crate::Rgba;
+
+
+/// The small and large intensity modifiers of the eight tables a subblock
+/// of an ETC block chooses from.
+const INTENSITY_TABLES: [[i32; 2]; 8] = [
+    [2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183],
+];
+
+/// The distances between the paint colors of the T and H modes.
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// The modifiers of the sixteen tables an EAC block chooses from.
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// The number of a pixel in a block. ETC and EAC blocks number their pixels
+/// column by column, while the pixels passed to the encoders and decoders are
+/// stored row by row.
+#[inline]
+fn pixel_number(x: usize, y: usize) -> usize {
+    x * 4 + y
+}
+
+/// The intensity modifier a selector picks from a table.
+fn intensity_modifier(table: usize, selector: u32) -> i32 {
+    let [small, large] = INTENSITY_TABLES[table];
+    match selector {
+        0 => small,
+        1 => large,
+        2 => -small,
+        _ => -large,
+    }
+}
+
+/// Expand a channel value with the given number of bits to eight bits.
+fn extend(value: u32, bits: u32) -> i32 {
+    let value = value << (8 - bits);
+
+    (value | (value >> bits)) as i32
+}
+
+fn clamp_channel(value: i32) -> i32 {
+    value.clamp(0, 255)
+}
+
+fn to_rgba(color: [i32; 3]) -> Rgba {
+    Rgba::new(clamp_channel(color[0]) as u8, clamp_channel(color[1]) as u8, clamp_channel(color[2]) as u8, 255)
+}
+
+fn offset(color: [i32; 3], amount: i32) -> [i32; 3] {
+    [clamp_channel(color[0] + amount), clamp_channel(color[1] + amount), clamp_channel(color[2] + amount)]
+}
+
+fn squared_error(a: [i32; 3], b: [i32; 3]) -> u32 {
+    a.iter().zip(&b).map(|(&a, &b)| ((a - b) * (a - b)) as u32).sum()
+}
+
+/// Read a field of a 64 bit block, counting bits from the least significant.
+#[inline]
+fn field(bits: u64, start: u32, count: u32) -> u32 {
+    ((bits >> start) & ((1 << count) - 1)) as u32
+}
+
+/// Sign extend a three bit two's complement value.
+fn signed_3(value: u32) -> i32 {
+    ((value as i32) << 29) >> 29
+}
+
+/// Decode an ETC2 RGB block into its sixteen pixels, row by row.
+fn decode_etc2_rgb_block(bits: u64) -> [Rgba; 16] {
+    let selector = |number: usize| (field(bits, 16 + number as u32, 1) << 1) | field(bits, number as u32, 1);
+    let mut pixels = [Rgba::new(0, 0, 0, 255); 16];
+
+    let paint = |pixels: &mut [Rgba; 16], colors: [[i32; 3]; 4]| {
+        for y in 0..4 {
+            for x in 0..4 {
+                pixels[y * 4 + x] = to_rgba(colors[selector(pixel_number(x, y)) as usize]);
+            }
+        }
+    };
+
+    let bases = if field(bits, 33, 1) == 0 {
+        [
+            [extend(field(bits, 60, 4), 4), extend(field(bits, 52, 4), 4), extend(field(bits, 44, 4), 4)],
+            [extend(field(bits, 56, 4), 4), extend(field(bits, 48, 4), 4), extend(field(bits, 40, 4), 4)],
+        ]
+    } else {
+        let (red, green, blue) = (field(bits, 59, 5) as i32, field(bits, 51, 5) as i32, field(bits, 43, 5) as i32);
+        let (red_delta, green_delta, blue_delta) =
+            (signed_3(field(bits, 56, 3)), signed_3(field(bits, 48, 3)), signed_3(field(bits, 40, 3)));
+        let overflows = |value: i32| !(0..=31).contains(&value);
+
+        if overflows(red + red_delta) {
+            let first = [
+                extend((field(bits, 59, 2) << 2) | field(bits, 56, 2), 4),
+                extend(field(bits, 52, 4), 4),
+                extend(field(bits, 48, 4), 4),
+            ];
+            let second = [
+                extend(field(bits, 44, 4), 4),
+                extend(field(bits, 40, 4), 4),
+                extend(field(bits, 36, 4), 4),
+            ];
+            let distance = DISTANCES[((field(bits, 34, 2) << 1) | field(bits, 32, 1)) as usize];
+            paint(&mut pixels, [first, offset(second, distance), second, offset(second, -distance)]);
+            return pixels;
+        }
+
+        if overflows(green + green_delta) {
+            let first = [
+                field(bits, 59, 4),
+                (field(bits, 56, 3) << 1) | field(bits, 52, 1),
+                (field(bits, 51, 1) << 3) | field(bits, 47, 3),
+            ];
+            let second = [field(bits, 43, 4), field(bits, 39, 4), field(bits, 35, 4)];
+            let packed = |color: [u32; 3]| (color[0] << 8) | (color[1] << 4) | color[2];
+            let order = (packed(first) >= packed(second)) as u32;
+            let distance = DISTANCES[((field(bits, 34, 1) << 2) | (field(bits, 32, 1) << 1) | order) as usize];
+            let first = [extend(first[0], 4), extend(first[1], 4), extend(first[2], 4)];
+            let second = [extend(second[0], 4), extend(second[1], 4), extend(second[2], 4)];
+            paint(&mut pixels, [
+                offset(first, distance), offset(first, -distance), offset(second, distance), offset(second, -distance),
+            ]);
+            return pixels;
+        }
+
+        if overflows(blue + blue_delta) {
+            let origin = [
+                extend(field(bits, 57, 6), 6),
+                extend((field(bits, 56, 1) << 6) | field(bits, 49, 6), 7),
+                extend((field(bits, 48, 1) << 5) | (field(bits, 43, 2) << 3) | field(bits, 39, 3), 6),
+            ];
+            let horizontal = [
+                extend((field(bits, 34, 5) << 1) | field(bits, 32, 1), 6),
+                extend(field(bits, 25, 7), 7),
+                extend(field(bits, 19, 6), 6),
+            ];
+            let vertical = [
+                extend(field(bits, 13, 6), 6),
+                extend(field(bits, 6, 7), 7),
+                extend(field(bits, 0, 6), 6),
+            ];
+            for y in 0..4 {
+                for x in 0..4 {
+                    let mut color = [0; 3];
+                    for channel in 0..3 {
+                        color[channel] = (x as i32 * (horizontal[channel] - origin[channel]) +
+                            y as i32 * (vertical[channel] - origin[channel]) + 4 * origin[channel] + 2) >> 2;
+                    }
+                    pixels[y * 4 + x] = to_rgba(color);
+                }
+            }
+            return pixels;
+        }
+
+        [
+            [extend(red as u32, 5), extend(green as u32, 5), extend(blue as u32, 5)],
+            [
+                extend((red + red_delta) as u32, 5),
+                extend((green + green_delta) as u32, 5),
+                extend((blue + blue_delta) as u32, 5),
+            ],
+        ]
+    };
+
+    let tables = [field(bits, 37, 3) as usize, field(bits, 34, 3) as usize];
+    let flip = field(bits, 32, 1) == 1;
+    for y in 0..4 {
+        for x in 0..4 {
+            let subblock = if flip { y / 2 } else { x / 2 };
+            let modifier = intensity_modifier(tables[subblock], selector(pixel_number(x, y)));
+            pixels[y * 4 + x] = to_rgba(offset(bases[subblock], modifier));
+        }
+    }
+
+    pixels
+}
+
+/// The value an EAC block reproduces. Eleven bit blocks scale their
+/// modifiers by eight, and use them unscaled when the multiplier is zero.
+fn eac_value(base: i32, multiplier: i32, modifier: i32, eleven_bit: bool) -> i32 {
+    if eleven_bit {
+        let scale = if multiplier == 0 { 1 } else { multiplier * 8 };
+        (base * 8 + 4 + modifier * scale).clamp(0, 2047)
+    } else {
+        (base + modifier * multiplier).clamp(0, 255)
+    }
+}
+
+/// Decode an EAC block into its sixteen values, row by row. Eleven bit
+/// blocks decode to values from 0 to 2047, and eight bit blocks to values
+/// from 0 to 255.
+fn decode_eac_block(bits: u64, eleven_bit: bool) -> [i32; 16] {
+    let base = field(bits, 56, 8) as i32;
+    let multiplier = field(bits, 52, 4) as i32;
+    let modifiers = EAC_MODIFIERS[field(bits, 48, 4) as usize];
+    let mut values = [0; 16];
+    for y in 0..4 {
+        for x in 0..4 {
+            let selector = field(bits, 45 - 3 * pixel_number(x, y) as u32, 3);
+            values[y * 4 + x] = eac_value(base, multiplier, modifiers[selector as usize], eleven_bit);
+        }
+    }
+
+    values
+}
+
+fn read_block(block: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&block[0..8]);
+
+    u64::from_be_bytes(bytes)
+}
+
+/// Reduce an eleven bit value to eight bits.
+fn eleven_to_eight(value: i32) -> u8 {
+    ((value * 255 + 1023) / 2047) as u8
+}
+
+/// Widen an eight bit value to eleven bits.
+fn eight_to_eleven(value: u8) -> i32 {
+    (value as i32 * 2047 + 127) / 255
+}
+
+pub(crate) fn decode_etc2_rgb(block: &[u8], pixels: &mut [Rgba]) {
+    pixels.copy_from_slice(&decode_etc2_rgb_block(read_block(block)));
+}
+
+pub(crate) fn decode_etc2_rgba8(block: &[u8], pixels: &mut [Rgba]) {
+    let alpha = decode_eac_block(read_block(&block[0..8]), false);
+    pixels.copy_from_slice(&decode_etc2_rgb_block(read_block(&block[8..16])));
+    for (pixel, &alpha) in pixels.iter_mut().zip(&alpha) {
+        pixel.a = alpha as u8;
+    }
+}
+
+pub(crate) fn decode_eac_r11(block: &[u8], pixels: &mut [Rgba]) {
+    let red = decode_eac_block(read_block(block), true);
+    for (pixel, &red) in pixels.iter_mut().zip(&red) {
+        *pixel = Rgba::new(eleven_to_eight(red), 0, 0, 255);
+    }
+}
+
+pub(crate) fn decode_eac_rg11(block: &[u8], pixels: &mut [Rgba]) {
+    let red = decode_eac_block(read_block(&block[0..8]), true);
+    let green = decode_eac_block(read_block(&block[8..16]), true);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = Rgba::new(eleven_to_eight(red[i]), eleven_to_eight(green[i]), 0, 255);
+    }
+}
+
+/// Choose the nearest modifier of a table for every value, and write the block.
+fn fit_eac(values: &[i32], base: i32, multiplier: i32, table: usize, eleven_bit: bool) -> (u64, u64) {
+    let modifiers = EAC_MODIFIERS[table];
+    let mut bits = ((base as u64) << 56) | ((multiplier as u64) << 52) | ((table as u64) << 48);
+    let mut error = 0;
+    for y in 0..4 {
+        for x in 0..4 {
+            let value = values[y * 4 + x];
+            let (selector, value_error) = modifiers.iter()
+                .map(|&modifier| (eac_value(base, multiplier, modifier, eleven_bit) - value).pow(2) as u64)
+                .enumerate()
+                .fold((0, u64::MAX), |best, next| if next.1 < best.1 { next } else { best });
+            bits |= (selector as u64) << (45 - 3 * pixel_number(x, y));
+            error += value_error;
+        }
+    }
+
+    (bits, error)
+}
+
+/// Encode sixteen values as an EAC block, searching the multipliers and base
+/// values around the ones that span the range of the values with each table.
+/// Eleven bit blocks take values from 0 to 2047.
+fn encode_eac(values: &[i32], eleven_bit: bool, quality: Quality) -> [u8; 8] {
+    let low = *values.iter().min().unwrap();
+    let high = *values.iter().max().unwrap();
+    let scale = if eleven_bit { 8 } else { 1 };
+    let (multiplier_window, base_window) = match quality {
+        Quality::Fast => (0, 0),
+        Quality::Normal => (1, 1),
+        Quality::Best => (2, 4),
+    };
+
+    let mut best = (0, u64::MAX);
+    for (table, modifiers) in EAC_MODIFIERS.iter().enumerate() {
+        let span = (modifiers[7] - modifiers[3]) * scale;
+        let estimate = ((high - low) as f32 / span as f32).round() as i32;
+        let lowest = if eleven_bit { 0 } else { 1 };
+        for multiplier in (estimate - multiplier_window).max(lowest)..(estimate + multiplier_window).min(15) + 1 {
+            let step = if multiplier == 0 { 1 } else { multiplier * scale };
+            let center = (low + high) as f32 / 2.0 - (modifiers[7] + modifiers[3]) as f32 * step as f32 / 2.0;
+            let estimate = ((center - (scale / 2) as f32) / scale as f32).round() as i32;
+            for base in (estimate - base_window).max(0)..(estimate + base_window).min(255) + 1 {
+                let candidate = fit_eac(values, base, multiplier, table, eleven_bit);
+                if candidate.1 < best.1 {
+                    best = candidate;
+                }
+            }
+        }
+    }
+
+    best.0.to_be_bytes()
+}
+
+/// The table, selectors and squared error of a subblock fit around a base
+/// color. The selectors are indexed by pixel number, and only the selectors
+/// of the pixels in the subblock are set.
+#[derive(Copy, Clone, Debug)]
+struct SubblockFit {
+    base: [i32; 3],
+    table: usize,
+    selectors: [u32; 16],
+    error: u32,
+}
+
+/// Choose the best table for a subblock around a base color given in the
+/// given number of bits per channel, and the best selector for each pixel.
+fn fit_subblock(subblock: &[(usize, [i32; 3])], base: [i32; 3], bits: u32) -> SubblockFit {
+    let color = [extend(base[0] as u32, bits), extend(base[1] as u32, bits), extend(base[2] as u32, bits)];
+    let mut best = SubblockFit { base: base, table: 0, selectors: [0; 16], error: u32::MAX };
+    for table in 0..8 {
+        let mut selectors = [0; 16];
+        let mut error = 0;
+        for &(number, pixel) in subblock {
+            let (selector, pixel_error) = (0..4)
+                .map(|selector| {
+                    (selector, squared_error(pixel, offset(color, intensity_modifier(table, selector))))
+                })
+                .fold((0, u32::MAX), |best, next| if next.1 < best.1 { next } else { best });
+            selectors[number] = selector;
+            error += pixel_error;
+        }
+
+        if error < best.error {
+            best = SubblockFit { base: base, table: table, selectors: selectors, error: error };
+        }
+    }
+
+    best
+}
+
+/// Round a color to the given number of bits per channel, keeping each
+/// channel within the given range.
+fn quantize_color(color: [f32; 3], bits: u32, range: [(i32, i32); 3]) -> [i32; 3] {
+    let max = ((1 << bits) - 1) as f32;
+    let mut quantized = [0; 3];
+    for channel in 0..3 {
+        let value = (color[channel].clamp(0.0, 255.0) * max / 255.0).round() as i32;
+        quantized[channel] = value.max(range[channel].0).min(range[channel].1);
+    }
+
+    quantized
+}
+
+/// Fit the base color of a subblock, starting from its average color and
+/// refining it as far as the quality allows. Every channel of the base color
+/// stays within the given range of quantized values.
+fn fit_base(
+    subblock: &[(usize, [i32; 3])],
+    bits: u32,
+    range: [(i32, i32); 3],
+    quality: Quality) -> SubblockFit
+{
+    let mut average = [0.0; 3];
+    for &(_, pixel) in subblock {
+        for channel in 0..3 {
+            average[channel] += pixel[channel] as f32 / subblock.len() as f32;
+        }
+    }
+    let mut best = fit_subblock(subblock, quantize_color(average, bits, range), bits);
+
+    let iterations = match quality {
+        Quality::Fast => 0,
+        Quality::Normal => 2,
+        Quality::Best => 4,
+    };
+    for _ in 0..iterations {
+        // The modifiers move every channel together, so the best base color
+        // for the chosen selectors is the average of the unmodified pixels.
+        let mut average = [0.0; 3];
+        for &(number, pixel) in subblock {
+            let modifier = intensity_modifier(best.table, best.selectors[number]);
+            for channel in 0..3 {
+                average[channel] += (pixel[channel] - modifier) as f32 / subblock.len() as f32;
+            }
+        }
+
+        let candidate = fit_subblock(subblock, quantize_color(average, bits, range), bits);
+        if candidate.error >= best.error {
+            break;
+        }
+        best = candidate;
+    }
+
+    if quality == Quality::Best {
+        let center = best.base;
+        for step in 0..27 {
+            let steps = [step % 3 - 1, step / 3 % 3 - 1, step / 9 - 1];
+            let mut base = center;
+            let mut inside = true;
+            for channel in 0..3 {
+                base[channel] += steps[channel];
+                inside &= base[channel] >= range[channel].0 && base[channel] <= range[channel].1;
+            }
+            if !inside || base == center {
+                continue;
+            }
+
+            let candidate = fit_subblock(subblock, base, bits);
+            if candidate.error < best.error {
+                best = candidate;
+            }
+        }
+    }
+
+    best
+}
+
+/// Write the selectors of both subblocks into the low half of a block.
+fn selector_bits(first: &SubblockFit, second: &SubblockFit, subblocks: &[Vec<(usize, [i32; 3])>; 2]) -> u64 {
+    let mut bits = 0u64;
+    for (fit, subblock) in [first, second].iter().zip(subblocks.iter()) {
+        for &(number, _) in subblock {
+            let selector = fit.selectors[number] as u64;
+            bits |= ((selector >> 1) << (16 + number)) | ((selector & 1) << number);
+        }
+    }
+
+    bits
+}
+
+/// Fit a block in the individual and differential modes with the given
+/// orientation of the subblocks, and return the better of the two blocks
+/// with its squared error.
+fn fit_subblock_modes(colors: &[[i32; 3]; 16], flip: bool, quality: Quality) -> (u64, u32) {
+    let mut subblocks: [Vec<(usize, [i32; 3])>; 2] = [vec![], vec![]];
+    for y in 0..4 {
+        for x in 0..4 {
+            let subblock = if flip { y / 2 } else { x / 2 };
+            subblocks[subblock].push((pixel_number(x, y), colors[y * 4 + x]));
+        }
+    }
+    let flip_bit = (flip as u64) << 32;
+
+    // The individual mode stores two four bit colors.
+    let full = [(0, 15); 3];
+    let first = fit_base(&subblocks[0], 4, full, quality);
+    let second = fit_base(&subblocks[1], 4, full, quality);
+    let mut bits = ((first.base[0] as u64) << 60) | ((second.base[0] as u64) << 56) |
+        ((first.base[1] as u64) << 52) | ((second.base[1] as u64) << 48) |
+        ((first.base[2] as u64) << 44) | ((second.base[2] as u64) << 40) |
+        ((first.table as u64) << 37) | ((second.table as u64) << 34) | flip_bit;
+    bits |= selector_bits(&first, &second, &subblocks);
+    let individual = (bits, first.error + second.error);
+
+    // The differential mode stores a five bit color and the difference of the
+    // second color from it, from -4 to 3.
+    let first = fit_base(&subblocks[0], 5, [(0, 31); 3], quality);
+    let mut range = [(0, 0); 3];
+    for (channel_range, &base) in range.iter_mut().zip(first.base.iter()) {
+        *channel_range = ((base - 4).max(0), (base + 3).min(31));
+    }
+    let second = fit_base(&subblocks[1], 5, range, quality);
+    let delta = |channel: usize| ((second.base[channel] - first.base[channel]) & 0x07) as u64;
+    let mut bits = ((first.base[0] as u64) << 59) | (delta(0) << 56) |
+        ((first.base[1] as u64) << 51) | (delta(1) << 48) |
+        ((first.base[2] as u64) << 43) | (delta(2) << 40) |
+        ((first.table as u64) << 37) | ((second.table as u64) << 34) | (1 << 33) | flip_bit;
+    bits |= selector_bits(&first, &second, &subblocks);
+    let differential = (bits, first.error + second.error);
+
+    if differential.1 <= individual.1 { differential } else { individual }
+}
+
+/// Solve for the origin, horizontal and vertical colors of the planar mode
+/// by least squares. The planar mode interpolates the color of the pixel at
+/// column `x` and row `y` as `origin + x * (horizontal - origin) / 4 + y *
+/// (vertical - origin) / 4`.
+fn fit_planar(colors: &[[i32; 3]; 16]) -> u64 {
+    let mut matrix = [[0.0f32; 3]; 3];
+    let mut sums = [[0.0f32; 3]; 3];
+    for y in 0..4 {
+        for x in 0..4 {
+            let basis = [1.0 - (x + y) as f32 / 4.0, x as f32 / 4.0, y as f32 / 4.0];
+            for row in 0..3 {
+                for column in 0..3 {
+                    matrix[row][column] += basis[row] * basis[column];
+                }
+                for channel in 0..3 {
+                    sums[channel][row] += basis[row] * colors[y * 4 + x][channel] as f32;
+                }
+            }
+        }
+    }
+
+    let determinant = |m: [[f32; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) -
+            m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0]) +
+            m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let whole = determinant(matrix);
+    let bits = [6, 7, 6];
+    let mut quantized = [[0u64; 3]; 3];
+    for channel in 0..3 {
+        for unknown in 0..3 {
+            let mut replaced = matrix;
+            for row in 0..3 {
+                replaced[row][unknown] = sums[channel][row];
+            }
+            let value = determinant(replaced) / whole;
+            let max = ((1 << bits[channel]) - 1) as f32;
+            quantized[unknown][channel] = (value.clamp(0.0, 255.0) * max / 255.0).round() as u64;
+        }
+    }
+
+    let [origin, horizontal, vertical] = quantized;
+    let mut bits = (origin[0] << 57) | ((origin[1] >> 6) << 56) | ((origin[1] & 0x3F) << 49) |
+        ((origin[2] >> 5) << 48) | (((origin[2] >> 3) & 0x03) << 43) | ((origin[2] & 0x07) << 39) |
+        ((horizontal[0] >> 1) << 34) | (1 << 33) | ((horizontal[0] & 1) << 32) |
+        (horizontal[1] << 25) | (horizontal[2] << 19) | (vertical[0] << 13) | (vertical[1] << 6) | vertical[2];
+
+    // The planar mode is signalled by the blue channel of the differential
+    // mode overflowing while the red and green channels do not. The unused
+    // bits are set to make that happen.
+    let red_delta = signed_3((((origin[0] & 0x03) << 1) | (origin[1] >> 6)) as u32);
+    if ((origin[0] >> 2) as i32) + red_delta < 0 {
+        bits |= 1 << 63;
+    }
+    let green_delta = signed_3((((origin[1] & 0x03) << 1) | (origin[2] >> 5)) as u32);
+    if (((origin[1] >> 2) & 0x0F) as i32) + green_delta < 0 {
+        bits |= 1 << 55;
+    }
+    if ((origin[2] >> 3) & 0x03) + ((origin[2] & 0x07) >> 1) < 4 {
+        bits |= 1 << 42;
+    } else {
+        bits |= 0x07 << 45;
+    }
+
+    bits
+}
+
+/// Split the colors of a block into two clusters, starting from the ends of
+/// their principal axis, and return the average color of each cluster.
+fn two_clusters(colors: &[[i32; 3]; 16], iterations: usize) -> [[f32; 3]; 2] {
+    let points: Vec<[f32; 3]> = colors.iter().map(|color| [color[0] as f32, color[1] as f32, color[2] as f32]).collect();
+    let (start, end) = principal_endpoints(&points);
+    let mut centers = [start, end];
+    for _ in 0..iterations {
+        let mut sums = [[0.0; 3]; 2];
+        let mut counts = [0.0; 2];
+        for point in &points {
+            let distance = |center: &[f32; 3]| (0..3).map(|channel| (point[channel] - center[channel]).powi(2)).sum::<f32>();
+            let cluster = if distance(&centers[1]) < distance(&centers[0]) { 1 } else { 0 };
+            for channel in 0..3 {
+                sums[cluster][channel] += point[channel];
+            }
+            counts[cluster] += 1.0;
+        }
+
+        for cluster in 0..2 {
+            if counts[cluster] > 0.0 {
+                for channel in 0..3 {
+                    centers[cluster][channel] = sums[cluster][channel] / counts[cluster];
+                }
+            }
+        }
+    }
+
+    centers
+}
+
+/// Choose the nearest paint color for every pixel, and return the selector
+/// bits with the squared error.
+fn fit_paint_colors(colors: &[[i32; 3]; 16], paint: [[i32; 3]; 4]) -> (u64, u32) {
+    let mut bits = 0u64;
+    let mut error = 0;
+    for y in 0..4 {
+        for x in 0..4 {
+            let (selector, pixel_error) = paint.iter()
+                .map(|&color| squared_error(colors[y * 4 + x], color))
+                .enumerate()
+                .fold((0, u32::MAX), |best, next| if next.1 < best.1 { next } else { best });
+            let number = pixel_number(x, y);
+            bits |= (((selector as u64) >> 1) << (16 + number)) | (((selector as u64) & 1) << number);
+            error += pixel_error;
+        }
+    }
+
+    (bits, error)
+}
+
+/// Fit a block in the T and H modes, which paint the block with four colors
+/// taken from two base colors, and return the better block with its squared
+/// error. The modes suit blocks holding two distinct colors, such as blocks
+/// along an edge between two colors, which the other modes blur.
+fn fit_paint_modes(colors: &[[i32; 3]; 16], quality: Quality) -> (u64, u32) {
+    let iterations = if quality == Quality::Best { 4 } else { 1 };
+    let centers = two_clusters(colors, iterations);
+    let full = [(0, 15); 3];
+    let bases = [quantize_color(centers[0], 4, full), quantize_color(centers[1], 4, full)];
+    let extended = |base: [i32; 3]| [extend(base[0] as u32, 4), extend(base[1] as u32, 4), extend(base[2] as u32, 4)];
+    let mut best = (0, u32::MAX);
+
+    // The T mode paints one base color, and the other base color moved by
+    // the distance in both directions. The red channel of the differential
+    // mode must overflow, which the unused bits are set to ensure.
+    for &(single, pair) in &[(bases[0], bases[1]), (bases[1], bases[0])] {
+        for (index, &distance) in DISTANCES.iter().enumerate() {
+            let paint = [
+                extended(single), offset(extended(pair), distance), extended(pair), offset(extended(pair), -distance),
+            ];
+            let (selectors, error) = fit_paint_colors(colors, paint);
+            if error >= best.1 {
+                continue;
+            }
+
+            let (red_high, red_low) = ((single[0] >> 2) as u64, (single[0] & 0x03) as u64);
+            let mut bits = (red_high << 59) | (red_low << 56) | ((single[1] as u64) << 52) | ((single[2] as u64) << 48) |
+                ((pair[0] as u64) << 44) | ((pair[1] as u64) << 40) | ((pair[2] as u64) << 36) |
+                (((index >> 1) as u64) << 34) | (1 << 33) | (((index & 1) as u64) << 32) | selectors;
+            if red_high + red_low < 4 {
+                bits |= 1 << 58;
+            } else {
+                bits |= 0x07 << 61;
+            }
+            best = (bits, error);
+        }
+    }
+
+    // The H mode paints both base colors moved by the distance in both
+    // directions. The lowest bit of the distance index is the order of the
+    // base colors, so the base colors are swapped to match it. The green
+    // channel of the differential mode must overflow while the red channel
+    // does not.
+    let packed = |color: [i32; 3]| (color[0] << 8) | (color[1] << 4) | color[2];
+    for (index, &distance) in DISTANCES.iter().enumerate() {
+        let (first, second) = if (packed(bases[0]) >= packed(bases[1])) == (index & 1 == 1) {
+            (bases[0], bases[1])
+        } else {
+            (bases[1], bases[0])
+        };
+        if (packed(first) >= packed(second)) != (index & 1 == 1) {
+            continue;
+        }
+
+        let paint = [
+            offset(extended(first), distance), offset(extended(first), -distance),
+            offset(extended(second), distance), offset(extended(second), -distance),
+        ];
+        let (selectors, error) = fit_paint_colors(colors, paint);
+        if error >= best.1 {
+            continue;
+        }
+
+        let (green_high, green_low) = ((first[1] >> 1) as u64, (first[1] & 1) as u64);
+        let (blue_high, blue_low) = ((first[2] >> 3) as u64, (first[2] & 0x07) as u64);
+        let mut bits = ((first[0] as u64) << 59) | (green_high << 56) | (green_low << 52) | (blue_high << 51) |
+            (blue_low << 47) | ((second[0] as u64) << 43) | ((second[1] as u64) << 39) | ((second[2] as u64) << 35) |
+            (((index >> 2) as u64) << 34) | (1 << 33) | ((((index >> 1) & 1) as u64) << 32) | selectors;
+        if first[0] + signed_3(green_high as u32) < 0 {
+            bits |= 1 << 63;
+        }
+        if ((green_low << 1) | blue_high) + (blue_low >> 1) < 4 {
+            bits |= 1 << 50;
+        } else {
+            bits |= 0x07 << 53;
+        }
+        best = (bits, error);
+    }
+
+    best
+}
+
+/// Encode the colors of a block as an ETC2 RGB block. Every quality tries
+/// both orientations of the individual and differential modes, and the
+/// better qualities also try the T and H modes, which suit blocks holding two
+/// distinct colors, and the planar mode, which suits smooth gradients.
+fn encode_etc2_rgb_block(pixels: &[Rgba], quality: Quality) -> [u8; 8] {
+    let mut colors = [[0; 3]; 16];
+    for (color, pixel) in colors.iter_mut().zip(pixels) {
+        *color = [pixel.r as i32, pixel.g as i32, pixel.b as i32];
+    }
+
+    let mut best = fit_subblock_modes(&colors, false, quality);
+    let flipped = fit_subblock_modes(&colors, true, quality);
+    if flipped.1 < best.1 {
+        best = flipped;
+    }
+
+    if quality != Quality::Fast {
+        let painted = fit_paint_modes(&colors, quality);
+        if painted.1 < best.1 {
+            best = painted;
+        }
+
+        let planar = fit_planar(&colors);
+        let decoded = decode_etc2_rgb_block(planar);
+        let error = decoded.iter().zip(colors.iter())
+            .map(|(pixel, &color)| squared_error([pixel.r as i32, pixel.g as i32, pixel.b as i32], color))
+            .sum::<u32>();
+        if error < best.1 {
+            best = (planar, error);
+        }
+    }
+
+    best.0.to_be_bytes()
+}
+
+pub(crate) fn encode_etc2_rgb(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    blocks.extend_from_slice(&encode_etc2_rgb_block(pixels, quality));
+}
+
+pub(crate) fn encode_etc2_rgba8(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let alpha: Vec<i32> = pixels.iter().map(|pixel| pixel.a as i32).collect();
+    blocks.extend_from_slice(&encode_eac(&alpha, false, quality));
+    blocks.extend_from_slice(&encode_etc2_rgb_block(pixels, quality));
+}
+
+pub(crate) fn encode_eac_r11(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let red: Vec<i32> = pixels.iter().map(|pixel| eight_to_eleven(pixel.r)).collect();
+    blocks.extend_from_slice(&encode_eac(&red, true, quality));
+}
+
+pub(crate) fn encode_eac_rg11(pixels: &[Rgba], quality: Quality, blocks: &mut Vec<u8>) {
+    let red: Vec<i32> = pixels.iter().map(|pixel| eight_to_eleven(pixel.r)).collect();
+    let green: Vec<i32> = pixels.iter().map(|pixel| eight_to_eleven(pixel.g)).collect();
+    blocks.extend_from_slice(&encode_eac(&red, true, quality));
+    blocks.extend_from_slice(&encode_eac(&green, true, quality));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn gradient_block() -> Vec<Rgba> {
+        (0..16).map(|i| {
+            let (x, y) = (i % 4, i / 4);
+            Rgba::new(40 + 30 * x, 200 - 25 * y, 90 + 10 * x + 5 * y, 255)
+        }).collect()
+    }
+
+    fn total_error(pixels: &[Rgba], decoded: &[Rgba]) -> u32 {
+        pixels.iter().zip(decoded).map(|(pixel, color)| {
+            let channels = |pixel: &Rgba| [pixel.r as i32, pixel.g as i32, pixel.b as i32];
+            squared_error(channels(pixel), channels(color))
+        }).sum()
+    }
+
+    #[test]
+    fn test_eleven_bit_values_round_trip() {
+        for value in 0..256 {
+            assert_eq!(eleven_to_eight(eight_to_eleven(value as u8)), value as u8);
+        }
+    }
+
+    #[test]
+    fn test_etc2_rgb_encodes_a_solid_block_closely() {
+        let pixels = vec![Rgba::new(0x20, 0x80, 0xC0, 0xFF); 16];
+        for &quality in &[Quality::Fast, Quality::Normal, Quality::Best] {
+            let mut blocks = vec![];
+            encode_etc2_rgb(&pixels, quality, &mut blocks);
+            let mut decoded = [Rgba::default(); 16];
+            decode_etc2_rgb(&blocks, &mut decoded);
+
+            assert!(total_error(&pixels, &decoded) <= 16 * 32, "{:?}", decoded);
+        }
+    }
+
+    #[test]
+    fn test_etc2_rgb_planar_mode_fits_gradients() {
+        let pixels = gradient_block();
+        let mut fast = vec![];
+        encode_etc2_rgb(&pixels, Quality::Fast, &mut fast);
+        let mut best = vec![];
+        encode_etc2_rgb(&pixels, Quality::Best, &mut best);
+        let mut decoded_fast = [Rgba::default(); 16];
+        decode_etc2_rgb(&fast, &mut decoded_fast);
+        let mut decoded_best = [Rgba::default(); 16];
+        decode_etc2_rgb(&best, &mut decoded_best);
+
+        let bits = read_block(&best);
+        let blue = field(bits, 43, 5) as i32 + signed_3(field(bits, 40, 3));
+        assert_eq!(field(bits, 33, 1), 1);
+        assert!(!(0..32).contains(&blue));
+        assert!(total_error(&pixels, &decoded_best) < total_error(&pixels, &decoded_fast));
+        assert!(total_error(&pixels, &decoded_best) <= 16 * 3 * 4);
+    }
+
+    #[test]
+    fn test_etc2_rgb_paint_modes_fit_two_colors() {
+        let pixels: Vec<Rgba> = (0..16).map(|i| {
+            if (i % 4 + i / 4) % 2 == 0 { Rgba::new(0xFF, 0x00, 0x00, 0xFF) } else { Rgba::new(0x00, 0x00, 0xFF, 0xFF) }
+        }).collect();
+        let mut fast = vec![];
+        encode_etc2_rgb(&pixels, Quality::Fast, &mut fast);
+        let mut normal = vec![];
+        encode_etc2_rgb(&pixels, Quality::Normal, &mut normal);
+        let mut decoded_fast = [Rgba::default(); 16];
+        decode_etc2_rgb(&fast, &mut decoded_fast);
+        let mut decoded_normal = [Rgba::default(); 16];
+        decode_etc2_rgb(&normal, &mut decoded_normal);
+
+        assert!(total_error(&pixels, &decoded_normal) <= 16 * 3 * 4, "{:?}", decoded_normal);
+        assert!(total_error(&pixels, &decoded_fast) > 16 * 3 * 100);
+    }
+
+    #[test]
+    fn test_decode_etc2_rgb_reads_t_and_h_modes() {
+        // A T mode block with a red overflow, whose first paint color is
+        // white and whose other paint colors lie around black.
+        let t_mode: u64 = (0b111 << 61) | (0b11 << 59) | (0b11 << 56) | (0xF << 52) | (0xF << 48) | (1 << 33) |
+            0x0000_FFFF;
+        let mut pixels = [Rgba::default(); 16];
+        decode_etc2_rgb(&t_mode.to_be_bytes(), &mut pixels);
+        assert_eq!(pixels[0], Rgba::new(3, 3, 3, 255));
+
+        let t_mode = t_mode & !0x0000_FFFF;
+        decode_etc2_rgb(&t_mode.to_be_bytes(), &mut pixels);
+        assert_eq!(pixels[0], Rgba::new(255, 255, 255, 255));
+
+        // An H mode block with a green overflow and two gray base colors.
+        let h_mode: u64 = (0x8 << 59) | (0b100 << 56) | (1 << 51) | (1 << 50) | (0x8 << 43) |
+            (0x8 << 39) | (0x8 << 35) | (1 << 33);
+        decode_etc2_rgb(&h_mode.to_be_bytes(), &mut pixels);
+        let distance = DISTANCES[1];
+        assert_eq!(pixels[0], Rgba::new(0x88 + distance as u8, 0x88 + distance as u8, 0x88 + distance as u8, 255));
+    }
+
+    #[test]
+    fn test_eac_encodes_values_closely() {
+        let values: Vec<Rgba> = (0..16).map(|i| Rgba::new(17 * i, 255 - 17 * i, 0, 12 * i)).collect();
+        let mut blocks = vec![];
+        encode_eac_rg11(&values, Quality::Normal, &mut blocks);
+        encode_etc2_rgba8(&values, Quality::Normal, &mut blocks);
+        let mut rg = [Rgba::default(); 16];
+        decode_eac_rg11(&blocks[0..16], &mut rg);
+        let mut rgba = [Rgba::default(); 16];
+        decode_etc2_rgba8(&blocks[16..32], &mut rgba);
+
+        for i in 0..16 {
+            assert!((rg[i].r as i32 - values[i].r as i32).abs() <= 20, "{:?}", rg);
+            assert!((rg[i].g as i32 - values[i].g as i32).abs() <= 20, "{:?}", rg);
+            assert!((rgba[i].a as i32 - values[i].a as i32).abs() <= 20, "{:?}", rgba);
+        }
+    }
+}
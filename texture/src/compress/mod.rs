@@ -0,0 +1,836 @@
+use crate::{mip_level_count, MipChain, Pixel, Rgba, TexImage2D, TexImage2DError};
+use std::fmt;
+
+mod astc;
+mod bc;
+mod bc6h;
+mod bc7;
+mod etc;
+
+
+/// The block compressed formats GPUs sample from directly. Each format
+/// stores a texture image as a grid of fixed size blocks of pixels, and
+/// every block takes up the same number of bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// BC1, also known as DXT1. RGB colors with an optional one bit alpha
+    /// channel, at half a byte per pixel.
+    Bc1,
+    /// BC2, also known as DXT3. RGB colors as in BC1 with an explicit four
+    /// bit alpha channel, at one byte per pixel.
+    Bc2,
+    /// BC3, also known as DXT5. RGB colors as in BC1 with a separately
+    /// interpolated alpha channel, at one byte per pixel.
+    Bc3,
+    /// BC4. A single red channel at half a byte per pixel.
+    Bc4,
+    /// BC5. Separate red and green channels at one byte per pixel, such as
+    /// the X and Y coordinates of a tangent space normal map.
+    Bc5,
+    /// BC6H with unsigned half precision floating point RGB colors, at one
+    /// byte per pixel. Texture images with eight bits per channel map to
+    /// colors from zero to one.
+    Bc6hUfloat,
+    /// BC6H with signed half precision floating point RGB colors, at one
+    /// byte per pixel.
+    Bc6hSfloat,
+    /// BC7. High quality RGBA colors at one byte per pixel.
+    Bc7,
+    /// ETC2 RGB. RGB colors at half a byte per pixel.
+    Etc2Rgb,
+    /// ETC2 RGBA8. RGB colors as in ETC2 RGB with an EAC alpha channel, at
+    /// one byte per pixel.
+    Etc2Rgba8,
+    /// EAC R11. A single red channel with eleven bits of precision, at half a
+    /// byte per pixel.
+    EacR11,
+    /// EAC RG11. Separate red and green channels with eleven bits of
+    /// precision, at one byte per pixel.
+    EacRg11,
+    /// ASTC with 4x4 pixel blocks, at one byte per pixel. ASTC stores RGB or
+    /// RGBA colors in sixteen byte blocks of every size, so larger blocks
+    /// trade quality for memory. Most mobile GPUs support it.
+    Astc4x4,
+    /// ASTC with 5x4 pixel blocks, at 6.4 bits per pixel.
+    Astc5x4,
+    /// ASTC with 5x5 pixel blocks, at 5.12 bits per pixel.
+    Astc5x5,
+    /// ASTC with 6x5 pixel blocks, at 4.27 bits per pixel.
+    Astc6x5,
+    /// ASTC with 6x6 pixel blocks, at 3.56 bits per pixel.
+    Astc6x6,
+    /// ASTC with 8x5 pixel blocks, at 3.2 bits per pixel.
+    Astc8x5,
+    /// ASTC with 8x6 pixel blocks, at 2.67 bits per pixel.
+    Astc8x6,
+    /// ASTC with 8x8 pixel blocks, at 2 bits per pixel.
+    Astc8x8,
+    /// ASTC with 10x5 pixel blocks, at 2.56 bits per pixel.
+    Astc10x5,
+    /// ASTC with 10x6 pixel blocks, at 2.13 bits per pixel.
+    Astc10x6,
+    /// ASTC with 10x8 pixel blocks, at 1.6 bits per pixel.
+    Astc10x8,
+    /// ASTC with 10x10 pixel blocks, at 1.28 bits per pixel.
+    Astc10x10,
+    /// ASTC with 12x10 pixel blocks, at 1.07 bits per pixel.
+    Astc12x10,
+    /// ASTC with 12x12 pixel blocks, at 0.89 bits per pixel.
+    Astc12x12,
+}
+
+impl CompressedFormat {
+    /// The width and height in pixels of a block.
+    pub fn block_dimensions(self) -> (u32, u32) {
+        match self {
+            CompressedFormat::Astc4x4 => (4, 4),
+            CompressedFormat::Astc5x4 => (5, 4),
+            CompressedFormat::Astc5x5 => (5, 5),
+            CompressedFormat::Astc6x5 => (6, 5),
+            CompressedFormat::Astc6x6 => (6, 6),
+            CompressedFormat::Astc8x5 => (8, 5),
+            CompressedFormat::Astc8x6 => (8, 6),
+            CompressedFormat::Astc8x8 => (8, 8),
+            CompressedFormat::Astc10x5 => (10, 5),
+            CompressedFormat::Astc10x6 => (10, 6),
+            CompressedFormat::Astc10x8 => (10, 8),
+            CompressedFormat::Astc10x10 => (10, 10),
+            CompressedFormat::Astc12x10 => (12, 10),
+            CompressedFormat::Astc12x12 => (12, 12),
+            // The BC, ETC2 and EAC formats all have four by four blocks.
+            _ => (4, 4),
+        }
+    }
+
+    /// The number of bytes a block takes up.
+    pub fn bytes_per_block(self) -> usize {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc4 => 8,
+            CompressedFormat::Bc2 | CompressedFormat::Bc3 | CompressedFormat::Bc5 => 16,
+            CompressedFormat::Bc6hUfloat | CompressedFormat::Bc6hSfloat | CompressedFormat::Bc7 => 16,
+            CompressedFormat::Etc2Rgb | CompressedFormat::EacR11 => 8,
+            CompressedFormat::Etc2Rgba8 | CompressedFormat::EacRg11 => 16,
+            // Every ASTC block takes up 128 bits, whatever its size.
+            _ => 16,
+        }
+    }
+
+    /// The number of blocks across and down a texture image of the given
+    /// dimensions. Partial blocks along the edges count as whole blocks.
+    pub fn block_count(self, width: u32, height: u32) -> (u32, u32) {
+        let (block_width, block_height) = self.block_dimensions();
+
+        (width.div_ceil(block_width), height.div_ceil(block_height))
+    }
+
+    /// The number of bytes a texture image of the given dimensions takes up
+    /// in this format.
+    pub fn compressed_size(self, width: u32, height: u32) -> usize {
+        let (blocks_across, blocks_down) = self.block_count(width, height);
+
+        (blocks_across as usize) * (blocks_down as usize) * self.bytes_per_block()
+    }
+}
+
+impl fmt::Display for CompressedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            CompressedFormat::Bc1 => "BC1",
+            CompressedFormat::Bc2 => "BC2",
+            CompressedFormat::Bc3 => "BC3",
+            CompressedFormat::Bc4 => "BC4",
+            CompressedFormat::Bc5 => "BC5",
+            CompressedFormat::Bc6hUfloat => "BC6H UFLOAT",
+            CompressedFormat::Bc6hSfloat => "BC6H SFLOAT",
+            CompressedFormat::Bc7 => "BC7",
+            CompressedFormat::Etc2Rgb => "ETC2 RGB",
+            CompressedFormat::Etc2Rgba8 => "ETC2 RGBA8",
+            CompressedFormat::EacR11 => "EAC R11",
+            CompressedFormat::EacRg11 => "EAC RG11",
+            _ => {
+                let (block_width, block_height) = self.block_dimensions();
+                return write!(f, "ASTC {}x{}", block_width, block_height);
+            }
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// How much time the encoder spends searching for the best encoding of
+/// each block.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// Fits the endpoints of each block along the principal axis of its
+    /// pixels, without refining them.
+    Fast,
+    /// Fits the endpoints of each block along the principal axis of its
+    /// pixels, and refines them to lower the error.
+    #[default]
+    Normal,
+    /// Refines the endpoints of each block further, and tries every
+    /// encoding mode the encoder supports.
+    Best,
+}
+
+/// The ends of the line through the mean of the points along their principal
+/// axis, just long enough to cover the projections of every point. The axis
+/// is found by power iteration, starting from the covariance of the channel
+/// that varies the most with every channel.
+fn principal_endpoints<const N: usize>(points: &[[f32; N]]) -> ([f32; N], [f32; N]) {
+    let mut mean = [0.0; N];
+    for point in points {
+        for channel in 0..N {
+            mean[channel] += point[channel] / (points.len() as f32);
+        }
+    }
+
+    let mut covariance = [[0.0; N]; N];
+    for point in points {
+        for row in 0..N {
+            for column in 0..N {
+                covariance[row][column] += (point[row] - mean[row]) * (point[column] - mean[column]);
+            }
+        }
+    }
+
+    let widest = (0..N).fold(0, |widest, channel| {
+        if covariance[channel][channel] > covariance[widest][widest] { channel } else { widest }
+    });
+    let mut axis = covariance[widest];
+    for _ in 0..8 {
+        let mut next = [0.0; N];
+        for row in 0..N {
+            for column in 0..N {
+                next[row] += covariance[row][column] * axis[column];
+            }
+        }
+        let largest = next.iter().fold(0.0f32, |largest, value| largest.max(value.abs()));
+        if largest == 0.0 {
+            break;
+        }
+        for channel in 0..N {
+            axis[channel] = next[channel] / largest;
+        }
+    }
+
+    let length = axis.iter().map(|value| value * value).sum::<f32>();
+    if length == 0.0 {
+        return (mean, mean);
+    }
+
+    let mut smallest = f32::MAX;
+    let mut largest = f32::MIN;
+    for point in points {
+        let projection = (0..N).map(|channel| (point[channel] - mean[channel]) * axis[channel]).sum::<f32>();
+        smallest = smallest.min(projection);
+        largest = largest.max(projection);
+    }
+
+    let mut start = [0.0; N];
+    let mut end = [0.0; N];
+    for channel in 0..N {
+        start[channel] = (mean[channel] + axis[channel] * smallest / length).clamp(0.0, 255.0);
+        end[channel] = (mean[channel] + axis[channel] * largest / length).clamp(0.0, 255.0);
+    }
+
+    (start, end)
+}
+
+/// The endpoints that minimize the squared error of the points, when each
+/// point is interpolated between the endpoints with the given weight of the
+/// second endpoint. Returns `None` when the weights do not determine the
+/// endpoints, such as when every point has the same weight.
+fn least_squares_endpoints<const N: usize>(
+    points: &[[f32; N]],
+    weights: &[f32]) -> Option<([f32; N], [f32; N])>
+{
+    let mut start_start = 0.0;
+    let mut start_end = 0.0;
+    let mut end_end = 0.0;
+    let mut start_sum = [0.0; N];
+    let mut end_sum = [0.0; N];
+    for (point, &weight) in points.iter().zip(weights) {
+        let inverse = 1.0 - weight;
+        start_start += inverse * inverse;
+        start_end += inverse * weight;
+        end_end += weight * weight;
+        for channel in 0..N {
+            start_sum[channel] += inverse * point[channel];
+            end_sum[channel] += weight * point[channel];
+        }
+    }
+
+    let determinant = start_start * end_end - start_end * start_end;
+    if determinant.abs() < 1.0e-6 {
+        return None;
+    }
+
+    let mut start = [0.0; N];
+    let mut end = [0.0; N];
+    for channel in 0..N {
+        start[channel] = ((end_end * start_sum[channel] - start_end * end_sum[channel]) / determinant)
+            .clamp(0.0, 255.0);
+        end[channel] = ((start_start * end_sum[channel] - start_end * start_sum[channel]) / determinant)
+            .clamp(0.0, 255.0);
+    }
+
+    Some((start, end))
+}
+
+/// The channels of a pixel as floating point values from 0 to 255.
+fn to_point(pixel: Rgba) -> [f32; 4] {
+    [pixel.r as f32, pixel.g as f32, pixel.b as f32, pixel.a as f32]
+}
+
+/// Writes fields of a 128 bit block, starting from the least significant bit.
+struct BitWriter {
+    value: u128,
+    position: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { value: 0, position: 0 }
+    }
+
+    fn write(&mut self, value: u32, bits: u32) {
+        debug_assert!(bits == 32 || value < (1 << bits));
+        self.value |= (value as u128) << self.position;
+        self.position += bits;
+    }
+
+    fn into_bytes(self) -> [u8; 16] {
+        self.value.to_le_bytes()
+    }
+}
+
+/// Reads fields of a 128 bit block, starting from the least significant bit.
+struct BitReader {
+    value: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn new(block: &[u8]) -> BitReader {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&block[0..16]);
+        BitReader { value: u128::from_le_bytes(bytes), position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> u32 {
+        debug_assert!(bits <= 32 && self.position + bits <= 128);
+        let value = self.value.checked_shr(self.position).unwrap_or(0) & ((1 << bits) - 1);
+        self.position += bits;
+
+        value as u32
+    }
+}
+
+/// The pixels of the block whose first pixel is at the given coordinates,
+/// row by row. Pixels past the last column or row of the texture image
+/// repeat the nearest edge pixel, so partial blocks encode as well as whole ones.
+fn block_pixels(
+    image: &TexImage2D<Rgba>,
+    x: u32,
+    y: u32,
+    block_width: u32,
+    block_height: u32) -> Vec<Rgba>
+{
+    let mut pixels = Vec::with_capacity((block_width * block_height) as usize);
+    for row in 0..block_height {
+        let y = (y + row).min(image.height - 1) as usize;
+        for column in 0..block_width {
+            let x = (x + column).min(image.width - 1) as usize;
+            pixels.push(image.data[y * (image.width as usize) + x]);
+        }
+    }
+
+    pixels
+}
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Compress the texture image into blocks of the given format. The blocks
+    /// are stored left to right, in the same row order as the pixel data, so
+    /// the result can be uploaded wherever the uncompressed pixel data could.
+    /// Texture images whose dimensions are not multiples of the block size
+    /// are padded by repeating their edge pixels. The pixels are converted to
+    /// `Rgba` before compressing, and the encoders use only integer and basic
+    /// floating point arithmetic, so the result is identical on every platform.
+    ///
+    /// # Panics
+    /// Panics if the format is BC2 or BC6H, which can only be decompressed.
+    pub fn compress(&self, format: CompressedFormat, quality: Quality) -> Vec<u8> {
+        let mut blocks = Vec::with_capacity(format.compressed_size(self.width, self.height));
+        if self.width == 0 || self.height == 0 {
+            return blocks;
+        }
+
+        let image = self.convert::<Rgba>();
+        let (block_width, block_height) = format.block_dimensions();
+        let (blocks_across, blocks_down) = format.block_count(self.width, self.height);
+        for block_y in 0..blocks_down {
+            for block_x in 0..blocks_across {
+                let pixels = block_pixels(
+                    &image, block_x * block_width, block_y * block_height, block_width, block_height
+                );
+                match format {
+                    CompressedFormat::Bc1 => bc::encode_bc1(&pixels, quality, &mut blocks),
+                    CompressedFormat::Bc3 => bc::encode_bc3(&pixels, quality, &mut blocks),
+                    CompressedFormat::Bc4 => bc::encode_bc4(&pixels, quality, &mut blocks),
+                    CompressedFormat::Bc5 => bc::encode_bc5(&pixels, quality, &mut blocks),
+                    CompressedFormat::Bc2 | CompressedFormat::Bc6hUfloat | CompressedFormat::Bc6hSfloat => {
+                        panic!("{} blocks can be decompressed, but not compressed", format)
+                    }
+                    CompressedFormat::Bc7 => bc7::encode_bc7(&pixels, quality, &mut blocks),
+                    CompressedFormat::Etc2Rgb => etc::encode_etc2_rgb(&pixels, quality, &mut blocks),
+                    CompressedFormat::Etc2Rgba8 => etc::encode_etc2_rgba8(&pixels, quality, &mut blocks),
+                    CompressedFormat::EacR11 => etc::encode_eac_r11(&pixels, quality, &mut blocks),
+                    CompressedFormat::EacRg11 => etc::encode_eac_rg11(&pixels, quality, &mut blocks),
+                    _ => astc::encode_astc(&pixels, block_width, block_height, quality, &mut blocks),
+                }
+            }
+        }
+
+        blocks
+    }
+}
+
+/// Decompress blocks of the given format into a texture image of the given
+/// dimensions, undoing `TexImage2D::compress`. Formats with fewer than four
+/// channels decompress to zero in the missing color channels and opaque
+/// alpha, eleven bit channels are rounded to eight bits, and the half
+/// precision colors of BC6H blocks are clamped to the range from zero to one.
+pub fn decompress(
+    blocks: &[u8],
+    format: CompressedFormat,
+    width: u32,
+    height: u32) -> Result<TexImage2D<Rgba>, TexImage2DError>
+{
+    let expected = format.compressed_size(width, height);
+    if blocks.len() != expected {
+        return Err(TexImage2DError::Decode {
+            path: None,
+            reason: format!(
+                "a {} x {} {} texture image takes {} bytes, but {} bytes were given",
+                width, height, format, expected, blocks.len()
+            ),
+        });
+    }
+
+    let decode_block: fn(&[u8], &mut [Rgba]) = match format {
+        CompressedFormat::Bc1 => bc::decode_bc1,
+        CompressedFormat::Bc2 => bc::decode_bc2,
+        CompressedFormat::Bc3 => bc::decode_bc3,
+        CompressedFormat::Bc4 => bc::decode_bc4,
+        CompressedFormat::Bc5 => bc::decode_bc5,
+        CompressedFormat::Bc6hUfloat => |block, pixels| bc6h::decode_bc6h(block, pixels, false),
+        CompressedFormat::Bc6hSfloat => |block, pixels| bc6h::decode_bc6h(block, pixels, true),
+        CompressedFormat::Bc7 => bc7::decode_bc7,
+        CompressedFormat::Etc2Rgb => etc::decode_etc2_rgb,
+        CompressedFormat::Etc2Rgba8 => etc::decode_etc2_rgba8,
+        CompressedFormat::EacR11 => etc::decode_eac_r11,
+        CompressedFormat::EacRg11 => etc::decode_eac_rg11,
+        CompressedFormat::Astc4x4 => astc::decode_astc::<4, 4>,
+        CompressedFormat::Astc5x4 => astc::decode_astc::<5, 4>,
+        CompressedFormat::Astc5x5 => astc::decode_astc::<5, 5>,
+        CompressedFormat::Astc6x5 => astc::decode_astc::<6, 5>,
+        CompressedFormat::Astc6x6 => astc::decode_astc::<6, 6>,
+        CompressedFormat::Astc8x5 => astc::decode_astc::<8, 5>,
+        CompressedFormat::Astc8x6 => astc::decode_astc::<8, 6>,
+        CompressedFormat::Astc8x8 => astc::decode_astc::<8, 8>,
+        CompressedFormat::Astc10x5 => astc::decode_astc::<10, 5>,
+        CompressedFormat::Astc10x6 => astc::decode_astc::<10, 6>,
+        CompressedFormat::Astc10x8 => astc::decode_astc::<10, 8>,
+        CompressedFormat::Astc10x10 => astc::decode_astc::<10, 10>,
+        CompressedFormat::Astc12x10 => astc::decode_astc::<12, 10>,
+        CompressedFormat::Astc12x12 => astc::decode_astc::<12, 12>,
+    };
+
+    let mut image = TexImage2D::new(width, height);
+    let (block_width, block_height) = format.block_dimensions();
+    let (blocks_across, _) = format.block_count(width, height);
+    let mut pixels = vec![Rgba::default(); (block_width * block_height) as usize];
+    for (i, block) in blocks.chunks(format.bytes_per_block()).enumerate() {
+        decode_block(block, &mut pixels);
+
+        let x = (i as u32 % blocks_across) * block_width;
+        let y = (i as u32 / blocks_across) * block_height;
+        let columns = block_width.min(width - x) as usize;
+        for row in 0..block_height.min(height - y) {
+            let start = ((y + row) * width + x) as usize;
+            let pixels = &pixels[(row * block_width) as usize..];
+            image.data[start..(start + columns)].copy_from_slice(&pixels[..columns]);
+        }
+    }
+
+    Ok(image)
+}
+
+/// The width and height in pixels of a mipmap level of a texture image
+/// with the given base level dimensions.
+fn level_dimensions(width: u32, height: u32, level: usize) -> (u32, u32) {
+    let shift = level.min(31) as u32;
+
+    ((width >> shift).max(1), (height >> shift).max(1))
+}
+
+/// Check that there is at least one mipmap level, no more levels than a full
+/// mipmap chain has, and that each level is the size of the blocks covering
+/// its dimensions.
+fn check_levels<L: AsRef<[u8]>>(
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    levels: &[L]) -> Result<(), TexImage2DError>
+{
+    let invalid = |reason: String| Err(TexImage2DError::InvalidLayout { path: None, reason: reason });
+    if levels.is_empty() {
+        return invalid(format!("a {} texture image needs at least one mipmap level", format));
+    }
+    if levels.len() > mip_level_count(width, height) {
+        return invalid(format!(
+            "a {} x {} texture image has at most {} mipmap levels, but {} levels were given",
+            width, height, mip_level_count(width, height), levels.len()
+        ));
+    }
+
+    for (level, blocks) in levels.iter().enumerate() {
+        let (level_width, level_height) = level_dimensions(width, height, level);
+        let expected = format.compressed_size(level_width, level_height);
+        if blocks.as_ref().len() != expected {
+            return invalid(format!(
+                "mipmap level {} of a {} x {} {} texture image takes {} bytes, but {} bytes were given",
+                level, width, height, format, expected, blocks.as_ref().len()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A texture image compressed into blocks of a GPU format, with its mipmap
+/// levels. Each level holds the blocks of a level in the order
+/// `TexImage2D::compress` stores them, so a level can be handed to
+/// `glCompressedTexImage2D` or a wgpu texture upload as is. The first level
+/// is the base level, and each following level has half the width and
+/// height of the previous level, rounded down.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressedTexImage2D {
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    levels: Vec<Vec<u8>>,
+}
+
+impl CompressedTexImage2D {
+    /// Construct a compressed texture image from the blocks of its mipmap
+    /// levels. Returns an error if there are no levels, more levels than a
+    /// full mipmap chain has, or a level whose length is not the size of
+    /// the blocks covering its dimensions.
+    pub fn new(
+        format: CompressedFormat,
+        width: u32,
+        height: u32,
+        levels: Vec<Vec<u8>>) -> Result<CompressedTexImage2D, TexImage2DError>
+    {
+        check_levels(format, width, height, &levels)?;
+
+        Ok(CompressedTexImage2D {
+            format: format,
+            width: width,
+            height: height,
+            levels: levels,
+        })
+    }
+
+    /// Compress a texture image into a compressed texture image with a
+    /// single level.
+    pub fn from_image<P: Pixel>(
+        image: &TexImage2D<P>,
+        format: CompressedFormat,
+        quality: Quality) -> CompressedTexImage2D
+    {
+        CompressedTexImage2D {
+            format: format,
+            width: image.width,
+            height: image.height,
+            levels: vec![image.compress(format, quality)],
+        }
+    }
+
+    /// Compress every level of a mipmap chain into a compressed texture image.
+    /// Returns an error if the chain is empty, or its levels do not halve in
+    /// size.
+    pub fn from_mip_chain<P: Pixel>(
+        chain: &MipChain<P>,
+        format: CompressedFormat,
+        quality: Quality) -> Result<CompressedTexImage2D, TexImage2DError>
+    {
+        let base = match chain.base() {
+            Some(base) => base,
+            None => return CompressedTexImage2D::new(format, 0, 0, vec![]),
+        };
+        let levels = chain.iter().map(|level| level.compress(format, quality)).collect();
+
+        CompressedTexImage2D::new(format, base.width, base.height, levels)
+    }
+
+    /// The compressed format the blocks are stored in.
+    pub fn format(&self) -> CompressedFormat {
+        self.format
+    }
+
+    /// The width in pixels of the base level.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height in pixels of the base level.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The blocks of every mipmap level, starting with the base level.
+    pub fn levels(&self) -> &[Vec<u8>] {
+        &self.levels
+    }
+
+    /// Take the blocks of every mipmap level, starting with the base level.
+    pub fn into_levels(self) -> Vec<Vec<u8>> {
+        self.levels
+    }
+
+    /// The number of mipmap levels, including the base level.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The blocks of a mipmap level. Level zero is the base level.
+    pub fn level(&self, level: usize) -> Option<&[u8]> {
+        self.levels.get(level).map(|blocks| blocks.as_slice())
+    }
+
+    /// The width and height in pixels of a mipmap level.
+    pub fn level_dimensions(&self, level: usize) -> (u32, u32) {
+        level_dimensions(self.width, self.height, level)
+    }
+
+    /// Decompress the base level into a texture image, for example to make a
+    /// thumbnail of a texture loaded from a DDS or KTX file.
+    pub fn decompress(&self) -> TexImage2D<Rgba> {
+        self.decompress_level(0).expect("A compressed texture image always has a base level")
+    }
+
+    /// Decompress a mipmap level into a texture image. Returns `None` if there
+    /// is no such level.
+    pub fn decompress_level(&self, level: usize) -> Option<TexImage2D<Rgba>> {
+        let blocks = self.level(level)?;
+        let (width, height) = self.level_dimensions(level);
+
+        decompress(blocks, self.format, width, height).ok()
+    }
+}
+
+/// A borrowed view of a compressed texture image. Generated code embeds the
+/// blocks of each mipmap level in a `static`, and hands out a view of them,
+/// so the blocks are not copied when the texture image is used.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CompressedTexImage2DRef<'a> {
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    levels: &'a [&'a [u8]],
+}
+
+impl<'a> CompressedTexImage2DRef<'a> {
+    /// Construct a view of the blocks of the mipmap levels of a compressed
+    /// texture image. Returns an error in the same cases as
+    /// `CompressedTexImage2D::new`.
+    pub fn new(
+        format: CompressedFormat,
+        width: u32,
+        height: u32,
+        levels: &'a [&'a [u8]]) -> Result<CompressedTexImage2DRef<'a>, TexImage2DError>
+    {
+        check_levels(format, width, height, levels)?;
+
+        Ok(CompressedTexImage2DRef {
+            format: format,
+            width: width,
+            height: height,
+            levels: levels,
+        })
+    }
+
+    /// The compressed format the blocks are stored in.
+    pub fn format(&self) -> CompressedFormat {
+        self.format
+    }
+
+    /// The width in pixels of the base level.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height in pixels of the base level.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The blocks of every mipmap level, starting with the base level.
+    pub fn levels(&self) -> &'a [&'a [u8]] {
+        self.levels
+    }
+
+    /// The number of mipmap levels, including the base level.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The blocks of a mipmap level. Level zero is the base level.
+    pub fn level(&self, level: usize) -> Option<&'a [u8]> {
+        self.levels.get(level).copied()
+    }
+
+    /// The width and height in pixels of a mipmap level.
+    pub fn level_dimensions(&self, level: usize) -> (u32, u32) {
+        level_dimensions(self.width, self.height, level)
+    }
+
+    /// Decompress the base level into a texture image.
+    pub fn decompress(&self) -> TexImage2D<Rgba> {
+        self.decompress_level(0).expect("A compressed texture image always has a base level")
+    }
+
+    /// Decompress a mipmap level into a texture image. Returns `None` if there
+    /// is no such level.
+    pub fn decompress_level(&self, level: usize) -> Option<TexImage2D<Rgba>> {
+        let blocks = self.level(level)?;
+        let (width, height) = self.level_dimensions(level);
+
+        decompress(blocks, self.format, width, height).ok()
+    }
+
+    /// Copy the blocks into an owned compressed texture image.
+    pub fn to_compressed_tex_image(&self) -> CompressedTexImage2D {
+        CompressedTexImage2D {
+            format: self.format,
+            width: self.width,
+            height: self.height,
+            levels: self.levels.iter().map(|blocks| blocks.to_vec()).collect(),
+        }
+    }
+}
+
+impl<'a> From<CompressedTexImage2DRef<'a>> for CompressedTexImage2D {
+    fn from(view: CompressedTexImage2DRef<'a>) -> CompressedTexImage2D {
+        view.to_compressed_tex_image()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_compressed_size_rounds_up_to_whole_blocks() {
+        assert_eq!(CompressedFormat::Bc1.block_count(5, 8), (2, 2));
+        assert_eq!(CompressedFormat::Bc1.compressed_size(5, 8), 32);
+        assert_eq!(CompressedFormat::Bc7.compressed_size(1, 1), 16);
+        assert_eq!(CompressedFormat::Bc4.compressed_size(0, 4), 0);
+    }
+
+    #[test]
+    fn test_block_pixels_repeat_the_edge_pixels() {
+        let data = (0..6).map(|i| Rgba::new(i, 0, 0, 255)).collect();
+        let image = TexImage2D::from_data(3, 2, data);
+        let pixels = block_pixels(&image, 0, 0, 4, 4);
+        let red: Vec<u8> = pixels.iter().map(|pixel| pixel.r).collect();
+
+        assert_eq!(red, vec![0, 1, 2, 2, 3, 4, 5, 5, 3, 4, 5, 5, 3, 4, 5, 5]);
+    }
+
+    #[test]
+    fn test_compress_produces_one_block_per_four_by_four_pixels() {
+        let image: TexImage2D<Rgba> = TexImage2D::new(10, 6);
+        for &format in &[
+            CompressedFormat::Bc1, CompressedFormat::Bc3, CompressedFormat::Bc4, CompressedFormat::Bc5,
+            CompressedFormat::Bc7, CompressedFormat::Etc2Rgb, CompressedFormat::Etc2Rgba8,
+            CompressedFormat::EacR11, CompressedFormat::EacRg11,
+        ] {
+            assert_eq!(image.compress(format, Quality::Fast).len(), format.compressed_size(10, 6));
+        }
+    }
+
+    #[test]
+    fn test_astc_block_dimensions_follow_the_format() {
+        assert_eq!(CompressedFormat::Astc12x10.block_count(10, 6), (1, 1));
+        assert_eq!(CompressedFormat::Astc5x4.block_count(10, 6), (2, 2));
+        assert_eq!(CompressedFormat::Astc10x8.compressed_size(21, 9), 6 * 16);
+        assert_eq!(CompressedFormat::Astc8x5.to_string(), "ASTC 8x5");
+
+        let image: TexImage2D<Rgba> = TexImage2D::new(10, 6);
+        let blocks = image.compress(CompressedFormat::Astc6x5, Quality::Fast);
+        assert_eq!(blocks.len(), CompressedFormat::Astc6x5.compressed_size(10, 6));
+        assert_eq!(decompress(&blocks, CompressedFormat::Astc6x5, 10, 6).unwrap(), image);
+    }
+
+    #[test]
+    fn test_compressed_tex_image_validates_every_level() {
+        let format = CompressedFormat::Bc1;
+        let levels = vec![vec![0; 6 * 8], vec![0; 2 * 8], vec![0; 8], vec![0; 8]];
+        let image = CompressedTexImage2D::new(format, 10, 6, levels).unwrap();
+        assert_eq!(image.level_count(), 4);
+        assert_eq!(image.level_dimensions(1), (5, 3));
+        assert_eq!(image.level_dimensions(3), (1, 1));
+        assert_eq!(image.level(1).map(|blocks| blocks.len()), Some(16));
+
+        assert!(CompressedTexImage2D::new(format, 10, 6, vec![]).is_err());
+        assert!(CompressedTexImage2D::new(format, 10, 6, vec![vec![0; 16]]).is_err());
+        assert!(CompressedTexImage2D::new(format, 10, 6, vec![vec![0; 48], vec![0; 8]]).is_err());
+        let too_many = vec![vec![0; 48], vec![0; 16], vec![0; 8], vec![0; 8], vec![0; 8]];
+        assert!(CompressedTexImage2D::new(format, 10, 6, too_many).is_err());
+    }
+
+    #[test]
+    fn test_compressed_tex_image_from_mip_chain_compresses_every_level() {
+        let image: TexImage2D<Rgba> = TexImage2D::new(12, 10);
+        let chain = image.generate_mipmaps(crate::Filter::Box);
+        let format = CompressedFormat::Astc6x5;
+        let compressed = CompressedTexImage2D::from_mip_chain(&chain, format, Quality::Fast).unwrap();
+
+        assert_eq!(compressed.level_count(), 4);
+        assert_eq!(compressed.level(0), Some(&image.compress(format, Quality::Fast)[..]));
+        assert_eq!(compressed.level(3).map(|blocks| blocks.len()), Some(16));
+        let empty: MipChain = MipChain::from_levels(vec![]);
+        assert!(CompressedTexImage2D::from_mip_chain(&empty, format, Quality::Fast).is_err());
+    }
+
+    #[test]
+    fn test_compressed_tex_image_decompresses_every_level() {
+        let data = (0..48).map(|i| Rgba::new(5 * i as u8, 255 - 5 * i as u8, 0, 255)).collect();
+        let image = TexImage2D::from_data(8, 6, data);
+        let chain = image.generate_mipmaps(crate::Filter::Box);
+        let compressed = CompressedTexImage2D::from_mip_chain(&chain, CompressedFormat::Bc7, Quality::Normal).unwrap();
+
+        assert_eq!(compressed.decompress(), decompress(&compressed.levels()[0], compressed.format(), 8, 6).unwrap());
+        assert_eq!(compressed.decompress_level(2).map(|level| (level.width, level.height)), Some((2, 1)));
+        assert_eq!(compressed.decompress_level(4), None);
+    }
+
+    #[test]
+    fn test_compressed_tex_image_view_borrows_the_blocks_of_every_level() {
+        static BASE: [u8; 16] = [0; 16];
+        static LEVEL_1: [u8; 8] = [0; 8];
+        static LEVELS: [&[u8]; 2] = [&BASE, &LEVEL_1];
+        let view = CompressedTexImage2DRef::new(CompressedFormat::Bc1, 8, 4, &LEVELS).unwrap();
+        let owned = view.to_compressed_tex_image();
+
+        assert_eq!(view.level(1).map(|blocks| blocks.as_ptr()), Some(LEVEL_1.as_ptr()));
+        assert_eq!(owned.levels(), &[BASE.to_vec(), LEVEL_1.to_vec()][..]);
+        assert_eq!(view.decompress(), owned.decompress());
+        assert!(CompressedTexImage2DRef::new(CompressedFormat::Bc1, 8, 4, &LEVELS[1..]).is_err());
+    }
+}
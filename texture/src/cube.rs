@@ -0,0 +1,301 @@
+use crate::array::{check_grid, grid_tile};
+use crate::{load_file_with, LoadOptions, Pixel, TexImage2D, TexImage2DError};
+use std::convert::TryInto;
+use std::fmt;
+use std::path::Path;
+
+
+/// The faces of a cube map, in the order graphics APIs number them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// Every cube map face, in order.
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// The index of the face in a cube map.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for CubeFace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            CubeFace::PositiveX => "+X",
+            CubeFace::NegativeX => "-X",
+            CubeFace::PositiveY => "+Y",
+            CubeFace::NegativeY => "-Y",
+            CubeFace::PositiveZ => "+Z",
+            CubeFace::NegativeZ => "-Z",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The ways the six faces of a cube map can be laid out in a single image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CubeLayout {
+    /// A grid four faces wide and three faces high. The middle row holds the
+    /// -X, +Z, +X and -Z faces from left to right, and the +Y and -Y faces sit
+    /// above and below the +Z face.
+    HorizontalCross,
+    /// A column of the six faces in order, with the +X face at the top.
+    VerticalStrip,
+}
+
+impl CubeLayout {
+    /// Determine the layout of a cube map image from its dimensions.
+    pub fn detect(width: u32, height: u32) -> Option<CubeLayout> {
+        if width > 0 && width.is_multiple_of(4) && width / 4 * 3 == height {
+            Some(CubeLayout::HorizontalCross)
+        } else if width > 0 && width.checked_mul(6) == Some(height) {
+            Some(CubeLayout::VerticalStrip)
+        } else {
+            None
+        }
+    }
+
+    /// The number of columns and rows of faces in the layout.
+    fn grid(self) -> (u32, u32) {
+        match self {
+            CubeLayout::HorizontalCross => (4, 3),
+            CubeLayout::VerticalStrip => (1, 6),
+        }
+    }
+
+    /// The column and row of a face in the layout, counting rows from the top.
+    fn tile(self, face: CubeFace) -> (u32, u32) {
+        match self {
+            CubeLayout::HorizontalCross => match face {
+                CubeFace::PositiveX => (2, 1),
+                CubeFace::NegativeX => (0, 1),
+                CubeFace::PositiveY => (1, 0),
+                CubeFace::NegativeY => (1, 2),
+                CubeFace::PositiveZ => (1, 1),
+                CubeFace::NegativeZ => (3, 1),
+            },
+            CubeLayout::VerticalStrip => (0, face.index() as u32),
+        }
+    }
+}
+
+/// A cube map texture made of six square faces of identical size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TexImageCube<P = crate::Rgba> {
+    pub faces: [TexImage2D<P>; 6],
+}
+
+impl<P: Pixel> TexImageCube<P> {
+    /// Construct a cube map from its faces, ordered the same way as
+    /// `CubeFace::ALL`. Every face must be square, and every face must have
+    /// the same size.
+    pub fn from_faces(faces: [TexImage2D<P>; 6]) -> Result<TexImageCube<P>, TexImage2DError> {
+        let size = faces[0].width;
+        for (face, image) in CubeFace::ALL.iter().zip(faces.iter()) {
+            check_face(*face, image, size).map_err(invalid_layout)?;
+        }
+
+        Ok(TexImageCube {
+            faces: faces,
+        })
+    }
+
+    /// Split a cube map image laid out as a horizontal cross or a vertical strip
+    /// into its faces, assuming the image is stored bottom row first the way the
+    /// loaders store it by default.
+    pub fn from_layout(image: &TexImage2D<P>, layout: CubeLayout) -> Result<TexImageCube<P>, TexImage2DError> {
+        layout_faces(image, layout, true, false).map_err(invalid_layout)
+    }
+
+    /// The width and height of every face.
+    pub fn size(&self) -> u32 {
+        self.faces[0].width
+    }
+
+    pub fn face(&self, face: CubeFace) -> &TexImage2D<P> {
+        &self.faces[face.index()]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, TexImage2D<P>> {
+        self.faces.iter()
+    }
+}
+
+impl<'a, P: Pixel> IntoIterator for &'a TexImageCube<P> {
+    type Item = &'a TexImage2D<P>;
+    type IntoIter = std::slice::Iter<'a, TexImage2D<P>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.faces.iter()
+    }
+}
+
+fn invalid_layout(reason: String) -> TexImage2DError {
+    TexImage2DError::InvalidLayout { path: None, reason: reason }
+}
+
+fn check_face<P: Pixel>(face: CubeFace, image: &TexImage2D<P>, size: u32) -> Result<(), String> {
+    if image.width == 0 || image.height == 0 {
+        return Err(format!("cube map face {} has no pixels", face));
+    }
+    if image.pixel_count() != (image.width * image.height) as usize {
+        return Err(format!("cube map face {} data does not match its dimensions", face));
+    }
+    if image.width != image.height {
+        return Err(format!("cube map face {} is {} x {}, but faces must be square", face, image.width, image.height));
+    }
+    if image.width != size {
+        return Err(format!(
+            "cube map face {} is {} x {}, but the first face is {} x {}",
+            face, image.width, image.height, size, size
+        ));
+    }
+
+    Ok(())
+}
+
+fn layout_faces<P: Pixel>(
+    image: &TexImage2D<P>,
+    layout: CubeLayout,
+    flipped_vertically: bool,
+    flipped_horizontally: bool) -> Result<TexImageCube<P>, String>
+{
+    let (columns, rows) = layout.grid();
+    check_grid(image, columns, rows)?;
+    if image.width / columns != image.height / rows {
+        return Err(format!(
+            "a {} x {} image does not hold square faces in a {:?} layout",
+            image.width, image.height, layout
+        ));
+    }
+
+    let faces: Vec<TexImage2D<P>> = CubeFace::ALL.iter().map(|&face| {
+        let (column, row) = layout.tile(face);
+        grid_tile(image, columns, rows, column, row, flipped_vertically, flipped_horizontally)
+    }).collect();
+
+    Ok(TexImageCube {
+        faces: faces.try_into().unwrap_or_else(|_| unreachable!()),
+    })
+}
+
+/// Load a cube map from six image files, one per face, ordered the same way
+/// as `CubeFace::ALL`. Every image is converted to the pixel format of the
+/// cube map.
+pub fn load_cube_faces<P, Q>(file_paths: &[Q; 6], options: &LoadOptions) -> Result<TexImageCube<P>, TexImage2DError>
+where
+    P: Pixel,
+    Q: AsRef<Path>,
+{
+    let mut faces: Vec<TexImage2D<P>> = Vec::with_capacity(6);
+    for (face, file_path) in CubeFace::ALL.iter().zip(file_paths.iter()) {
+        let file_path = file_path.as_ref();
+        let image = load_file_with(file_path, options)?.image.convert();
+        let size = faces.first().map_or(image.width, |first| first.width);
+        check_face(*face, &image, size).map_err(|reason| invalid_layout(reason).with_path(file_path))?;
+        faces.push(image);
+    }
+
+    Ok(TexImageCube {
+        faces: faces.try_into().unwrap_or_else(|_| unreachable!()),
+    })
+}
+
+/// Load a cube map from an image file holding all six faces in a horizontal
+/// cross or vertical strip layout.
+pub fn load_cube_layout<P, Q>(file_path: Q, layout: CubeLayout, options: &LoadOptions) -> Result<TexImageCube<P>, TexImage2DError>
+where
+    P: Pixel,
+    Q: AsRef<Path>,
+{
+    let file_path = file_path.as_ref();
+    let image: TexImage2D<P> = load_file_with(file_path, options)?.image.convert();
+
+    layout_faces(&image, layout, options.flip_vertically, options.flip_horizontally)
+        .map_err(|reason| invalid_layout(reason).with_path(file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::R8;
+
+
+    /// A horizontal cross image whose faces are filled with their face index,
+    /// stored bottom row first.
+    fn cross_image(size: u32) -> TexImage2D<R8> {
+        let mut image = TexImage2D::from_data(4 * size, 3 * size, vec![R8::new(0xFF); (12 * size * size) as usize]);
+        for face in CubeFace::ALL.iter() {
+            let (column, row) = CubeLayout::HorizontalCross.tile(*face);
+            for y in 0..size {
+                for x in 0..size {
+                    let index = (2 - row) * size * image.width + y * image.width + column * size + x;
+                    image.data[index as usize] = R8::new(face.index() as u8);
+                }
+            }
+        }
+
+        image
+    }
+
+    #[test]
+    fn test_detect_layout() {
+        assert_eq!(CubeLayout::detect(64, 48), Some(CubeLayout::HorizontalCross));
+        assert_eq!(CubeLayout::detect(16, 96), Some(CubeLayout::VerticalStrip));
+        assert_eq!(CubeLayout::detect(64, 64), None);
+    }
+
+    #[test]
+    fn test_from_horizontal_cross_extracts_faces() {
+        let cube = TexImageCube::from_layout(&cross_image(2), CubeLayout::HorizontalCross).unwrap();
+
+        assert_eq!(cube.size(), 2);
+        for face in CubeFace::ALL.iter() {
+            assert_eq!(cube.face(*face).data, vec![R8::new(face.index() as u8); 4]);
+        }
+    }
+
+    #[test]
+    fn test_from_vertical_strip_extracts_faces_from_the_top() {
+        let data = (0..6).rev().map(R8::new).collect();
+        let strip = TexImage2D::from_data(1, 6, data);
+        let cube = TexImageCube::from_layout(&strip, CubeLayout::VerticalStrip).unwrap();
+
+        assert_eq!(cube.face(CubeFace::PositiveX).data, vec![R8::new(0)]);
+        assert_eq!(cube.face(CubeFace::NegativeZ).data, vec![R8::new(5)]);
+    }
+
+    #[test]
+    fn test_from_faces_rejects_non_square_face() {
+        let mut faces: Vec<TexImage2D<R8>> = (0..6).map(|_| TexImage2D::new(4, 4)).collect();
+        faces[2] = TexImage2D::new(4, 2);
+        let error = TexImageCube::from_faces(faces.try_into().unwrap()).unwrap_err();
+
+        assert!(format!("{}", error).contains("face +Y is 4 x 2, but faces must be square"));
+    }
+
+    #[test]
+    fn test_from_faces_rejects_mismatched_face_sizes() {
+        let mut faces: Vec<TexImage2D<R8>> = (0..6).map(|_| TexImage2D::new(4, 4)).collect();
+        faces[5] = TexImage2D::new(2, 2);
+        let error = TexImageCube::from_faces(faces.try_into().unwrap()).unwrap_err();
+
+        assert!(format!("{}", error).contains("face -Z is 2 x 2, but the first face is 4 x 4"));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::TexImage2DError;
+#[cfg(feature = "png-decoder")]
+use std::io;
+
+
+/// The channel data of a decoded image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodedData {
+    /// Eight bit channels, from low dynamic range images.
+    U8(Vec<u8>),
+    /// Floating point channels, from high dynamic range images.
+    F32(Vec<f32>),
+}
+
+/// An image decoded by a `Decoder`, before any load options are applied.
+/// The channel data is stored top row first, with the channels of each pixel
+/// in RGBA order, or gray and alpha order for one and two channel images.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedImage {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub data: DecodedData,
+}
+
+/// An image decoding backend. Every loader decodes the image data with a
+/// decoder, and then applies the load options to the decoded image in the
+/// same way whichever decoder was used.
+pub trait Decoder {
+    /// Decode an image file held in memory. The decoded image is converted
+    /// to the desired number of channels, or keeps the number of channels
+    /// stored in the image file when the desired number of channels is zero.
+    fn decode(&self, buffer: &[u8], desired_channels: usize) -> Result<DecodedImage, TexImage2DError>;
+}
+
+/// The decoder the loaders use unless told otherwise. This is the
+/// `stb_image` decoder when it is enabled, since it reads the most formats.
+pub(crate) fn default_decoder() -> &'static dyn Decoder {
+    #[cfg(feature = "stb-decoder")]
+    {
+        &StbDecoder
+    }
+    #[cfg(not(feature = "stb-decoder"))]
+    {
+        &PngDecoder
+    }
+}
+
+#[cfg(not(any(feature = "stb-decoder", feature = "png-decoder")))]
+compile_error!("At least one of the `stb-decoder` and `png-decoder` features must be enabled.");
+
+
+#[cfg(feature = "stb-decoder")]
+pub use self::stb::StbDecoder;
+
+#[cfg(feature = "stb-decoder")]
+mod stb {
+    use super::{DecodedData, DecodedImage, Decoder};
+    use crate::TexImage2DError;
+    use stb_image::image;
+    use stb_image::image::LoadResult;
+    use stb_image::stb_image::bindgen;
+    use std::ffi::CStr;
+    use std::os::raw::c_int;
+    use std::sync::Mutex;
+
+
+    /// The `stb_image` decoder. It reads PNG, JPEG, TGA, BMP, PSD, GIF, HDR,
+    /// PIC, and PNM files, and loads high dynamic range images as floating
+    /// point data.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+    pub struct StbDecoder;
+
+    /// The decoder records the reason for its most recent failure in a global
+    /// variable, so calls into the decoder are serialized to keep the reason
+    /// attached to the call that failed.
+    static DECODER_LOCK: Mutex<()> = Mutex::new(());
+
+    /// The reason the decoder gave for its most recent failure.
+    fn decoder_failure_reason() -> String {
+        let reason = unsafe { bindgen::stbi_failure_reason() };
+        let reason = if reason.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(reason) }.to_string_lossy().into_owned()
+        };
+
+        if reason.is_empty() {
+            String::from("unknown reason")
+        } else {
+            reason
+        }
+    }
+
+    /// The dimensions recorded in the image header, if the decoder can read it.
+    fn image_dimensions(buffer: &[u8]) -> Option<(usize, usize)> {
+        let mut width = 0 as c_int;
+        let mut height = 0 as c_int;
+        let mut channels = 0 as c_int;
+        let found = unsafe {
+            bindgen::stbi_info_from_memory(
+                buffer.as_ptr(), buffer.len() as c_int, &mut width, &mut height, &mut channels
+            )
+        };
+        if found != 0 {
+            Some((width as usize, height as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Classify a decoder failure reason into an error.
+    fn decode_error(buffer: &[u8], reason: String) -> TexImage2DError {
+        match reason.as_str() {
+            "unknown image type" | "unsupported format" | "unsupported data layout" | "1/2/4/8-bit only" |
+            "only 8-bit" | "no header height" | "BMP RLE" | "monochrome" | "unknown BMP" |
+            "wrong channel count" | "wrong version" => {
+                TexImage2DError::UnsupportedFormat { path: None, reason: reason }
+            }
+            "too large" => match image_dimensions(buffer) {
+                Some((width, height)) => {
+                    TexImage2DError::UnsupportedDimensions { path: None, width: width, height: height }
+                }
+                None => TexImage2DError::Decode { path: None, reason: reason },
+            },
+            _ => TexImage2DError::Decode { path: None, reason: reason },
+        }
+    }
+
+    impl Decoder for StbDecoder {
+        fn decode(&self, buffer: &[u8], desired_channels: usize) -> Result<DecodedImage, TexImage2DError> {
+            if buffer.len() > c_int::MAX as usize {
+                return Err(TexImage2DError::UnsupportedFormat {
+                    path: None,
+                    reason: String::from("image buffer is too large for the decoder"),
+                });
+            }
+
+            let _guard = DECODER_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match image::load_from_memory_with_depth(buffer, desired_channels, false) {
+                LoadResult::ImageU8(image) => Ok(DecodedImage {
+                    width: image.width,
+                    height: image.height,
+                    depth: image.depth,
+                    data: DecodedData::U8(image.data),
+                }),
+                LoadResult::ImageF32(image) => Ok(DecodedImage {
+                    width: image.width,
+                    height: image.height,
+                    depth: image.depth,
+                    data: DecodedData::F32(image.data),
+                }),
+                LoadResult::Error(_) => Err(decode_error(buffer, decoder_failure_reason())),
+            }
+        }
+    }
+}
+
+
+/// A decoder for PNG files written in pure Rust on top of the `png` crate.
+/// Sixteen bit images are reduced to eight bits per channel, the same way
+/// the `stb_image` decoder reduces them.
+#[cfg(feature = "png-decoder")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PngDecoder;
+
+#[cfg(feature = "png-decoder")]
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// The luma of an RGB color, using the same integer weights as `stb_image`.
+#[cfg(feature = "png-decoder")]
+#[inline]
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 77 + g as u32 * 150 + b as u32 * 29) >> 8) as u8
+}
+
+/// Convert eight bit channel data between channel counts. Gray is expanded
+/// into every color channel, color is reduced to its luma, and a missing
+/// alpha channel is filled in as opaque.
+#[cfg(feature = "png-decoder")]
+fn convert_channels(data: &[u8], depth: usize, desired_channels: usize) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() / depth * desired_channels);
+    for pixel in data.chunks(depth) {
+        let (r, g, b, a) = match depth {
+            1 => (pixel[0], pixel[0], pixel[0], 255),
+            2 => (pixel[0], pixel[0], pixel[0], pixel[1]),
+            3 => (pixel[0], pixel[1], pixel[2], 255),
+            _ => (pixel[0], pixel[1], pixel[2], pixel[3]),
+        };
+        let gray = if depth <= 2 { r } else { luma(r, g, b) };
+        match desired_channels {
+            1 => result.push(gray),
+            2 => result.extend_from_slice(&[gray, a]),
+            3 => result.extend_from_slice(&[r, g, b]),
+            _ => result.extend_from_slice(&[r, g, b, a]),
+        }
+    }
+
+    result
+}
+
+#[cfg(feature = "png-decoder")]
+fn png_error(error: png::DecodingError) -> TexImage2DError {
+    match error {
+        png::DecodingError::LimitsExceeded => TexImage2DError::Decode {
+            path: None,
+            reason: String::from("image is too large for the decoder"),
+        },
+        error => TexImage2DError::Decode { path: None, reason: format!("{}", error) },
+    }
+}
+
+#[cfg(feature = "png-decoder")]
+impl Decoder for PngDecoder {
+    fn decode(&self, buffer: &[u8], desired_channels: usize) -> Result<DecodedImage, TexImage2DError> {
+        if !buffer.starts_with(&PNG_SIGNATURE) {
+            return Err(TexImage2DError::UnsupportedFormat {
+                path: None,
+                reason: String::from("not a PNG image"),
+            });
+        }
+
+        let mut decoder = png::Decoder::new(io::Cursor::new(buffer));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(png_error)?;
+        let size = reader.output_buffer_size().ok_or_else(|| {
+            let (width, height) = reader.info().size();
+            TexImage2DError::UnsupportedDimensions { path: None, width: width as usize, height: height as usize }
+        })?;
+        let mut data = vec![0; size];
+        let info = reader.next_frame(&mut data).map_err(png_error)?;
+        data.truncate(info.buffer_size());
+
+        let depth = info.color_type.samples();
+        let (data, depth) = if desired_channels == 0 || desired_channels == depth {
+            (data, depth)
+        } else {
+            (convert_channels(&data, depth, desired_channels), desired_channels)
+        };
+
+        Ok(DecodedImage {
+            width: info.width as usize,
+            height: info.height as usize,
+            depth: depth,
+            data: DecodedData::U8(data),
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "png-decoder")]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_convert_channels() {
+        let rgba = [255, 0, 0, 128];
+
+        assert_eq!(convert_channels(&rgba, 4, 1), vec![76]);
+        assert_eq!(convert_channels(&rgba, 4, 2), vec![76, 128]);
+        assert_eq!(convert_channels(&rgba, 4, 3), vec![255, 0, 0]);
+        assert_eq!(convert_channels(&[7, 200], 2, 4), vec![7, 7, 7, 200]);
+        assert_eq!(convert_channels(&[7], 1, 2), vec![7, 255]);
+    }
+
+    #[test]
+    fn test_png_decoder_rejects_other_formats() {
+        let result = PngDecoder.decode(b"this is not an image", 4);
+
+        match result {
+            Err(TexImage2DError::UnsupportedFormat { ref reason, .. }) => assert_eq!(reason, "not a PNG image"),
+            other => panic!("expected an unsupported format error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "stb-decoder")]
+    #[test]
+    fn test_png_decoder_matches_stb_decoder() {
+        let buffer = std::fs::read("../assets/sample.png").unwrap();
+        for desired_channels in 0..5 {
+            let expected = StbDecoder.decode(&buffer, desired_channels).unwrap();
+            let result = PngDecoder.decode(&buffer, desired_channels).unwrap();
+
+            assert_eq!(result, expected);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{Channel, Pixel, RgbaF32, TexImage2D};
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+
+/// The image file formats a texture image can be encoded to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// PNG files, which require the `png-encoder` feature.
+    #[cfg(feature = "png-encoder")]
+    Png,
+    Tga,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// Determine the image file format from a file extension.
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "png-encoder")]
+            "png" => Some(ImageFormat::Png),
+            "tga" => Some(ImageFormat::Tga),
+            "bmp" => Some(ImageFormat::Bmp),
+            _ => None,
+        }
+    }
+
+    /// Determine the image file format from the extension of a file path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        path.as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension)
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// The rows of a texture image from top to bottom. Texture images are stored
+/// bottom row first, while image files are generally stored top row first.
+#[cfg(feature = "png-encoder")]
+fn rows_top_down<P: Pixel>(tex: &TexImage2D<P>) -> impl Iterator<Item = &[P]> {
+    tex.data.chunks(tex.width as usize).rev()
+}
+
+fn check_dimensions<P: Pixel>(tex: &TexImage2D<P>, max_dimension: u32) -> io::Result<()> {
+    if tex.width == 0 || tex.height == 0 {
+        return Err(invalid_input("Cannot encode a texture image with no pixels."));
+    }
+    if tex.width > max_dimension || tex.height > max_dimension {
+        return Err(invalid_input("Texture image dimensions are too large for the image format."));
+    }
+    if tex.data.len() != (tex.width as usize) * (tex.height as usize) {
+        return Err(invalid_input("Texture image data does not match its dimensions."));
+    }
+
+    Ok(())
+}
+
+/// A grayscale value for single channel formats, which store their value
+/// in the red channel.
+#[inline]
+fn gray(color: RgbaF32) -> u8 {
+    u8::from_f32(color.r)
+}
+
+/// The gray and alpha values of a two channel format, which are stored in its
+/// first and second channels, the same way PNG files store them.
+#[inline]
+fn gray_alpha<P: Pixel>(pixel: &P) -> [u8; 2] {
+    [u8::from_f32(pixel.channel(0).to_f32()), u8::from_f32(pixel.channel(1).to_f32())]
+}
+
+#[inline]
+fn bytes(color: RgbaF32) -> [u8; 4] {
+    [u8::from_f32(color.r), u8::from_f32(color.g), u8::from_f32(color.b), u8::from_f32(color.a)]
+}
+
+#[cfg(feature = "png-encoder")]
+#[inline]
+fn words(color: RgbaF32) -> [u16; 4] {
+    [u16::from_f32(color.r), u16::from_f32(color.g), u16::from_f32(color.b), u16::from_f32(color.a)]
+}
+
+/// Write a texture image as a PNG file. Sixteen bit and floating point formats
+/// are written with sixteen bits per channel, and two channel formats are
+/// written as grayscale with alpha, storing their channels as they are.
+#[cfg(feature = "png-encoder")]
+fn write_png<P: Pixel, W: Write>(tex: &TexImage2D<P>, writer: &mut W) -> io::Result<()> {
+    use png::{BitDepth, ColorType};
+
+    check_dimensions(tex, u32::MAX)?;
+    let sixteen_bit = P::FORMAT.bytes_per_channel() > 1;
+    let color_type = match P::CHANNEL_COUNT {
+        1 => ColorType::Grayscale,
+        2 => ColorType::GrayscaleAlpha,
+        3 => ColorType::Rgb,
+        _ => ColorType::Rgba,
+    };
+    let channels = color_type.samples();
+    let mut data = Vec::with_capacity(tex.pixel_count() * channels * if sixteen_bit { 2 } else { 1 });
+    for row in rows_top_down(tex) {
+        for pixel in row.iter() {
+            let color = if color_type == ColorType::GrayscaleAlpha {
+                RgbaF32::new(pixel.channel(0).to_f32(), pixel.channel(1).to_f32(), 0.0, 0.0)
+            } else {
+                pixel.to_rgba_f32()
+            };
+            if sixteen_bit {
+                let color = words(color);
+                for channel in color[0..channels].iter() {
+                    data.extend_from_slice(&channel.to_be_bytes());
+                }
+            } else {
+                data.extend_from_slice(&bytes(color)[0..channels]);
+            }
+        }
+    }
+
+    let mut encoder = png::Encoder::new(writer, tex.width, tex.height);
+    encoder.set_color(color_type);
+    encoder.set_depth(if sixteen_bit { BitDepth::Sixteen } else { BitDepth::Eight });
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(&data)?;
+    png_writer.finish()?;
+
+    Ok(())
+}
+
+/// Write a texture image as an uncompressed TGA file. TGA files store the
+/// bottom row first, so the rows are written in the order they are stored in.
+/// Two channel formats are written as grayscale with alpha.
+fn write_tga<P: Pixel, W: Write>(tex: &TexImage2D<P>, writer: &mut W) -> io::Result<()> {
+    check_dimensions(tex, u16::MAX as u32)?;
+    let (image_type, bits_per_pixel, alpha_bits) = match P::CHANNEL_COUNT {
+        1 => (3, 8, 0),
+        2 => (3, 16, 8),
+        3 => (2, 24, 0),
+        _ => (2, 32, 8),
+    };
+
+    let mut header = [0_u8; 18];
+    header[2] = image_type;
+    header[12..14].copy_from_slice(&(tex.width as u16).to_le_bytes());
+    header[14..16].copy_from_slice(&(tex.height as u16).to_le_bytes());
+    header[16] = bits_per_pixel;
+    header[17] = alpha_bits;
+    writer.write_all(&header)?;
+
+    let mut data = Vec::with_capacity(tex.pixel_count() * (bits_per_pixel as usize / 8));
+    for pixel in tex.data.iter() {
+        let color = pixel.to_rgba_f32();
+        match bits_per_pixel {
+            8 => data.push(gray(color)),
+            16 => data.extend_from_slice(&gray_alpha(pixel)),
+            24 => {
+                let [r, g, b, _] = bytes(color);
+                data.extend_from_slice(&[b, g, r]);
+            }
+            _ => {
+                let [r, g, b, a] = bytes(color);
+                data.extend_from_slice(&[b, g, r, a]);
+            }
+        }
+    }
+
+    writer.write_all(&data)
+}
+
+/// Write a texture image as an uncompressed BMP file. A positive height in the
+/// header tells the reader the bottom row comes first, so the rows are written
+/// in the order they are stored in. Single channel formats are written as
+/// grayscale palette images, and formats with an alpha channel are written with
+/// a version 4 header carrying the channel masks. Two channel formats have an
+/// alpha channel, and are written as gray colors with alpha.
+fn write_bmp<P: Pixel, W: Write>(tex: &TexImage2D<P>, writer: &mut W) -> io::Result<()> {
+    check_dimensions(tex, i32::MAX as u32)?;
+    let (bits_per_pixel, info_header_size, palette_size) = match P::CHANNEL_COUNT {
+        1 => (8_u32, 40_u32, 256 * 4_u32),
+        3 => (24, 40, 0),
+        _ => (32, 108, 0),
+    };
+    let bytes_per_pixel = (bits_per_pixel / 8) as usize;
+    let row_size = (tex.width as usize * bytes_per_pixel + 3) & !3;
+    let image_size = row_size * tex.height as usize;
+    let pixel_offset = 14 + info_header_size + palette_size;
+    let file_size = pixel_offset as usize + image_size;
+    if file_size > u32::MAX as usize {
+        return Err(invalid_input("Texture image dimensions are too large for the image format."));
+    }
+
+    let mut header = Vec::with_capacity(pixel_offset as usize);
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&(file_size as u32).to_le_bytes());
+    header.extend_from_slice(&0_u32.to_le_bytes());
+    header.extend_from_slice(&pixel_offset.to_le_bytes());
+
+    header.extend_from_slice(&info_header_size.to_le_bytes());
+    header.extend_from_slice(&(tex.width as i32).to_le_bytes());
+    header.extend_from_slice(&(tex.height as i32).to_le_bytes());
+    header.extend_from_slice(&1_u16.to_le_bytes());
+    header.extend_from_slice(&(bits_per_pixel as u16).to_le_bytes());
+    let compression: u32 = if bits_per_pixel == 32 { 3 } else { 0 };
+    header.extend_from_slice(&compression.to_le_bytes());
+    header.extend_from_slice(&(image_size as u32).to_le_bytes());
+    // Resolution of 72 DPI in pixels per meter.
+    header.extend_from_slice(&2835_u32.to_le_bytes());
+    header.extend_from_slice(&2835_u32.to_le_bytes());
+    let colors_used: u32 = if palette_size > 0 { 256 } else { 0 };
+    header.extend_from_slice(&colors_used.to_le_bytes());
+    header.extend_from_slice(&0_u32.to_le_bytes());
+    if info_header_size == 108 {
+        for mask in [0x00FF0000_u32, 0x0000FF00, 0x000000FF, 0xFF000000].iter() {
+            header.extend_from_slice(&mask.to_le_bytes());
+        }
+        header.extend_from_slice(b"BGRs");
+        // The color space endpoints and gamma values are unused for sRGB.
+        header.extend_from_slice(&[0_u8; 48]);
+    }
+    for value in 0..palette_size / 4 {
+        let value = value as u8;
+        header.extend_from_slice(&[value, value, value, 0]);
+    }
+    writer.write_all(&header)?;
+
+    let mut row_data = vec![0_u8; row_size];
+    for row in tex.data.chunks(tex.width as usize) {
+        for (pixel, out) in row.iter().zip(row_data.chunks_mut(bytes_per_pixel)) {
+            let color = pixel.to_rgba_f32();
+            match bits_per_pixel {
+                8 => out[0] = gray(color),
+                24 => {
+                    let [r, g, b, _] = bytes(color);
+                    out.copy_from_slice(&[b, g, r]);
+                }
+                _ if P::CHANNEL_COUNT == 2 => {
+                    let [l, a] = gray_alpha(pixel);
+                    out.copy_from_slice(&[l, l, l, a]);
+                }
+                _ => {
+                    let [r, g, b, a] = bytes(color);
+                    out.copy_from_slice(&[b, g, r, a]);
+                }
+            }
+        }
+        writer.write_all(&row_data)?;
+    }
+
+    Ok(())
+}
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Encode the texture image into a writer in the given image file format.
+    /// Texture images are stored bottom row first, and are written so that
+    /// loading the encoded image yields the same texture image again. Formats
+    /// the image file format cannot represent exactly are converted to the
+    /// closest format the image file format supports.
+    pub fn write_to<W: Write>(&self, writer: &mut W, format: ImageFormat) -> io::Result<()> {
+        match format {
+            #[cfg(feature = "png-encoder")]
+            ImageFormat::Png => write_png(self, writer),
+            ImageFormat::Tga => write_tga(self, writer),
+            ImageFormat::Bmp => write_bmp(self, writer),
+        }
+    }
+
+    /// Save the texture image to a file in the given image file format.
+    pub fn save<Q: AsRef<Path>>(&self, file_path: Q, format: ImageFormat) -> io::Result<()> {
+        let file = File::create(file_path)?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer, format)?;
+        writer.flush()
+    }
+
+    /// Save the texture image to a PNG file.
+    #[cfg(feature = "png-encoder")]
+    pub fn save_png<Q: AsRef<Path>>(&self, file_path: Q) -> io::Result<()> {
+        self.save(file_path, ImageFormat::Png)
+    }
+
+    /// Save the texture image to a TGA file.
+    pub fn save_tga<Q: AsRef<Path>>(&self, file_path: Q) -> io::Result<()> {
+        self.save(file_path, ImageFormat::Tga)
+    }
+
+    /// Save the texture image to a BMP file.
+    pub fn save_bmp<Q: AsRef<Path>>(&self, file_path: Q) -> io::Result<()> {
+        self.save(file_path, ImageFormat::Bmp)
+    }
+}
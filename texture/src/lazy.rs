@@ -0,0 +1,123 @@
+use crate::{load_from_memory, Pixel, Rgba, TexImage2D, TexImage2DError};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+
+/// A texture image embedded as the bytes of a compressed image file, such
+/// as a PNG file, that is decoded the first time it is accessed. Embedding
+/// the compressed bytes instead of the pixel data keeps large texture images
+/// from bloating the binary and the compile time. A lazy texture image can be
+/// constructed in a constant expression, so generated code can place it in a
+/// `static`.
+///
+/// The bytes are decoded with `load_from_memory` and the default load
+/// options, and converted to the pixel type. The decoder produces eight bits
+/// per channel, so sixteen bit and floating point texture images lose
+/// precision when they are embedded lazily.
+pub struct LazyTexture<P = Rgba> {
+    bytes: &'static [u8],
+    image: OnceLock<TexImage2D<P>>,
+}
+
+impl<P: Pixel> LazyTexture<P> {
+    pub const fn new(bytes: &'static [u8]) -> LazyTexture<P> {
+        LazyTexture {
+            bytes: bytes,
+            image: OnceLock::new(),
+        }
+    }
+
+    /// The compressed bytes of the texture image.
+    pub fn bytes(&self) -> &'static [u8] {
+        self.bytes
+    }
+
+    /// Whether the texture image has been decoded yet.
+    pub fn is_decoded(&self) -> bool {
+        self.image.get().is_some()
+    }
+
+    /// Decode the texture image if it has not been decoded yet, and return it.
+    pub fn try_get(&self) -> Result<&TexImage2D<P>, TexImage2DError> {
+        if let Some(image) = self.image.get() {
+            return Ok(image);
+        }
+
+        let image = load_from_memory(self.bytes)?.image.convert::<P>();
+
+        Ok(self.image.get_or_init(|| image))
+    }
+
+    /// Decode the texture image if it has not been decoded yet, and return it.
+    ///
+    /// # Panics
+    /// Panics if the embedded bytes fail to decode.
+    pub fn get(&self) -> &TexImage2D<P> {
+        match self.try_get() {
+            Ok(image) => image,
+            Err(error) => panic!("Failed to decode a lazily embedded texture image: {}", error),
+        }
+    }
+}
+
+impl<P: Pixel> Deref for LazyTexture<P> {
+    type Target = TexImage2D<P>;
+
+    fn deref(&self) -> &TexImage2D<P> {
+        self.get()
+    }
+}
+
+impl<P: Pixel + fmt::Debug> fmt::Debug for LazyTexture<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazyTexture")
+            .field("bytes", &self.bytes.len())
+            .field("image", &self.image.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "png-encoder")]
+    use crate::{ImageFormat, R8};
+
+
+    #[cfg(feature = "png-encoder")]
+    #[test]
+    fn test_lazy_texture_decodes_on_first_access() {
+        let image = TexImage2D::from_data(2, 2, vec![
+            Rgba::new(0x01, 0x02, 0x03, 0xFF), Rgba::new(0x04, 0x05, 0x06, 0xFF),
+            Rgba::new(0x07, 0x08, 0x09, 0x80), Rgba::new(0x0A, 0x0B, 0x0C, 0x00),
+        ]);
+        let mut bytes = vec![];
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        let lazy: LazyTexture = LazyTexture::new(Box::leak(bytes.into_boxed_slice()));
+
+        assert!(!lazy.is_decoded());
+        assert_eq!(*lazy.get(), image);
+        assert!(lazy.is_decoded());
+        assert_eq!(lazy.width, 2);
+    }
+
+    #[cfg(feature = "png-encoder")]
+    #[test]
+    fn test_lazy_texture_converts_to_pixel_type() {
+        let image = TexImage2D::from_data(2, 1, vec![R8::new(0x10), R8::new(0x20)]);
+        let mut bytes = vec![];
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        let lazy: LazyTexture<R8> = LazyTexture::new(Box::leak(bytes.into_boxed_slice()));
+
+        assert_eq!(*lazy.get(), image);
+    }
+
+    #[test]
+    fn test_lazy_texture_reports_decode_errors() {
+        static LAZY: LazyTexture = LazyTexture::new(b"this is not an image");
+
+        assert!(LAZY.try_get().is_err());
+        assert!(!LAZY.is_decoded());
+    }
+}
@@ -1,88 +1,251 @@
-#![feature(vec_into_raw_parts)]
+#[cfg(feature = "stb-decoder")]
 use stb_image::image;
-use stb_image::image::LoadResult;
-use std::path::Path;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::error::Error;
 use std::fmt;
-use std::mem;
 
+mod array;
+mod atlas;
+mod compress;
+mod cube;
+mod decode;
+mod encode;
+mod lazy;
+mod mipmap;
+mod pixel;
+mod resample;
+mod view;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct Rgba {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
-    pub a: u8,
-}
-
-impl Rgba {
-    #[inline]
-    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Rgba {
-        Rgba { r: r, g: g, b: b, a: a }
-    }
-}
-
-impl Default for Rgba {
-    #[inline]
-    fn default() -> Rgba {
-        Rgba::new(0, 0, 0, 255)
-    }
-}
+pub use array::{load_array_grid, load_array_layers, TexImage2DArray};
+pub use atlas::{find_atlas_region, AtlasError, AtlasOptions, AtlasRegion, TextureAtlas, UvRect};
+pub use compress::{decompress, CompressedFormat, CompressedTexImage2D, CompressedTexImage2DRef, Quality};
+pub use cube::{load_cube_faces, load_cube_layout, CubeFace, CubeLayout, TexImageCube};
+pub use decode::{DecodedData, DecodedImage, Decoder};
+#[cfg(feature = "png-decoder")]
+pub use decode::PngDecoder;
+#[cfg(feature = "stb-decoder")]
+pub use decode::StbDecoder;
+pub use encode::ImageFormat;
+pub use lazy::LazyTexture;
+pub use mipmap::{mip_level_count, MipChain};
+pub use pixel::*;
+pub use resample::{Filter, Policy};
+pub use view::{to_native_endian, SubImage, TexImage2DRef};
+pub use bytemuck::{Pod, Zeroable};
 
-impl From<u32> for Rgba {
-    #[inline]
-    fn from(val: u32) -> Rgba {
-        Rgba {
-            r: ((val & 0xFF000000) >> 24) as u8,
-            g: ((val & 0x00FF0000) >> 16) as u8,
-            b: ((val & 0x0000FF00) >> 8) as u8,
-            a: ((val & 0x000000FF) >> 0) as u8,
-        }
-    }
-}
 
+/// A two dimensional texture image. The pixel type determines the format
+/// the texture is stored in, and defaults to eight bit RGBA pixels.
 #[derive(Clone, Debug, PartialEq)]
-pub struct TexImage2D {
+pub struct TexImage2D<P = Rgba> {
     pub width: u32,
     pub height: u32,
     pub depth: u32,
-    pub data: Vec<Rgba>,
+    pub data: Vec<P>,
 }
 
-impl TexImage2D {
-    pub fn new(width: u32, height: u32) -> TexImage2D {
+impl<P: Pixel> TexImage2D<P> {
+    pub fn new(width: u32, height: u32) -> TexImage2D<P> {
         TexImage2D {
             width: width,
             height: height,
-            depth: 4,
-            data: vec![Rgba::default(); (width * height) as usize],
+            depth: P::CHANNEL_COUNT as u32,
+            data: vec![P::default(); (width * height) as usize],
         }
     }
 
-    pub fn from_rgba_data(width: u32, height: u32, data: Vec<Rgba>) -> TexImage2D {
+    pub fn from_data(width: u32, height: u32, data: Vec<P>) -> TexImage2D<P> {
         TexImage2D {
             width: width,
             height: height,
-            depth: 4,
+            depth: P::CHANNEL_COUNT as u32,
             data: data,
         }
     }
 
+    /// The pixel format the texture image is stored in.
+    pub fn format(&self) -> PixelFormat {
+        P::FORMAT
+    }
+
     pub fn pixel_count(&self) -> usize {
         self.data.len()
     }
 
+    /// Convert the texture image to a different pixel format.
+    pub fn convert<Q: Pixel>(&self) -> TexImage2D<Q> {
+        let data = self.data.iter().map(|pixel| pixel.convert()).collect();
+
+        TexImage2D::from_data(self.width, self.height, data)
+    }
+
+    /// Construct a texture image from raw pixel data. The pixels are stored
+    /// bottom row first with their channels in RGBA order, and channels wider
+    /// than a byte are stored in little endian byte order. This is the layout
+    /// `to_bytes` produces, and it lets generated code embed a texture image
+    /// as a single byte array.
+    ///
+    /// Panics if the length of the data does not match the dimensions.
+    pub fn from_bytes(width: u32, height: u32, bytes: &[u8]) -> TexImage2D<P> {
+        let channel_size = P::FORMAT.bytes_per_channel();
+        let pixel_size = P::FORMAT.bytes_per_pixel();
+        assert_eq!(
+            bytes.len(), (width as usize) * (height as usize) * pixel_size,
+            "The raw pixel data does not match a {} x {} {} texture image.", width, height, P::FORMAT
+        );
+
+        let mut channels = Vec::with_capacity(P::CHANNEL_COUNT);
+        let data = bytes.chunks(pixel_size).map(|pixel| {
+            channels.clear();
+            channels.extend(pixel.chunks(channel_size).map(P::Channel::from_le_slice));
+            P::from_channels(&channels)
+        }).collect();
+
+        TexImage2D::from_data(width, height, data)
+    }
+
+    /// The raw pixel data of the texture image in the layout `from_bytes` reads.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixel_count() * P::FORMAT.bytes_per_pixel());
+        for pixel in self.data.iter() {
+            for i in 0..P::CHANNEL_COUNT {
+                pixel.channel(i).push_le_bytes(&mut bytes);
+            }
+        }
+
+        bytes
+    }
+
+    /// Construct a texture image from a flat buffer of channel values in
+    /// RGBA order, such as the buffer a decoder returns. The buffer is reused
+    /// as the pixel data without copying whenever its capacity holds a whole
+    /// number of pixels.
+    ///
+    /// # Panics
+    /// Panics if the length of the buffer does not match the dimensions.
+    pub fn from_channel_data(width: u32, height: u32, channels: Vec<P::Channel>) -> TexImage2D<P> {
+        assert_eq!(
+            channels.len(), (width as usize) * (height as usize) * P::CHANNEL_COUNT,
+            "The channel data does not match a {} x {} {} texture image.", width, height, P::FORMAT
+        );
+
+        let data = match bytemuck::allocation::try_cast_vec(channels) {
+            Ok(data) => data,
+            Err((_, channels)) => bytemuck::cast_slice(&channels).to_vec(),
+        };
+
+        TexImage2D::from_data(width, height, data)
+    }
+
+    /// Convert the texture image into a flat buffer of channel values in
+    /// RGBA order without copying the pixel data.
+    pub fn into_channel_data(self) -> Vec<P::Channel> {
+        // A pixel is a whole number of channels with the same alignment, so
+        // the cast cannot fail.
+        bytemuck::allocation::cast_vec(self.data)
+    }
+
+    /// The pixel data of the texture image as a flat slice of channel values.
+    pub fn as_channels(&self) -> &[P::Channel] {
+        bytemuck::cast_slice(&self.data)
+    }
+
+    /// The pixel data of the texture image as a flat mutable slice of channel values.
+    pub fn as_channels_mut(&mut self) -> &mut [P::Channel] {
+        bytemuck::cast_slice_mut(&mut self.data)
+    }
+
+    #[inline]
+    pub fn as_ptr(&self) -> *const P::Channel {
+        self.as_channels().as_ptr()
+    }
+
+    /// The index into the pixel data of the pixel at the given coordinates.
+    /// Row zero is the first row stored, which is the bottom row of a texture
+    /// image loaded with the default load options.
+    ///
+    /// # Panics
+    /// Panics if the coordinates lie outside the texture image.
+    #[inline]
+    fn pixel_index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "The pixel ({}, {}) lies outside a {} x {} texture image.", x, y, self.width, self.height
+        );
+
+        (y as usize) * (self.width as usize) + (x as usize)
+    }
+
+    /// Get the pixel at the given coordinates. Row zero is the first row
+    /// stored, which is the bottom row of a texture image loaded with the
+    /// default load options.
+    ///
+    /// # Panics
+    /// Panics if the coordinates lie outside the texture image.
+    #[inline]
+    pub fn get_pixel(&self, x: u32, y: u32) -> P {
+        self.data[self.pixel_index(x, y)]
+    }
+
+    /// Set the pixel at the given coordinates.
+    ///
+    /// # Panics
+    /// Panics if the coordinates lie outside the texture image.
     #[inline]
-    pub fn as_ptr(&self) -> *const u8 {
-        &self.data[0].r
+    pub fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
+        let index = self.pixel_index(x, y);
+        self.data[index] = pixel;
+    }
+
+    /// Iterate over the rows of the texture image in the order they are stored.
+    pub fn rows(&self) -> std::slice::Chunks<'_, P> {
+        self.data.chunks(self.width.max(1) as usize)
+    }
+
+    /// Iterate mutably over the rows of the texture image in the order they
+    /// are stored.
+    pub fn rows_mut(&mut self) -> std::slice::ChunksMut<'_, P> {
+        self.data.chunks_mut(self.width.max(1) as usize)
+    }
+
+    /// The pixel data of the texture image as bytes in native byte order,
+    /// ready to hand to a graphics API. Unlike `to_bytes`, nothing is copied.
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.data)
+    }
+
+    /// The pixel data of the texture image as mutable bytes in native byte order.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        bytemuck::cast_slice_mut(&mut self.data)
+    }
+
+    /// Copy a rectangle of pixels out of the texture image. The rectangle
+    /// must lie inside the texture image.
+    pub(crate) fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> TexImage2D<P> {
+        self.sub_image(x, y, width, height).to_tex_image()
+    }
+}
+
+impl TexImage2D<Rgba> {
+    pub fn from_rgba_data(width: u32, height: u32, data: Vec<Rgba>) -> TexImage2D {
+        TexImage2D::from_data(width, height, data)
     }
 }
 
-impl<'a> From<&'a image::Image<u8>> for TexImage2D {
-    fn from(image: &'a image::Image<u8>) -> TexImage2D {
+/// A texture image whose pixels are stored as 32 bit floating point values.
+/// This is the type of texture produced when loading high dynamic range
+/// images such as Radiance `.hdr` files.
+pub type HdrTexImage2D = TexImage2D<RgbaF32>;
+
+#[cfg(feature = "stb-decoder")]
+impl<'a, P: Pixel<Channel = u8>> From<&'a image::Image<u8>> for TexImage2D<P> {
+    fn from(image: &'a image::Image<u8>) -> TexImage2D<P> {
         let mut data = vec![];
-        for chunk in image.data.chunks(4) {
-            data.push(Rgba::new(chunk[0], chunk[1], chunk[2], chunk[3]));
+        for chunk in image.data.chunks(P::CHANNEL_COUNT) {
+            data.push(P::from_channels(chunk));
         }
 
         TexImage2D {
@@ -94,20 +257,302 @@ impl<'a> From<&'a image::Image<u8>> for TexImage2D {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg(feature = "stb-decoder")]
+impl<'a, P: Pixel<Channel = f32>> From<&'a image::Image<f32>> for TexImage2D<P> {
+    fn from(image: &'a image::Image<f32>) -> TexImage2D<P> {
+        let mut data = vec![];
+        for chunk in image.data.chunks(P::CHANNEL_COUNT) {
+            data.push(P::from_channels(chunk));
+        }
+
+        TexImage2D {
+            width: image.width as u32,
+            height: image.height as u32,
+            depth: image.depth as u32,
+            data: data,
+        }
+    }
+}
+
+/// A texture image produced by one of the loaders. Low dynamic range
+/// images are loaded as byte textures, and high dynamic range images are
+/// loaded as floating point textures. The number of channels depends on
+/// the channel count requested in the load options. One and two channel
+/// byte textures are grayscale, and convert to other formats with the gray
+/// value in every color channel.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynamicTexImage2D {
+    L8(TexImage2D<L8>),
+    La8(TexImage2D<La8>),
+    Rgb8(TexImage2D<Rgb8>),
+    Rgba8(TexImage2D),
+    RF32(TexImage2D<RF32>),
+    RgbaF32(HdrTexImage2D),
+}
+
+impl DynamicTexImage2D {
+    pub fn width(&self) -> u32 {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.width,
+            DynamicTexImage2D::La8(ref image) => image.width,
+            DynamicTexImage2D::Rgb8(ref image) => image.width,
+            DynamicTexImage2D::Rgba8(ref image) => image.width,
+            DynamicTexImage2D::RF32(ref image) => image.width,
+            DynamicTexImage2D::RgbaF32(ref image) => image.width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.height,
+            DynamicTexImage2D::La8(ref image) => image.height,
+            DynamicTexImage2D::Rgb8(ref image) => image.height,
+            DynamicTexImage2D::Rgba8(ref image) => image.height,
+            DynamicTexImage2D::RF32(ref image) => image.height,
+            DynamicTexImage2D::RgbaF32(ref image) => image.height,
+        }
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.pixel_count(),
+            DynamicTexImage2D::La8(ref image) => image.pixel_count(),
+            DynamicTexImage2D::Rgb8(ref image) => image.pixel_count(),
+            DynamicTexImage2D::Rgba8(ref image) => image.pixel_count(),
+            DynamicTexImage2D::RF32(ref image) => image.pixel_count(),
+            DynamicTexImage2D::RgbaF32(ref image) => image.pixel_count(),
+        }
+    }
+
+    /// The pixel format the texture image is stored in.
+    pub fn format(&self) -> PixelFormat {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.format(),
+            DynamicTexImage2D::La8(ref image) => image.format(),
+            DynamicTexImage2D::Rgb8(ref image) => image.format(),
+            DynamicTexImage2D::Rgba8(ref image) => image.format(),
+            DynamicTexImage2D::RF32(ref image) => image.format(),
+            DynamicTexImage2D::RgbaF32(ref image) => image.format(),
+        }
+    }
+
+    /// Determine whether the texture image holds high dynamic range data.
+    pub fn is_hdr(&self) -> bool {
+        matches!(*self, DynamicTexImage2D::RF32(_) | DynamicTexImage2D::RgbaF32(_))
+    }
+
+    /// Convert the texture image to eight bit RGBA pixels, whatever format it
+    /// is stored in.
+    pub fn to_rgba8(&self) -> TexImage2D {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.convert(),
+            DynamicTexImage2D::La8(ref image) => image.convert(),
+            DynamicTexImage2D::Rgb8(ref image) => image.convert(),
+            DynamicTexImage2D::Rgba8(ref image) => image.clone(),
+            DynamicTexImage2D::RF32(ref image) => image.convert(),
+            DynamicTexImage2D::RgbaF32(ref image) => image.convert(),
+        }
+    }
+
+    /// Convert the texture image to any pixel format, whatever format it is
+    /// stored in.
+    pub fn convert<P: Pixel>(&self) -> TexImage2D<P> {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.convert(),
+            DynamicTexImage2D::La8(ref image) => image.convert(),
+            DynamicTexImage2D::Rgb8(ref image) => image.convert(),
+            DynamicTexImage2D::Rgba8(ref image) => image.convert(),
+            DynamicTexImage2D::RF32(ref image) => image.convert(),
+            DynamicTexImage2D::RgbaF32(ref image) => image.convert(),
+        }
+    }
+
+    pub fn as_l8(&self) -> Option<&TexImage2D<L8>> {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_l8(self) -> Option<TexImage2D<L8>> {
+        match self {
+            DynamicTexImage2D::L8(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn as_la8(&self) -> Option<&TexImage2D<La8>> {
+        match *self {
+            DynamicTexImage2D::La8(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_la8(self) -> Option<TexImage2D<La8>> {
+        match self {
+            DynamicTexImage2D::La8(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn as_rgb8(&self) -> Option<&TexImage2D<Rgb8>> {
+        match *self {
+            DynamicTexImage2D::Rgb8(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_rgb8(self) -> Option<TexImage2D<Rgb8>> {
+        match self {
+            DynamicTexImage2D::Rgb8(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn as_rgba8(&self) -> Option<&TexImage2D> {
+        match *self {
+            DynamicTexImage2D::Rgba8(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_rgba8(self) -> Option<TexImage2D> {
+        match self {
+            DynamicTexImage2D::Rgba8(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn as_r_f32(&self) -> Option<&TexImage2D<RF32>> {
+        match *self {
+            DynamicTexImage2D::RF32(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_r_f32(self) -> Option<TexImage2D<RF32>> {
+        match self {
+            DynamicTexImage2D::RF32(image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn as_rgba_f32(&self) -> Option<&HdrTexImage2D> {
+        match *self {
+            DynamicTexImage2D::RgbaF32(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn into_rgba_f32(self) -> Option<HdrTexImage2D> {
+        match self {
+            DynamicTexImage2D::RgbaF32(image) => Some(image),
+            _ => None,
+        }
+    }
+}
+
+impl From<TexImage2D> for DynamicTexImage2D {
+    fn from(image: TexImage2D) -> DynamicTexImage2D {
+        DynamicTexImage2D::Rgba8(image)
+    }
+}
+
+impl From<HdrTexImage2D> for DynamicTexImage2D {
+    fn from(image: HdrTexImage2D) -> DynamicTexImage2D {
+        DynamicTexImage2D::RgbaF32(image)
+    }
+}
+
+/// The errors that can occur when loading a texture image. Errors from loading
+/// a file carry the path of the file.
+#[derive(Debug)]
 pub enum TexImage2DError {
-    CouldNotLoadImageBuffer,
-    Got32BitFloatingPointImageInsteadOfByteImage,
+    /// The image file or reader could not be read.
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// The decoder failed to decode the image data.
+    Decode {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+    /// The image data is in a format, or a variant of a format, that the
+    /// decoder does not support.
+    UnsupportedFormat {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+    /// The image dimensions are too large for the decoder.
+    UnsupportedDimensions {
+        path: Option<PathBuf>,
+        width: usize,
+        height: usize,
+    },
+    /// The images do not form a valid cube map, texture array or mipmap
+    /// chain, such as faces or layers with mismatched dimensions, or
+    /// compressed mipmap levels of the wrong size.
+    InvalidLayout {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+}
+
+impl TexImage2DError {
+    /// The path of the image file that failed to load, if the image was
+    /// loaded from a file.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            TexImage2DError::Io { ref path, .. } => path.as_deref(),
+            TexImage2DError::Decode { ref path, .. } => path.as_deref(),
+            TexImage2DError::UnsupportedFormat { ref path, .. } => path.as_deref(),
+            TexImage2DError::UnsupportedDimensions { ref path, .. } => path.as_deref(),
+            TexImage2DError::InvalidLayout { ref path, .. } => path.as_deref(),
+        }
+    }
+
+    /// Attach the path of the image file that failed to load.
+    pub(crate) fn with_path(self, file_path: &Path) -> TexImage2DError {
+        let file_path = Some(file_path.to_path_buf());
+        match self {
+            TexImage2DError::Io { source, .. } => TexImage2DError::Io { path: file_path, source: source },
+            TexImage2DError::Decode { reason, .. } => {
+                TexImage2DError::Decode { path: file_path, reason: reason }
+            }
+            TexImage2DError::UnsupportedFormat { reason, .. } => {
+                TexImage2DError::UnsupportedFormat { path: file_path, reason: reason }
+            }
+            TexImage2DError::UnsupportedDimensions { width, height, .. } => {
+                TexImage2DError::UnsupportedDimensions { path: file_path, width: width, height: height }
+            }
+            TexImage2DError::InvalidLayout { reason, .. } => {
+                TexImage2DError::InvalidLayout { path: file_path, reason: reason }
+            }
+        }
+    }
 }
 
 impl fmt::Display for TexImage2DError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(path) = self.path() {
+            write!(f, "{}: ", path.display())?;
+        }
         match *self {
-            TexImage2DError::CouldNotLoadImageBuffer => {
-                write!(f, "{}", "Could not load image buffer.")
+            TexImage2DError::Io { ref source, .. } => {
+                write!(f, "Could not read image file: {}", source)
+            }
+            TexImage2DError::Decode { ref reason, .. } => {
+                write!(f, "Could not decode image buffer: {}", reason)
+            }
+            TexImage2DError::UnsupportedFormat { ref reason, .. } => {
+                write!(f, "Unsupported image format: {}", reason)
+            }
+            TexImage2DError::UnsupportedDimensions { width, height, .. } => {
+                write!(f, "Unsupported image dimensions: {} x {}", width, height)
             }
-            TexImage2DError::Got32BitFloatingPointImageInsteadOfByteImage => {
-                write!(f, "{}", "Tried to load an image as byte vectors, got 32 bit floating point image instead.")
+            TexImage2DError::InvalidLayout { ref reason, .. } => {
+                write!(f, "Invalid texture layout: {}", reason)
             }
         }
     }
@@ -115,130 +560,394 @@ impl fmt::Display for TexImage2DError {
 
 impl Error for TexImage2DError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match *self {
+            TexImage2DError::Io { ref source, .. } => Some(source),
+            _ => None,
+        }
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The potential problems with a texture image that do not prevent it from
+/// being used.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TexImage2DWarning {
-    NoWarnings,
+    /// Older graphics hardware and some texture wrapping modes require power of
+    /// two texture dimensions.
     TextureDimensionsAreNotAPowerOfTwo,
+    /// The texture image is larger than the maximum texture size.
+    TextureDimensionsExceedMaximumSize {
+        max_texture_size: u32,
+    },
+    /// The width and height of the texture image differ.
+    TextureIsNotSquare,
+    /// Every pixel is fully opaque, so the alpha channel could be dropped.
+    AlphaChannelIsFullyOpaque,
+    /// Every pixel has equal red, green, and blue values, so the texture
+    /// image could be stored as a single channel texture.
+    UsesOnlyGrayscaleValues,
+    /// Every alpha value is either fully transparent or fully opaque, so the
+    /// alpha channel could be stored with one bit per pixel.
+    AlphaChannelIsOneBit,
+}
+
+impl fmt::Display for TexImage2DWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo => {
+                f.write_str("Texture dimensions are not a power of two.")
+            }
+            TexImage2DWarning::TextureDimensionsExceedMaximumSize { max_texture_size } => {
+                write!(f, "Texture dimensions exceed the maximum texture size of {}.", max_texture_size)
+            }
+            TexImage2DWarning::TextureIsNotSquare => {
+                f.write_str("Texture is not square.")
+            }
+            TexImage2DWarning::AlphaChannelIsFullyOpaque => {
+                f.write_str("Alpha channel is fully opaque and could be dropped.")
+            }
+            TexImage2DWarning::UsesOnlyGrayscaleValues => {
+                f.write_str("Texture uses only grayscale values and could be stored in one channel.")
+            }
+            TexImage2DWarning::AlphaChannelIsOneBit => {
+                f.write_str("Alpha channel only uses fully transparent and fully opaque values.")
+            }
+        }
+    }
+}
+
+/// The default maximum texture size used for warnings. This is the largest
+/// texture size that Direct3D 11 class hardware is required to support.
+pub const DEFAULT_MAX_TEXTURE_SIZE: u32 = 16384;
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Check the texture image for potential problems. The texture image is
+    /// compared against the given maximum texture size. Texture images with
+    /// one or two channels have no alpha channel.
+    pub fn check_warnings(&self, max_texture_size: u32) -> Vec<TexImage2DWarning> {
+        let mut warnings = vec![];
+        if !self.width.is_power_of_two() || !self.height.is_power_of_two() {
+            warnings.push(TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo);
+        }
+        if self.width > max_texture_size || self.height > max_texture_size {
+            warnings.push(TexImage2DWarning::TextureDimensionsExceedMaximumSize {
+                max_texture_size: max_texture_size,
+            });
+        }
+        if self.width != self.height {
+            warnings.push(TexImage2DWarning::TextureIsNotSquare);
+        }
+        if self.data.is_empty() {
+            return warnings;
+        }
+
+        if P::CHANNEL_COUNT == 4 {
+            let alpha = |pixel: &P| pixel.channel(3).to_f32();
+            if self.data.iter().all(|pixel| alpha(pixel) == 1.0) {
+                warnings.push(TexImage2DWarning::AlphaChannelIsFullyOpaque);
+            } else if self.data.iter().all(|pixel| alpha(pixel) == 0.0 || alpha(pixel) == 1.0) {
+                warnings.push(TexImage2DWarning::AlphaChannelIsOneBit);
+            }
+        }
+        if P::CHANNEL_COUNT >= 3 {
+            let is_gray = |pixel: &P| pixel.channel(0) == pixel.channel(1) && pixel.channel(1) == pixel.channel(2);
+            if self.data.iter().all(is_gray) {
+                warnings.push(TexImage2DWarning::UsesOnlyGrayscaleValues);
+            }
+        }
+
+        warnings
+    }
+}
+
+impl DynamicTexImage2D {
+    /// Check the texture image for potential problems.
+    pub fn check_warnings(&self, max_texture_size: u32) -> Vec<TexImage2DWarning> {
+        match *self {
+            DynamicTexImage2D::L8(ref image) => image.check_warnings(max_texture_size),
+            DynamicTexImage2D::La8(ref image) => image.check_warnings(max_texture_size),
+            DynamicTexImage2D::Rgb8(ref image) => image.check_warnings(max_texture_size),
+            DynamicTexImage2D::Rgba8(ref image) => image.check_warnings(max_texture_size),
+            DynamicTexImage2D::RF32(ref image) => image.check_warnings(max_texture_size),
+            DynamicTexImage2D::RgbaF32(ref image) => image.check_warnings(max_texture_size),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct TexImage2DResult {
-    pub image: TexImage2D,
-    pub warnings: TexImage2DWarning,
+    pub image: DynamicTexImage2D,
+    pub warnings: Vec<TexImage2DWarning>,
+    /// The color space the load options tagged the texture image with.
+    pub color_space: ColorSpace,
 }
 
 impl TexImage2DResult {
     pub fn has_no_warnings(&self) -> bool {
-        self.warnings == TexImage2DWarning::NoWarnings
+        self.warnings.is_empty()
+    }
+
+    pub fn has_warning(&self, warning: TexImage2DWarning) -> bool {
+        self.warnings.contains(&warning)
     }
 }
 
-/// Load a PNG texture image from a reader or buffer.
-pub fn load_from_memory(buffer: &[u8]) -> Result<TexImage2DResult, TexImage2DError> {
-    let force_channels = 4;
-    let mut image_data = match image::load_from_memory_with_depth(buffer, force_channels, false) {
-        LoadResult::ImageU8(image_data) => image_data,
-        LoadResult::Error(_) => {
-            return Err(TexImage2DError::CouldNotLoadImageBuffer);
-        }
-        LoadResult::ImageF32(_) => {
-            return Err(TexImage2DError::Got32BitFloatingPointImageInsteadOfByteImage);
+/// The options controlling how the loaders decode an image into a texture
+/// image. The default options flip the rows so the first row is the bottom
+/// row of the image, which is what OpenGL expects, and force four channels.
+/// Graphics APIs with a top left texture origin, such as Vulkan, wgpu, and
+/// Direct3D, should disable vertical flipping.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LoadOptions {
+    flip_vertically: bool,
+    flip_horizontally: bool,
+    desired_channels: usize,
+    premultiply_alpha: bool,
+    color_space: ColorSpace,
+    max_texture_size: u32,
+}
+
+impl LoadOptions {
+    pub fn new() -> LoadOptions {
+        LoadOptions {
+            flip_vertically: true,
+            flip_horizontally: false,
+            desired_channels: 4,
+            premultiply_alpha: false,
+            color_space: ColorSpace::Linear,
+            max_texture_size: DEFAULT_MAX_TEXTURE_SIZE,
         }
-    };
+    }
 
-    let width = image_data.width;
-    let height = image_data.height;
+    /// Flip the rows of the image so the first row is the bottom row.
+    pub fn flip_vertically(mut self, flip: bool) -> LoadOptions {
+        self.flip_vertically = flip;
+        self
+    }
 
-    // Check that the image size is a power of two.
-    let warnings = if (width & (width - 1)) != 0 || (height & (height - 1)) != 0 {
-        TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo
-    } else {
-        TexImage2DWarning::NoWarnings
-    };
+    /// Flip the columns of the image so the first column is the rightmost column.
+    pub fn flip_horizontally(mut self, flip: bool) -> LoadOptions {
+        self.flip_horizontally = flip;
+        self
+    }
 
-    let width_in_bytes = 4 *width;
-    let half_height = height / 2;
-    for row in 0..half_height {
-        for col in 0..width_in_bytes {
-            let temp = image_data.data[row * width_in_bytes + col];
-            image_data.data[row * width_in_bytes + col] = image_data.data[((height - row - 1) * width_in_bytes) + col];
-            image_data.data[((height - row - 1) * width_in_bytes) + col] = temp;
-        }
+    /// The number of channels to convert the image to. One channel images are
+    /// grayscale, two channel images are grayscale with alpha in the second
+    /// channel, three channel images are RGB, and four channel images are RGBA.
+    /// A channel count of zero keeps the number of channels stored in the image.
+    /// High dynamic range images with two or three channels are expanded to
+    /// four channels.
+    ///
+    /// # Panics
+    /// Panics if the channel count is larger than four.
+    pub fn desired_channels(mut self, channels: usize) -> LoadOptions {
+        assert!(channels <= 4, "A texture image has at most four channels, got {}.", channels);
+        self.desired_channels = channels;
+        self
     }
 
-    let tex_image_data = unsafe { 
-        let (old_ptr, old_length, old_capacity) = image_data.data.into_raw_parts();
-        let ptr = mem::transmute::<*mut u8, *mut Rgba>(old_ptr);
-        let length = old_length / 4;
-        let capacity = old_capacity / 4;
-        Vec::from_raw_parts(ptr, length, capacity)
-    };
-    let tex_image = TexImage2D::from_rgba_data(width as u32, height as u32, tex_image_data);
-    let result = TexImage2DResult {
-        image: tex_image,
-        warnings: warnings,
-    };
+    /// Multiply the color channels by the alpha channel.
+    pub fn premultiply_alpha(mut self, premultiply: bool) -> LoadOptions {
+        self.premultiply_alpha = premultiply;
+        self
+    }
 
-    Ok(result)
+    /// Tag the loaded texture image with the color space its color channels
+    /// are encoded in. The pixel data is not converted.
+    pub fn color_space(mut self, color_space: ColorSpace) -> LoadOptions {
+        self.color_space = color_space;
+        self
+    }
+
+    /// The maximum texture size the loaded texture image is checked against.
+    pub fn max_texture_size(mut self, max_texture_size: u32) -> LoadOptions {
+        self.max_texture_size = max_texture_size;
+        self
+    }
 }
 
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions::new()
+    }
+}
 
-/// Load a PNG texture image from a file name.
-pub fn load_file<P: AsRef<Path>>(file_path: P) -> Result<TexImage2DResult, TexImage2DError> {
-    let force_channels = 4;
-    let mut image_data = match image::load_with_depth(&file_path, force_channels, false) {
-        LoadResult::ImageU8(image_data) => image_data,
-        LoadResult::Error(_) => {
-            return Err(TexImage2DError::CouldNotLoadImageBuffer);
+/// Flip the rows of an image buffer so the first row is the bottom row of the
+/// image, since OpenGL expects texture data to start at the bottom left corner.
+fn flip_vertically<T: Copy>(data: &mut [T], width: usize, height: usize, depth: usize) {
+    let width_in_elements = depth * width;
+    let half_height = height / 2;
+    for row in 0..half_height {
+        let (top, bottom) = data.split_at_mut((height - row - 1) * width_in_elements);
+        top[row * width_in_elements..(row + 1) * width_in_elements]
+            .swap_with_slice(&mut bottom[0..width_in_elements]);
+    }
+}
+
+/// Flip the columns of an image buffer so the first column is the rightmost
+/// column of the image.
+fn flip_horizontally<T: Copy>(data: &mut [T], width: usize, height: usize, depth: usize) {
+    let width_in_elements = depth * width;
+    let half_width = width / 2;
+    for row in 0..height {
+        let row_data = &mut data[row * width_in_elements..(row + 1) * width_in_elements];
+        for col in 0..half_width {
+            for channel in 0..depth {
+                row_data.swap(col * depth + channel, (width - col - 1) * depth + channel);
+            }
         }
-        LoadResult::ImageF32(_) => {
-            return Err(TexImage2DError::Got32BitFloatingPointImageInsteadOfByteImage);
+    }
+}
+
+/// Multiply the color channels of an image buffer by the alpha channel. Only
+/// images with two or four channels have an alpha channel.
+fn premultiply_alpha<T: Channel>(data: &mut [T], depth: usize) {
+    if depth != 2 && depth != 4 {
+        return;
+    }
+
+    for pixel in data.chunks_mut(depth) {
+        let alpha = pixel[depth - 1].to_f32();
+        for channel in pixel[0..depth - 1].iter_mut() {
+            *channel = T::from_f32(channel.to_f32() * alpha);
         }
-    };
+    }
+}
+
+/// Apply the load options that operate on the raw channel data.
+fn apply_options<T: Channel>(data: &mut [T], width: usize, height: usize, depth: usize, options: &LoadOptions) {
+    if options.flip_vertically {
+        flip_vertically(data, width, height, depth);
+    }
+    if options.flip_horizontally {
+        flip_horizontally(data, width, height, depth);
+    }
+    if options.premultiply_alpha {
+        premultiply_alpha(data, depth);
+    }
+}
 
-    let width = image_data.width;
-    let height = image_data.height;
+fn byte_image_result(
+    width: usize,
+    height: usize,
+    depth: usize,
+    mut data: Vec<u8>,
+    options: &LoadOptions) -> TexImage2DResult
+{
+    apply_options(&mut data, width, height, depth, options);
 
-    // Check that the image size is a power of two.
-    let warnings = if (width & (width - 1)) != 0 || (height & (height - 1)) != 0 {
-        TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo
-    } else {
-        TexImage2DWarning::NoWarnings
+    let (width, height) = (width as u32, height as u32);
+    let tex_image = match depth {
+        1 => DynamicTexImage2D::L8(TexImage2D::from_channel_data(width, height, data)),
+        2 => DynamicTexImage2D::La8(TexImage2D::from_channel_data(width, height, data)),
+        3 => DynamicTexImage2D::Rgb8(TexImage2D::from_channel_data(width, height, data)),
+        _ => DynamicTexImage2D::Rgba8(TexImage2D::from_channel_data(width, height, data)),
     };
 
-    let width_in_bytes = 4 * width;
-    let half_height = height / 2;
-    for row in 0..half_height {
-        for col in 0..width_in_bytes {
-            let temp = image_data.data[row * width_in_bytes + col];
-            image_data.data[row * width_in_bytes + col] = image_data.data[((height - row - 1) * width_in_bytes) + col];
-            image_data.data[((height - row - 1) * width_in_bytes) + col] = temp;
-        }
+    TexImage2DResult {
+        warnings: tex_image.check_warnings(options.max_texture_size),
+        image: tex_image,
+        color_space: options.color_space,
     }
+}
+
+fn float_image_result(
+    width: usize,
+    height: usize,
+    depth: usize,
+    mut data: Vec<f32>,
+    options: &LoadOptions) -> TexImage2DResult
+{
+    apply_options(&mut data, width, height, depth, options);
 
-    let tex_image_data = unsafe { 
-        let (old_ptr, old_length, old_capacity) = image_data.data.into_raw_parts();
-        let ptr = mem::transmute::<*mut u8, *mut Rgba>(old_ptr);
-        let length = old_length / 4;
-        let capacity = old_capacity / 4;
-        Vec::from_raw_parts(ptr, length, capacity)
+    let tex_image = match depth {
+        1 => DynamicTexImage2D::RF32(TexImage2D::from_channel_data(width as u32, height as u32, data)),
+        4 => DynamicTexImage2D::RgbaF32(TexImage2D::from_channel_data(width as u32, height as u32, data)),
+        _ => {
+            // There are no two or three channel floating point pixel formats.
+            let data = data.chunks(depth).map(|chunk| {
+                match depth {
+                    2 => RgbaF32::new(chunk[0], chunk[0], chunk[0], chunk[1]),
+                    _ => RgbaF32::new(chunk[0], chunk[1], chunk[2], 1.0),
+                }
+            }).collect();
+            DynamicTexImage2D::RgbaF32(TexImage2D::from_data(width as u32, height as u32, data))
+        }
     };
-    let tex_image = TexImage2D::from_rgba_data(width as u32, height as u32, tex_image_data);
-    let result = TexImage2DResult {
+
+    TexImage2DResult {
+        warnings: tex_image.check_warnings(options.max_texture_size),
         image: tex_image,
-        warnings: warnings,
-    };
+        color_space: options.color_space,
+    }
+}
+
+/// Apply the load options to a decoded image. Every loader goes through this
+/// pipeline, whichever decoder decoded the image.
+fn process_decoded_image(image: DecodedImage, options: &LoadOptions) -> TexImage2DResult {
+    match image.data {
+        DecodedData::U8(data) => byte_image_result(image.width, image.height, image.depth, data, options),
+        DecodedData::F32(data) => float_image_result(image.width, image.height, image.depth, data, options),
+    }
+}
+
+/// Load a PNG texture image from a reader or buffer. High dynamic range
+/// images are loaded as floating point textures.
+pub fn load_from_memory(buffer: &[u8]) -> Result<TexImage2DResult, TexImage2DError> {
+    load_from_memory_with(buffer, &LoadOptions::default())
+}
+
+/// Load a PNG texture image from a reader or buffer using the given load options.
+pub fn load_from_memory_with(buffer: &[u8], options: &LoadOptions) -> Result<TexImage2DResult, TexImage2DError> {
+    load_with_decoder(decode::default_decoder(), buffer, options)
+}
+
+/// Load a texture image from a buffer with the given decoder, such as the
+/// pure Rust `PngDecoder`, using the given load options.
+pub fn load_with_decoder(
+    decoder: &dyn Decoder,
+    buffer: &[u8],
+    options: &LoadOptions) -> Result<TexImage2DResult, TexImage2DError>
+{
+    let image = decoder.decode(buffer, options.desired_channels)?;
+
+    Ok(process_decoded_image(image, options))
+}
 
-    Ok(result)
+/// Load a texture image from anything implementing `std::io::Read`, such as
+/// a network stream or a file inside an archive. High dynamic range images
+/// are loaded as floating point textures.
+pub fn load_from_reader<R: Read>(reader: R) -> Result<TexImage2DResult, TexImage2DError> {
+    load_from_reader_with(reader, &LoadOptions::default())
+}
+
+/// Load a texture image from a reader using the given load options.
+pub fn load_from_reader_with<R: Read>(mut reader: R, options: &LoadOptions) -> Result<TexImage2DResult, TexImage2DError> {
+    let mut buffer = vec![];
+    reader.read_to_end(&mut buffer).map_err(|e| TexImage2DError::Io { path: None, source: e })?;
+
+    load_from_memory_with(&buffer, options)
+}
+
+
+/// Load a PNG texture image from a file name. High dynamic range
+/// images are loaded as floating point textures.
+pub fn load_file<P: AsRef<Path>>(file_path: P) -> Result<TexImage2DResult, TexImage2DError> {
+    load_file_with(file_path, &LoadOptions::default())
+}
+
+/// Load a PNG texture image from a file name using the given load options.
+pub fn load_file_with<P: AsRef<Path>>(file_path: P, options: &LoadOptions) -> Result<TexImage2DResult, TexImage2DError> {
+    let file_path = file_path.as_ref();
+    let buffer = fs::read(file_path).map_err(|e| {
+        TexImage2DError::Io { path: Some(file_path.to_path_buf()), source: e }
+    })?;
+
+    load_from_memory_with(&buffer, options).map_err(|e| e.with_path(file_path))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Rgba;
+    use super::{Rgba, RgbaF32};
 
 
     #[test]
@@ -249,4 +958,232 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_raw_bytes_round_trip() {
+        let image = super::TexImage2D::from_data(2, 1, vec![Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)]);
+        let bytes = image.to_bytes();
+
+        assert_eq!(bytes, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(super::TexImage2D::from_bytes(2, 1, &bytes), image);
+    }
+
+    #[test]
+    fn test_get_and_put_pixel() {
+        let mut image = super::TexImage2D::from_data(2, 2, vec![Rgba::new(0, 0, 0, 0); 4]);
+        image.put_pixel(1, 0, Rgba::new(1, 2, 3, 4));
+
+        assert_eq!(image.get_pixel(1, 0), Rgba::new(1, 2, 3, 4));
+        assert_eq!(image.data[1], Rgba::new(1, 2, 3, 4));
+        assert_eq!(image.rows().next().unwrap(), &[Rgba::new(0, 0, 0, 0), Rgba::new(1, 2, 3, 4)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pixel_outside_the_texture_image_panics() {
+        let image: super::TexImage2D = super::TexImage2D::new(2, 2);
+        image.get_pixel(2, 0);
+    }
+
+    #[test]
+    fn test_as_bytes_views_the_pixel_data() {
+        let mut image = super::TexImage2D::from_data(2, 1, vec![Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)]);
+        image.as_bytes_mut()[0] = 9;
+        for row in image.rows_mut() {
+            row[1].a = 10;
+        }
+
+        assert_eq!(image.as_bytes(), &[9, 2, 3, 4, 5, 6, 7, 10]);
+        assert_eq!(super::TexImage2D::<Rgba>::new(0, 0).as_bytes(), &[] as &[u8]);
+    }
+
+    #[test]
+    fn test_channel_data_round_trip() {
+        let channels = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let image: super::TexImage2D = super::TexImage2D::from_channel_data(2, 1, channels.clone());
+
+        assert_eq!(image.data, vec![Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)]);
+        assert_eq!(image.as_channels(), &channels[..]);
+        assert_eq!(image.into_channel_data(), channels);
+    }
+
+    #[test]
+    fn test_channel_data_with_partial_pixel_capacity_is_copied() {
+        let mut channels = Vec::with_capacity(7);
+        channels.extend_from_slice(&[1.0, 2.0, 3.0, 4.0]);
+        let image: super::TexImage2D<RgbaF32> = super::TexImage2D::from_channel_data(1, 1, channels);
+
+        assert_eq!(image.data, vec![RgbaF32::new(1.0, 2.0, 3.0, 4.0)]);
+    }
+
+    #[test]
+    fn test_raw_bytes_store_wide_channels_little_endian() {
+        let image = super::TexImage2D::from_data(1, 1, vec![super::R16::new(0x1234)]);
+        let float_image = super::TexImage2D::from_data(1, 1, vec![RgbaF32::new(1.0, 0.5, -2.0, 0.0)]);
+
+        assert_eq!(image.to_bytes(), vec![0x34, 0x12]);
+        assert_eq!(super::TexImage2D::from_bytes(1, 1, &float_image.to_bytes()), float_image);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_bytes_rejects_mismatched_length() {
+        super::TexImage2D::<Rgba>::from_bytes(2, 2, &[0; 15]);
+    }
+
+    /// Build a flat (non run length encoded) Radiance image in memory. The pixels are
+    /// given in top to bottom row order as (mantissa, exponent) pairs.
+    #[cfg(feature = "stb-decoder")]
+    fn radiance_image(width: usize, height: usize, pixels: &[[u8; 4]]) -> Vec<u8> {
+        let mut buffer = format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width
+        ).into_bytes();
+        for pixel in pixels.iter() {
+            buffer.extend_from_slice(pixel);
+        }
+
+        buffer
+    }
+
+    #[cfg(feature = "stb-decoder")]
+    #[test]
+    fn test_load_from_memory_loads_hdr_image_as_floating_point_texture() {
+        let buffer = radiance_image(1, 1, &[[128, 64, 0, 129]]);
+        let result = super::load_from_memory(&buffer).unwrap();
+        let expected = RgbaF32::new(1.0, 0.5, 0.0, 1.0);
+
+        assert!(result.image.is_hdr());
+        assert_eq!(result.image.as_rgba_f32().unwrap().data, vec![expected]);
+    }
+
+    /// Floating point images get flipped vertically just like byte images.
+    #[cfg(feature = "stb-decoder")]
+    #[test]
+    fn test_load_from_memory_flips_hdr_image_vertically() {
+        let buffer = radiance_image(1, 2, &[[128, 128, 128, 129], [0, 0, 0, 0]]);
+        let result = super::load_from_memory(&buffer).unwrap();
+        let image = result.image.into_rgba_f32().unwrap();
+        let expected = vec![RgbaF32::new(0.0, 0.0, 0.0, 1.0), RgbaF32::new(1.0, 1.0, 1.0, 1.0)];
+
+        assert_eq!(image.data, expected);
+    }
+
+    #[cfg(feature = "stb-decoder")]
+    #[test]
+    fn test_load_from_memory_warns_about_hdr_image_dimensions() {
+        let buffer = radiance_image(3, 1, &[[0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]);
+        let result = super::load_from_memory(&buffer).unwrap();
+
+        assert!(!result.has_no_warnings());
+    }
+
+    /// Premultiplying alpha scales the color channels and leaves alpha alone.
+    #[test]
+    fn test_premultiply_alpha() {
+        let mut data = vec![255_u8, 128, 64, 128, 10, 20, 30, 255, 200, 0];
+        super::premultiply_alpha(&mut data[0..8], 4);
+        super::premultiply_alpha(&mut data[8..10], 2);
+
+        assert_eq!(data, vec![128, 64, 32, 128, 10, 20, 30, 255, 0, 0]);
+    }
+
+    #[test]
+    fn test_flip_vertically() {
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        super::flip_vertically(&mut data, 2, 3, 2);
+
+        assert_eq!(data, vec![9, 10, 11, 12, 5, 6, 7, 8, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_flip_horizontally() {
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        super::flip_horizontally(&mut data, 3, 2, 2);
+
+        assert_eq!(data, vec![5, 6, 3, 4, 1, 2, 11, 12, 9, 10, 7, 8]);
+    }
+
+    #[test]
+    fn test_loading_missing_file_reports_path_and_io_error() {
+        let result = super::load_file("assets/does_not_exist.png");
+        let error = result.unwrap_err();
+
+        assert_eq!(error.path(), Some(std::path::Path::new("assets/does_not_exist.png")));
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(format!("{}", error).starts_with("assets/does_not_exist.png: "));
+    }
+
+    #[cfg(feature = "stb-decoder")]
+    #[test]
+    fn test_loading_unknown_format_reports_unsupported_format() {
+        let result = super::load_from_memory(b"this is not an image");
+
+        match result {
+            Err(super::TexImage2DError::UnsupportedFormat { path: None, ref reason }) => {
+                assert_eq!(reason, "unknown image type");
+            }
+            other => panic!("expected an unsupported format error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "stb-decoder")]
+    #[test]
+    fn test_loading_corrupt_image_reports_decoder_reason() {
+        let mut buffer = std::fs::read("../assets/sample.png").unwrap();
+        // Replace the color type in the PNG header with an invalid one.
+        buffer[25] = 5;
+        let result = super::load_from_memory(&buffer);
+
+        match result {
+            Err(super::TexImage2DError::Decode { ref reason, .. }) => assert_eq!(reason, "bad ctype"),
+            other => panic!("expected a decode error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check_warnings_reports_every_warning() {
+        use super::{TexImage2D, TexImage2DWarning};
+
+        let data = vec![Rgba::new(10, 10, 10, 0), Rgba::new(20, 20, 20, 255), Rgba::new(30, 30, 30, 255)];
+        let image = TexImage2D::from_data(3, 1, data);
+        let warnings = image.check_warnings(2);
+        let expected = vec![
+            TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo,
+            TexImage2DWarning::TextureDimensionsExceedMaximumSize { max_texture_size: 2 },
+            TexImage2DWarning::TextureIsNotSquare,
+            TexImage2DWarning::AlphaChannelIsOneBit,
+            TexImage2DWarning::UsesOnlyGrayscaleValues,
+        ];
+
+        assert_eq!(warnings, expected);
+    }
+
+    #[test]
+    fn test_check_warnings_reports_opaque_alpha_instead_of_one_bit_alpha() {
+        use super::{TexImage2D, TexImage2DWarning};
+
+        let image = TexImage2D::from_data(1, 1, vec![Rgba::new(255, 0, 0, 255)]);
+        let warnings = image.check_warnings(super::DEFAULT_MAX_TEXTURE_SIZE);
+
+        assert_eq!(warnings, vec![TexImage2DWarning::AlphaChannelIsFullyOpaque]);
+    }
+
+    #[test]
+    fn test_check_warnings_on_empty_image() {
+        use super::{TexImage2D, TexImage2DWarning};
+
+        let image: TexImage2D = TexImage2D::new(0, 0);
+        let warnings = image.check_warnings(super::DEFAULT_MAX_TEXTURE_SIZE);
+
+        assert_eq!(warnings, vec![TexImage2DWarning::TextureDimensionsAreNotAPowerOfTwo]);
+    }
+
+    #[test]
+    fn test_warnings_are_displayable() {
+        use super::TexImage2DWarning;
+
+        let warning = TexImage2DWarning::TextureDimensionsExceedMaximumSize { max_texture_size: 4096 };
+
+        assert_eq!(format!("{}", warning), "Texture dimensions exceed the maximum texture size of 4096.");
+    }
 }
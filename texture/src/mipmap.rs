@@ -0,0 +1,187 @@
+use crate::{ColorSpace, Pixel, RgbaF32, TexImage2D};
+use crate::resample;
+use crate::resample::Filter;
+
+
+/// A chain of mipmap levels. The first level is the base texture image, and
+/// each following level has half the width and height of the previous level,
+/// rounded down, until the last level is one pixel by one pixel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MipChain<P = crate::Rgba> {
+    pub levels: Vec<TexImage2D<P>>,
+}
+
+impl<P: Pixel> MipChain<P> {
+    pub fn from_levels(levels: Vec<TexImage2D<P>>) -> MipChain<P> {
+        MipChain {
+            levels: levels,
+        }
+    }
+
+    /// The number of mipmap levels in the chain, including the base level.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Get a mipmap level. Level zero is the base level.
+    pub fn level(&self, level: usize) -> Option<&TexImage2D<P>> {
+        self.levels.get(level)
+    }
+
+    pub fn base(&self) -> Option<&TexImage2D<P>> {
+        self.levels.first()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, TexImage2D<P>> {
+        self.levels.iter()
+    }
+}
+
+impl<'a, P: Pixel> IntoIterator for &'a MipChain<P> {
+    type Item = &'a TexImage2D<P>;
+    type IntoIter = std::slice::Iter<'a, TexImage2D<P>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.levels.iter()
+    }
+}
+
+/// The number of mipmap levels needed to reduce an image of the given
+/// dimensions down to one pixel by one pixel, including the base level.
+pub fn mip_level_count(width: u32, height: u32) -> usize {
+    let largest = width.max(height).max(1);
+
+    (32 - largest.leading_zeros()) as usize
+}
+
+/// The dimensions of the next mipmap level after a level with the given dimensions.
+fn next_level_dimensions(width: u32, height: u32) -> (u32, u32) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Generate the full mipmap chain for a texture image whose color channels
+    /// are stored in linear light.
+    pub fn generate_mipmaps(&self, filter: Filter) -> MipChain<P> {
+        self.generate_mipmaps_with_color_space(filter, ColorSpace::Linear)
+    }
+
+    /// Generate the full mipmap chain for a texture image. When the texture image is
+    /// sRGB encoded, the color channels are converted to linear light before filtering
+    /// and converted back afterwards, so the mipmaps do not darken. The color channels
+    /// are filtered premultiplied by the alpha channel, the same way `resize` filters
+    /// them, so the color of transparent pixels does not bleed into the mipmaps.
+    ///
+    /// Every level is filtered from the level before it, so generating the chain costs
+    /// about a third more than filtering the base level once. The levels are kept in
+    /// floating point between steps, so filtering error does not accumulate through
+    /// rounding. Texture images whose dimensions are not a power of two are reduced by
+    /// rounding each dimension down, the same way graphics APIs compute mipmap dimensions.
+    pub fn generate_mipmaps_with_color_space(&self, filter: Filter, color_space: ColorSpace) -> MipChain<P> {
+        let mut previous: Vec<RgbaF32> = self.data.iter().map(|pixel| {
+            let color = pixel.to_rgba_f32();
+            match color_space {
+                ColorSpace::Linear => color,
+                ColorSpace::Srgb => color.srgb_to_linear(),
+            }
+        }).collect();
+
+        let mut levels = vec![self.clone()];
+        let (mut level_width, mut level_height) = (self.width, self.height);
+        for _ in 1..mip_level_count(self.width, self.height) {
+            let (next_width, next_height) = next_level_dimensions(level_width, level_height);
+            let filtered = resample::resample_premultiplied(
+                &previous,
+                level_width as usize,
+                level_height as usize,
+                next_width as usize,
+                next_height as usize,
+                filter,
+            );
+            let data = filtered.iter().map(|color| {
+                match color_space {
+                    ColorSpace::Linear => P::from_rgba_f32(*color),
+                    ColorSpace::Srgb => P::from_rgba_f32(color.linear_to_srgb()),
+                }
+            }).collect();
+            levels.push(TexImage2D::from_data(next_width, next_height, data));
+            previous = filtered;
+            level_width = next_width;
+            level_height = next_height;
+        }
+
+        MipChain::from_levels(levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rgba, R8};
+
+
+    #[test]
+    fn test_mip_level_count() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(16, 16), 5);
+        assert_eq!(mip_level_count(16, 4), 5);
+        assert_eq!(mip_level_count(5, 3), 3);
+        assert_eq!(mip_level_count(0, 0), 1);
+    }
+
+    #[test]
+    fn test_mipmap_chain_reaches_one_by_one() {
+        let image: TexImage2D = TexImage2D::new(12, 5);
+        let chain = image.generate_mipmaps(Filter::Box);
+        let dimensions: Vec<(u32, u32)> = chain.iter().map(|level| (level.width, level.height)).collect();
+
+        assert_eq!(dimensions, vec![(12, 5), (6, 2), (3, 1), (1, 1)]);
+        for level in chain.iter() {
+            assert_eq!(level.pixel_count(), (level.width * level.height) as usize);
+        }
+    }
+
+    #[test]
+    fn test_box_filtered_checkerboard_averages_to_gray() {
+        let data = (0..16).map(|i| if (i % 4 + i / 4) % 2 == 0 { R8::new(0) } else { R8::new(255) }).collect();
+        let image = TexImage2D::from_data(4, 4, data);
+        let chain = image.generate_mipmaps(Filter::Box);
+
+        assert_eq!(chain.level_count(), 3);
+        assert_eq!(chain.level(1).unwrap().data, vec![R8::new(128); 4]);
+        assert_eq!(chain.level(2).unwrap().data, vec![R8::new(128)]);
+    }
+
+    /// Averaging black and white in linear light yields a brighter sRGB value
+    /// than averaging the encoded values directly.
+    #[test]
+    fn test_srgb_mipmaps_do_not_darken() {
+        let data = vec![Rgba::new(0, 0, 0, 255), Rgba::new(255, 255, 255, 255)];
+        let image = TexImage2D::from_data(2, 1, data);
+        let linear = image.generate_mipmaps(Filter::Box);
+        let srgb = image.generate_mipmaps_with_color_space(Filter::Box, ColorSpace::Srgb);
+
+        assert_eq!(linear.level(1).unwrap().data, vec![Rgba::new(128, 128, 128, 255)]);
+        assert_eq!(srgb.level(1).unwrap().data, vec![Rgba::new(188, 188, 188, 255)]);
+    }
+
+    #[test]
+    fn test_transparent_pixels_do_not_bleed_into_mipmaps() {
+        let data = vec![Rgba::new(255, 0, 0, 0), Rgba::new(0, 0, 255, 255)];
+        let image = TexImage2D::from_data(2, 1, data);
+        let chain = image.generate_mipmaps(Filter::Box);
+
+        assert_eq!(chain.level(1).unwrap().data, vec![Rgba::new(0, 0, 255, 128)]);
+    }
+
+    #[test]
+    fn test_every_filter_preserves_constant_images() {
+        let image: TexImage2D = TexImage2D::from_data(7, 5, vec![Rgba::new(10, 200, 30, 128); 35]);
+        for filter in [Filter::Box, Filter::Triangle, Filter::Kaiser, Filter::Lanczos3].iter() {
+            let chain = image.generate_mipmaps(*filter);
+            for level in chain.iter() {
+                assert!(level.data.iter().all(|pixel| *pixel == Rgba::new(10, 200, 30, 128)));
+            }
+        }
+    }
+}
@@ -0,0 +1,900 @@
+use bytemuck::{Pod, Zeroable};
+use std::fmt;
+
+
+/// The pixel formats a texture image can be stored in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    R8,
+    Rg8,
+    L8,
+    La8,
+    Rgb8,
+    Rgba8,
+    R16,
+    Rgba16,
+    RF32,
+    RgbaF32,
+}
+
+impl PixelFormat {
+    /// Determine the pixel format from its name, such as `rgba8` or `r32f`.
+    /// Names are case insensitive and match the names the pixel formats are
+    /// displayed with.
+    pub fn from_name(name: &str) -> Option<PixelFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "r8" => Some(PixelFormat::R8),
+            "rg8" => Some(PixelFormat::Rg8),
+            "l8" => Some(PixelFormat::L8),
+            "la8" => Some(PixelFormat::La8),
+            "rgb8" => Some(PixelFormat::Rgb8),
+            "rgba8" => Some(PixelFormat::Rgba8),
+            "r16" => Some(PixelFormat::R16),
+            "rgba16" => Some(PixelFormat::Rgba16),
+            "r32f" => Some(PixelFormat::RF32),
+            "rgba32f" => Some(PixelFormat::RgbaF32),
+            _ => None,
+        }
+    }
+
+    /// The number of color channels in a pixel.
+    pub fn channel_count(self) -> usize {
+        match self {
+            PixelFormat::R8 | PixelFormat::L8 | PixelFormat::R16 | PixelFormat::RF32 => 1,
+            PixelFormat::Rg8 | PixelFormat::La8 => 2,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 | PixelFormat::Rgba16 | PixelFormat::RgbaF32 => 4,
+        }
+    }
+
+    /// The number of bytes occupied by a single color channel.
+    pub fn bytes_per_channel(self) -> usize {
+        match self {
+            PixelFormat::R8
+            | PixelFormat::Rg8
+            | PixelFormat::L8
+            | PixelFormat::La8
+            | PixelFormat::Rgb8
+            | PixelFormat::Rgba8 => 1,
+            PixelFormat::R16 | PixelFormat::Rgba16 => 2,
+            PixelFormat::RF32 | PixelFormat::RgbaF32 => 4,
+        }
+    }
+
+    /// The number of bytes occupied by a single pixel.
+    pub fn bytes_per_pixel(self) -> usize {
+        self.channel_count() * self.bytes_per_channel()
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            PixelFormat::R8 => "R8",
+            PixelFormat::Rg8 => "RG8",
+            PixelFormat::L8 => "L8",
+            PixelFormat::La8 => "LA8",
+            PixelFormat::Rgb8 => "RGB8",
+            PixelFormat::Rgba8 => "RGBA8",
+            PixelFormat::R16 => "R16",
+            PixelFormat::Rgba16 => "RGBA16",
+            PixelFormat::RF32 => "R32F",
+            PixelFormat::RgbaF32 => "RGBA32F",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The color space the color channels of a texture image are encoded in. The
+/// alpha channel is always linear.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+impl Default for ColorSpace {
+    #[inline]
+    fn default() -> ColorSpace {
+        ColorSpace::Linear
+    }
+}
+
+/// Convert an sRGB encoded color channel value to linear light.
+#[inline]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear light color channel value to sRGB encoding.
+#[inline]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl RgbaF32 {
+    /// Convert the color channels of an sRGB encoded color to linear light.
+    #[inline]
+    pub fn srgb_to_linear(self) -> RgbaF32 {
+        RgbaF32::new(srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a)
+    }
+
+    /// Convert the color channels of a linear light color to sRGB encoding.
+    #[inline]
+    pub fn linear_to_srgb(self) -> RgbaF32 {
+        RgbaF32::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a)
+    }
+}
+
+/// The storage type of a single color channel in a pixel.
+pub trait Channel: Copy + Clone + PartialEq + fmt::Debug + Pod {
+    /// Convert the channel value to a floating point value. Integer channels
+    /// are normalized to the unit interval.
+    fn to_f32(self) -> f32;
+
+    /// Convert a floating point value to a channel value. Integer channels
+    /// clamp the value to the unit interval and round to the nearest integer.
+    fn from_f32(value: f32) -> Self;
+
+    /// Read a channel value from the start of a byte slice, where multi byte
+    /// channels are stored in little endian byte order.
+    fn from_le_slice(bytes: &[u8]) -> Self;
+
+    /// Append the bytes of the channel value in little endian byte order.
+    fn push_le_bytes(self, bytes: &mut Vec<u8>);
+}
+
+impl Channel for u8 {
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32 / 255.0
+    }
+
+    #[inline]
+    fn from_f32(value: f32) -> u8 {
+        (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+    }
+
+    #[inline]
+    fn from_le_slice(bytes: &[u8]) -> u8 {
+        bytes[0]
+    }
+
+    #[inline]
+    fn push_le_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.push(self);
+    }
+}
+
+impl Channel for u16 {
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32 / 65535.0
+    }
+
+    #[inline]
+    fn from_f32(value: f32) -> u16 {
+        (value.clamp(0.0, 1.0) * 65535.0 + 0.5) as u16
+    }
+
+    #[inline]
+    fn from_le_slice(bytes: &[u8]) -> u16 {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+
+    #[inline]
+    fn push_le_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Channel for f32 {
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    #[inline]
+    fn from_f32(value: f32) -> f32 {
+        value
+    }
+
+    #[inline]
+    fn from_le_slice(bytes: &[u8]) -> f32 {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    #[inline]
+    fn push_le_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// A pixel in a texture image. Conversions between pixel formats go through
+/// `RgbaF32`, and follow the OpenGL convention of filling in missing color
+/// channels with zero and a missing alpha channel with one.
+///
+/// Pixel types are `Pod` structs of `CHANNEL_COUNT` channels in RGBA order, so
+/// the pixel data of a texture image can be cast to channels or raw bytes
+/// without copying, and handed to graphics APIs as is.
+pub trait Pixel: Copy + Clone + Default + PartialEq + fmt::Debug + Pod {
+    type Channel: Channel;
+
+    /// The pixel format tag for the pixel type.
+    const FORMAT: PixelFormat;
+
+    /// The number of color channels in the pixel.
+    const CHANNEL_COUNT: usize;
+
+    /// Construct a pixel from a slice of channel values in RGBA order.
+    fn from_channels(channels: &[Self::Channel]) -> Self;
+
+    /// Get the channel value at the given index in RGBA order.
+    fn channel(&self, index: usize) -> Self::Channel;
+
+    fn to_rgba_f32(&self) -> RgbaF32;
+
+    fn from_rgba_f32(color: RgbaF32) -> Self;
+
+    /// Convert the pixel to a pixel of a different format.
+    #[inline]
+    fn convert<Q: Pixel>(&self) -> Q {
+        Q::from_rgba_f32(self.to_rgba_f32())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct R8 {
+    pub r: u8,
+}
+
+impl R8 {
+    #[inline]
+    pub const fn new(r: u8) -> R8 {
+        R8 { r: r }
+    }
+}
+
+impl Default for R8 {
+    #[inline]
+    fn default() -> R8 {
+        R8::new(0)
+    }
+}
+
+impl Pixel for R8 {
+    type Channel = u8;
+    const FORMAT: PixelFormat = PixelFormat::R8;
+    const CHANNEL_COUNT: usize = 1;
+
+    #[inline]
+    fn from_channels(channels: &[u8]) -> R8 {
+        R8::new(channels[0])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u8 {
+        [self.r][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::new(self.r.to_f32(), 0.0, 0.0, 1.0)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> R8 {
+        R8::new(u8::from_f32(color.r))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Rg8 {
+    pub r: u8,
+    pub g: u8,
+}
+
+impl Rg8 {
+    #[inline]
+    pub const fn new(r: u8, g: u8) -> Rg8 {
+        Rg8 { r: r, g: g }
+    }
+}
+
+impl Default for Rg8 {
+    #[inline]
+    fn default() -> Rg8 {
+        Rg8::new(0, 0)
+    }
+}
+
+impl Pixel for Rg8 {
+    type Channel = u8;
+    const FORMAT: PixelFormat = PixelFormat::Rg8;
+    const CHANNEL_COUNT: usize = 2;
+
+    #[inline]
+    fn from_channels(channels: &[u8]) -> Rg8 {
+        Rg8::new(channels[0], channels[1])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u8 {
+        [self.r, self.g][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::new(self.r.to_f32(), self.g.to_f32(), 0.0, 1.0)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> Rg8 {
+        Rg8::new(u8::from_f32(color.r), u8::from_f32(color.g))
+    }
+}
+
+/// The luma of a color, using the same weights as the loaders use to reduce
+/// color images to grayscale.
+#[inline]
+fn luma(color: RgbaF32) -> f32 {
+    0.299 * color.r + 0.587 * color.g + 0.114 * color.b
+}
+
+/// A grayscale pixel. Unlike `R8`, which stores a red channel, the value is
+/// expanded into every color channel when converting to other formats, the
+/// same way OpenGL treats luminance textures. Grayscale images are loaded as
+/// `L8` pixels.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct L8 {
+    pub l: u8,
+}
+
+impl L8 {
+    #[inline]
+    pub const fn new(l: u8) -> L8 {
+        L8 { l: l }
+    }
+}
+
+impl Default for L8 {
+    #[inline]
+    fn default() -> L8 {
+        L8::new(0)
+    }
+}
+
+impl Pixel for L8 {
+    type Channel = u8;
+    const FORMAT: PixelFormat = PixelFormat::L8;
+    const CHANNEL_COUNT: usize = 1;
+
+    #[inline]
+    fn from_channels(channels: &[u8]) -> L8 {
+        L8::new(channels[0])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u8 {
+        [self.l][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        let l = self.l.to_f32();
+        RgbaF32::new(l, l, l, 1.0)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> L8 {
+        L8::new(u8::from_f32(luma(color)))
+    }
+}
+
+/// A grayscale pixel with alpha. The gray value is expanded into every color
+/// channel when converting to other formats. Grayscale images with alpha are
+/// loaded as `La8` pixels.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct La8 {
+    pub l: u8,
+    pub a: u8,
+}
+
+impl La8 {
+    #[inline]
+    pub const fn new(l: u8, a: u8) -> La8 {
+        La8 { l: l, a: a }
+    }
+}
+
+impl Default for La8 {
+    #[inline]
+    fn default() -> La8 {
+        La8::new(0, 255)
+    }
+}
+
+impl Pixel for La8 {
+    type Channel = u8;
+    const FORMAT: PixelFormat = PixelFormat::La8;
+    const CHANNEL_COUNT: usize = 2;
+
+    #[inline]
+    fn from_channels(channels: &[u8]) -> La8 {
+        La8::new(channels[0], channels[1])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u8 {
+        [self.l, self.a][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        let l = self.l.to_f32();
+        RgbaF32::new(l, l, l, self.a.to_f32())
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> La8 {
+        La8::new(u8::from_f32(luma(color)), u8::from_f32(color.a))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Rgb8 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb8 {
+    #[inline]
+    pub const fn new(r: u8, g: u8, b: u8) -> Rgb8 {
+        Rgb8 { r: r, g: g, b: b }
+    }
+}
+
+impl Default for Rgb8 {
+    #[inline]
+    fn default() -> Rgb8 {
+        Rgb8::new(0, 0, 0)
+    }
+}
+
+impl Pixel for Rgb8 {
+    type Channel = u8;
+    const FORMAT: PixelFormat = PixelFormat::Rgb8;
+    const CHANNEL_COUNT: usize = 3;
+
+    #[inline]
+    fn from_channels(channels: &[u8]) -> Rgb8 {
+        Rgb8::new(channels[0], channels[1], channels[2])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u8 {
+        [self.r, self.g, self.b][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::new(self.r.to_f32(), self.g.to_f32(), self.b.to_f32(), 1.0)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> Rgb8 {
+        Rgb8::new(u8::from_f32(color.r), u8::from_f32(color.g), u8::from_f32(color.b))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// An alias for `Rgba` that names the storage format explicitly.
+pub type Rgba8 = Rgba;
+
+impl Rgba {
+    #[inline]
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Rgba {
+        Rgba { r: r, g: g, b: b, a: a }
+    }
+}
+
+impl Default for Rgba {
+    #[inline]
+    fn default() -> Rgba {
+        Rgba::new(0, 0, 0, 255)
+    }
+}
+
+impl From<u32> for Rgba {
+    #[inline]
+    fn from(val: u32) -> Rgba {
+        Rgba {
+            r: ((val & 0xFF000000) >> 24) as u8,
+            g: ((val & 0x00FF0000) >> 16) as u8,
+            b: ((val & 0x0000FF00) >> 8) as u8,
+            a: ((val & 0x000000FF) >> 0) as u8,
+        }
+    }
+}
+
+impl From<R8> for Rgba {
+    #[inline]
+    fn from(val: R8) -> Rgba {
+        Rgba::new(val.r, 0, 0, 255)
+    }
+}
+
+impl From<Rg8> for Rgba {
+    #[inline]
+    fn from(val: Rg8) -> Rgba {
+        Rgba::new(val.r, val.g, 0, 255)
+    }
+}
+
+impl From<L8> for Rgba {
+    #[inline]
+    fn from(val: L8) -> Rgba {
+        Rgba::new(val.l, val.l, val.l, 255)
+    }
+}
+
+impl From<La8> for Rgba {
+    #[inline]
+    fn from(val: La8) -> Rgba {
+        Rgba::new(val.l, val.l, val.l, val.a)
+    }
+}
+
+impl From<Rgb8> for Rgba {
+    #[inline]
+    fn from(val: Rgb8) -> Rgba {
+        Rgba::new(val.r, val.g, val.b, 255)
+    }
+}
+
+impl From<Rgba> for R8 {
+    #[inline]
+    fn from(val: Rgba) -> R8 {
+        R8::new(val.r)
+    }
+}
+
+impl From<Rgba> for Rg8 {
+    #[inline]
+    fn from(val: Rgba) -> Rg8 {
+        Rg8::new(val.r, val.g)
+    }
+}
+
+impl From<Rgba> for Rgb8 {
+    #[inline]
+    fn from(val: Rgba) -> Rgb8 {
+        Rgb8::new(val.r, val.g, val.b)
+    }
+}
+
+impl Pixel for Rgba {
+    type Channel = u8;
+    const FORMAT: PixelFormat = PixelFormat::Rgba8;
+    const CHANNEL_COUNT: usize = 4;
+
+    #[inline]
+    fn from_channels(channels: &[u8]) -> Rgba {
+        Rgba::new(channels[0], channels[1], channels[2], channels[3])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u8 {
+        [self.r, self.g, self.b, self.a][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::from(*self)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> Rgba {
+        Rgba::new(
+            u8::from_f32(color.r), u8::from_f32(color.g), u8::from_f32(color.b), u8::from_f32(color.a)
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct R16 {
+    pub r: u16,
+}
+
+impl R16 {
+    #[inline]
+    pub const fn new(r: u16) -> R16 {
+        R16 { r: r }
+    }
+}
+
+impl Default for R16 {
+    #[inline]
+    fn default() -> R16 {
+        R16::new(0)
+    }
+}
+
+impl Pixel for R16 {
+    type Channel = u16;
+    const FORMAT: PixelFormat = PixelFormat::R16;
+    const CHANNEL_COUNT: usize = 1;
+
+    #[inline]
+    fn from_channels(channels: &[u16]) -> R16 {
+        R16::new(channels[0])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u16 {
+        [self.r][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::new(self.r.to_f32(), 0.0, 0.0, 1.0)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> R16 {
+        R16::new(u16::from_f32(color.r))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Rgba16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+    pub a: u16,
+}
+
+impl Rgba16 {
+    #[inline]
+    pub const fn new(r: u16, g: u16, b: u16, a: u16) -> Rgba16 {
+        Rgba16 { r: r, g: g, b: b, a: a }
+    }
+}
+
+impl Default for Rgba16 {
+    #[inline]
+    fn default() -> Rgba16 {
+        Rgba16::new(0, 0, 0, 65535)
+    }
+}
+
+impl From<Rgba> for Rgba16 {
+    #[inline]
+    fn from(val: Rgba) -> Rgba16 {
+        // Replicating the byte into both halves maps 0xFF to 0xFFFF exactly.
+        Rgba16::new(
+            (val.r as u16) * 257, (val.g as u16) * 257, (val.b as u16) * 257, (val.a as u16) * 257
+        )
+    }
+}
+
+impl Pixel for Rgba16 {
+    type Channel = u16;
+    const FORMAT: PixelFormat = PixelFormat::Rgba16;
+    const CHANNEL_COUNT: usize = 4;
+
+    #[inline]
+    fn from_channels(channels: &[u16]) -> Rgba16 {
+        Rgba16::new(channels[0], channels[1], channels[2], channels[3])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> u16 {
+        [self.r, self.g, self.b, self.a][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::new(self.r.to_f32(), self.g.to_f32(), self.b.to_f32(), self.a.to_f32())
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> Rgba16 {
+        Rgba16::new(
+            u16::from_f32(color.r), u16::from_f32(color.g), u16::from_f32(color.b), u16::from_f32(color.a)
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct RF32 {
+    pub r: f32,
+}
+
+impl RF32 {
+    #[inline]
+    pub const fn new(r: f32) -> RF32 {
+        RF32 { r: r }
+    }
+}
+
+impl Default for RF32 {
+    #[inline]
+    fn default() -> RF32 {
+        RF32::new(0.0)
+    }
+}
+
+impl Pixel for RF32 {
+    type Channel = f32;
+    const FORMAT: PixelFormat = PixelFormat::RF32;
+    const CHANNEL_COUNT: usize = 1;
+
+    #[inline]
+    fn from_channels(channels: &[f32]) -> RF32 {
+        RF32::new(channels[0])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> f32 {
+        [self.r][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        RgbaF32::new(self.r, 0.0, 0.0, 1.0)
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> RF32 {
+        RF32::new(color.r)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct RgbaF32 {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl RgbaF32 {
+    #[inline]
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> RgbaF32 {
+        RgbaF32 { r: r, g: g, b: b, a: a }
+    }
+}
+
+impl Default for RgbaF32 {
+    #[inline]
+    fn default() -> RgbaF32 {
+        RgbaF32::new(0.0, 0.0, 0.0, 1.0)
+    }
+}
+
+impl From<Rgba> for RgbaF32 {
+    #[inline]
+    fn from(val: Rgba) -> RgbaF32 {
+        RgbaF32 {
+            r: val.r as f32 / 255.0,
+            g: val.g as f32 / 255.0,
+            b: val.b as f32 / 255.0,
+            a: val.a as f32 / 255.0,
+        }
+    }
+}
+
+impl Pixel for RgbaF32 {
+    type Channel = f32;
+    const FORMAT: PixelFormat = PixelFormat::RgbaF32;
+    const CHANNEL_COUNT: usize = 4;
+
+    #[inline]
+    fn from_channels(channels: &[f32]) -> RgbaF32 {
+        RgbaF32::new(channels[0], channels[1], channels[2], channels[3])
+    }
+
+    #[inline]
+    fn channel(&self, index: usize) -> f32 {
+        [self.r, self.g, self.b, self.a][index]
+    }
+
+    #[inline]
+    fn to_rgba_f32(&self) -> RgbaF32 {
+        *self
+    }
+
+    #[inline]
+    fn from_rgba_f32(color: RgbaF32) -> RgbaF32 {
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_byte_channel_round_trips_through_f32() {
+        for value in 0..=255_u8 {
+            assert_eq!(u8::from_f32(value.to_f32()), value);
+        }
+    }
+
+    #[test]
+    fn test_pixel_format_names_round_trip() {
+        let formats = [
+            PixelFormat::R8, PixelFormat::Rg8, PixelFormat::L8, PixelFormat::La8, PixelFormat::Rgb8, PixelFormat::Rgba8,
+            PixelFormat::R16, PixelFormat::Rgba16, PixelFormat::RF32, PixelFormat::RgbaF32,
+        ];
+        for format in formats.iter() {
+            assert_eq!(PixelFormat::from_name(&format!("{}", format)), Some(*format));
+        }
+        assert_eq!(PixelFormat::from_name("rgb8"), Some(PixelFormat::Rgb8));
+        assert_eq!(PixelFormat::from_name("bgra8"), None);
+    }
+
+    #[test]
+    fn test_rgba_to_rgba16_conversion_matches_generic_conversion() {
+        let color = Rgba::new(0x00, 0x7F, 0x80, 0xFF);
+        let result: Rgba16 = color.convert();
+        let expected = Rgba16::from(color);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_single_channel_conversion_to_rgba_fills_missing_channels() {
+        let result: Rgba = R8::new(0x42).convert();
+        let expected = Rgba::new(0x42, 0x00, 0x00, 0xFF);
+
+        assert_eq!(result, expected);
+        assert_eq!(Rgba::from(R8::new(0x42)), expected);
+    }
+
+    #[test]
+    fn test_grayscale_conversion_to_rgba_expands_gray() {
+        let result: Rgba = La8::new(0x42, 0x80).convert();
+
+        assert_eq!(result, Rgba::new(0x42, 0x42, 0x42, 0x80));
+        assert_eq!(Rgba::from(L8::new(0x42)), Rgba::new(0x42, 0x42, 0x42, 0xFF));
+        assert_eq!(L8::from_rgba_f32(RgbaF32::from(Rgba::new(0x42, 0x42, 0x42, 0xFF))), L8::new(0x42));
+    }
+
+    #[test]
+    fn test_conversion_clamps_hdr_values() {
+        let result: Rgb8 = RgbaF32::new(2.0, -1.0, 0.5, 1.0).convert();
+        let expected = Rgb8::new(0xFF, 0x00, 0x80);
+
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{Pixel, RgbaF32, TexImage2D};
+use std::convert::TryFrom;
+
+
+/// The reconstruction filters used when resampling a texture image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    /// Takes the source pixel nearest to the center of the destination pixel.
+    Nearest,
+    /// Averages every source pixel that falls inside the destination pixel.
+    Box,
+    /// A tent filter. When magnifying this is bilinear interpolation.
+    Triangle,
+    /// The Catmull-Rom cubic filter, with a radius of two pixels. When
+    /// magnifying this is bicubic interpolation.
+    Bicubic,
+    /// A windowed sinc filter using a Kaiser window with a radius of three pixels.
+    Kaiser,
+    /// A windowed sinc filter using a Lanczos window with a radius of three pixels.
+    Lanczos3,
+}
+
+impl Filter {
+    /// The radius of the filter in source pixels at unit scale.
+    fn support(self) -> f64 {
+        match self {
+            Filter::Nearest => 0.5,
+            Filter::Box => 0.5,
+            Filter::Triangle => 1.0,
+            Filter::Bicubic => 2.0,
+            Filter::Kaiser => 3.0,
+            Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn evaluate(self, x: f64) -> f64 {
+        match self {
+            Filter::Nearest | Filter::Box => {
+                if (-0.5..0.5).contains(&x) { 1.0 } else { 0.0 }
+            }
+            Filter::Triangle => {
+                let x = x.abs();
+                if x < 1.0 { 1.0 - x } else { 0.0 }
+            }
+            Filter::Bicubic => {
+                let x = x.abs();
+                if x < 1.0 {
+                    (1.5 * x - 2.5) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Kaiser => {
+                const ALPHA: f64 = 4.0;
+                let t = x / 3.0;
+                if t.abs() < 1.0 {
+                    sinc(x) * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+                } else {
+                    0.0
+                }
+            }
+            Filter::Lanczos3 => {
+                if x.abs() < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 }
+            }
+        }
+    }
+}
+
+/// Compute `sin(pi * x)` using only basic arithmetic. The platform math library
+/// is avoided so resampling yields identical results on every platform.
+fn sin_pi(x: f64) -> f64 {
+    // Reduce the argument to the interval [-1, 1].
+    let mut t = x - 2.0 * (x / 2.0).round();
+    // Use the symmetry sin(pi * (1 - t)) == sin(pi * t) to reduce to [-1/2, 1/2].
+    if t > 0.5 {
+        t = 1.0 - t;
+    } else if t < -0.5 {
+        t = -1.0 - t;
+    }
+
+    let theta = std::f64::consts::PI * t;
+    let theta_squared = theta * theta;
+    let mut term = theta;
+    let mut sum = theta;
+    for n in 1..12 {
+        term *= -theta_squared / (((2 * n) * (2 * n + 1)) as f64);
+        sum += term;
+    }
+
+    sum
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-8 {
+        1.0
+    } else {
+        sin_pi(x) / (std::f64::consts::PI * x)
+    }
+}
+
+/// The zeroth order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let half_x = x / 2.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-16 {
+        term *= (half_x / k) * (half_x / k);
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
+/// The source pixels and normalized weights contributing to one destination pixel.
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Compute the filter weights for resampling a row of `src_len` pixels into a
+/// row of `dst_len` pixels. Source pixels past the edges of the row are clamped
+/// to the nearest edge pixel, which is folded into the weight of the edge pixel.
+fn contributions(src_len: usize, dst_len: usize, filter: Filter) -> Vec<Contribution> {
+    let scale = src_len as f64 / dst_len as f64;
+    // When minifying, the filter is stretched to cover every source pixel.
+    let filter_scale = if scale > 1.0 { scale } else { 1.0 };
+    let radius = filter.support() * filter_scale;
+
+    let mut result = Vec::with_capacity(dst_len);
+    for i in 0..dst_len {
+        let center = (i as f64 + 0.5) * scale;
+        if filter == Filter::Nearest {
+            let index = (center.floor() as usize).min(src_len - 1);
+            result.push(Contribution { start: index, weights: vec![1.0] });
+            continue;
+        }
+
+        let left = (center - radius).floor() as isize;
+        let right = (center + radius).ceil() as isize;
+        let first = left.max(0).min(src_len as isize - 1) as usize;
+        let last = (right - 1).max(0).min(src_len as isize - 1) as usize;
+        let mut weights = vec![0_f64; last - first + 1];
+        for j in left..right {
+            let weight = filter.evaluate((j as f64 + 0.5 - center) / filter_scale);
+            let index = j.max(first as isize).min(last as isize) as usize;
+            weights[index - first] += weight;
+        }
+
+        let total: f64 = weights.iter().sum();
+        if total == 0.0 {
+            // The filter missed every pixel, so fall back to the nearest pixel.
+            let index = (center.floor() as usize).min(src_len - 1);
+            result.push(Contribution { start: index, weights: vec![1.0] });
+            continue;
+        }
+
+        let weights = weights.iter().map(|weight| (weight / total) as f32).collect();
+        result.push(Contribution { start: first, weights: weights });
+    }
+
+    result
+}
+
+#[inline]
+fn accumulate(sum: &mut RgbaF32, color: RgbaF32, weight: f32) {
+    sum.r += color.r * weight;
+    sum.g += color.g * weight;
+    sum.b += color.b * weight;
+    sum.a += color.a * weight;
+}
+
+/// Resample an image with a separable filter, first along the rows and then
+/// along the columns.
+pub(crate) fn resample(
+    src: &[RgbaF32],
+    src_width: usize,
+    src_height: usize,
+    dst_width: usize,
+    dst_height: usize,
+    filter: Filter) -> Vec<RgbaF32>
+{
+    if src_width == 0 || src_height == 0 || dst_width == 0 || dst_height == 0 {
+        return vec![RgbaF32::new(0.0, 0.0, 0.0, 0.0); dst_width * dst_height];
+    }
+
+    let zero = RgbaF32::new(0.0, 0.0, 0.0, 0.0);
+    let horizontal = contributions(src_width, dst_width, filter);
+    let mut temp = vec![zero; dst_width * src_height];
+    for row in 0..src_height {
+        let src_row = &src[row * src_width..(row + 1) * src_width];
+        for (col, contribution) in horizontal.iter().enumerate() {
+            let mut sum = zero;
+            for (k, weight) in contribution.weights.iter().enumerate() {
+                accumulate(&mut sum, src_row[contribution.start + k], *weight);
+            }
+            temp[row * dst_width + col] = sum;
+        }
+    }
+
+    let vertical = contributions(src_height, dst_height, filter);
+    let mut dst = vec![zero; dst_width * dst_height];
+    for (row, contribution) in vertical.iter().enumerate() {
+        for col in 0..dst_width {
+            let mut sum = zero;
+            for (k, weight) in contribution.weights.iter().enumerate() {
+                accumulate(&mut sum, temp[(contribution.start + k) * dst_width + col], *weight);
+            }
+            dst[row * dst_width + col] = sum;
+        }
+    }
+
+    dst
+}
+
+/// Resample an image with alpha, filtering the color channels premultiplied
+/// by the alpha channel, so the color of transparent pixels does not bleed
+/// into their neighbours.
+pub(crate) fn resample_premultiplied(
+    src: &[RgbaF32],
+    src_width: usize,
+    src_height: usize,
+    dst_width: usize,
+    dst_height: usize,
+    filter: Filter) -> Vec<RgbaF32>
+{
+    let premultiplied: Vec<RgbaF32> = src.iter().map(|color| {
+        RgbaF32::new(color.r * color.a, color.g * color.a, color.b * color.a, color.a)
+    }).collect();
+
+    let filtered = resample(&premultiplied, src_width, src_height, dst_width, dst_height, filter);
+
+    filtered.into_iter().map(|color| {
+        if color.a > 0.0 {
+            RgbaF32::new(color.r / color.a, color.g / color.a, color.b / color.a, color.a)
+        } else {
+            RgbaF32::new(0.0, 0.0, 0.0, 0.0)
+        }
+    }).collect()
+}
+
+/// How `resize_to_power_of_two` picks the power of two to resize each
+/// dimension of a texture image to. Dimensions above 2^31 have no larger
+/// power of two that fits in a `u32`, so where a policy would round them up
+/// they saturate to 2^31 instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// The smallest power of two that is not smaller than the dimension.
+    Up,
+    /// The largest power of two that is not larger than the dimension.
+    Down,
+    /// The power of two closest to the dimension. Ties round up.
+    Nearest,
+}
+
+impl Policy {
+    /// The power of two a dimension is resized to.
+    fn apply(self, dimension: u32) -> u32 {
+        let dimension = dimension.max(1);
+        let up = u64::from(dimension).next_power_of_two();
+        let down = 1 << (31 - dimension.leading_zeros());
+        let saturated_up = u32::try_from(up).unwrap_or(down);
+        match self {
+            Policy::Up => saturated_up,
+            Policy::Down => down,
+            Policy::Nearest => {
+                if up - u64::from(dimension) <= u64::from(dimension - down) { saturated_up } else { down }
+            }
+        }
+    }
+}
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Resize the texture image with the given filter. The color channels are
+    /// filtered premultiplied by the alpha channel. Resampling uses only basic
+    /// arithmetic, so the result is identical on every platform.
+    pub fn resize(&self, width: u32, height: u32, filter: Filter) -> TexImage2D<P> {
+        if width == self.width && height == self.height {
+            return self.clone();
+        }
+
+        let src: Vec<RgbaF32> = self.data.iter().map(|pixel| pixel.to_rgba_f32()).collect();
+        let filtered = resample_premultiplied(
+            &src, self.width as usize, self.height as usize, width as usize, height as usize, filter
+        );
+        let data = filtered.into_iter().map(P::from_rgba_f32).collect();
+
+        TexImage2D::from_data(width, height, data)
+    }
+
+    /// Resize the texture image so both of its dimensions are powers of two,
+    /// which clears the `TextureDimensionsAreNotAPowerOfTwo` warning.
+    pub fn resize_to_power_of_two(&self, policy: Policy, filter: Filter) -> TexImage2D<P> {
+        self.resize(policy.apply(self.width), policy.apply(self.height), filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_sin_pi_matches_library_sine() {
+        for i in -400..400 {
+            let x = i as f64 / 37.0;
+            let expected = (std::f64::consts::PI * x).sin();
+
+            assert!((sin_pi(x) - expected).abs() < 1e-12, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_filter_weights_are_normalized() {
+        let filters = [
+            Filter::Nearest, Filter::Box, Filter::Triangle, Filter::Bicubic, Filter::Kaiser, Filter::Lanczos3,
+        ];
+        for filter in filters.iter() {
+            for &(src_len, dst_len) in [(16, 8), (7, 3), (5, 2), (3, 1), (4, 9)].iter() {
+                for contribution in contributions(src_len, dst_len, *filter) {
+                    let total: f32 = contribution.weights.iter().sum();
+
+                    assert!((total - 1.0).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_box_filter_halving_averages_pixel_pairs() {
+        let src = vec![
+            RgbaF32::new(0.0, 0.0, 0.0, 1.0), RgbaF32::new(1.0, 0.5, 0.0, 1.0),
+            RgbaF32::new(0.5, 0.5, 0.5, 0.0), RgbaF32::new(0.5, 0.5, 0.5, 0.0),
+        ];
+        let result = resample(&src, 4, 1, 2, 1, Filter::Box);
+        let expected = vec![RgbaF32::new(0.5, 0.25, 0.0, 1.0), RgbaF32::new(0.5, 0.5, 0.5, 0.0)];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_power_of_two_policies() {
+        assert_eq!(Policy::Up.apply(300), 512);
+        assert_eq!(Policy::Down.apply(300), 256);
+        assert_eq!(Policy::Nearest.apply(300), 256);
+        assert_eq!(Policy::Nearest.apply(400), 512);
+        assert_eq!(Policy::Nearest.apply(384), 512);
+        assert_eq!(Policy::Up.apply(256), 256);
+        assert_eq!(Policy::Down.apply(0), 1);
+    }
+
+    #[test]
+    fn test_power_of_two_policies_saturate_above_the_largest_power_of_two() {
+        let dimension = (1 << 31) + 1;
+
+        assert_eq!(Policy::Up.apply(dimension), 1 << 31);
+        assert_eq!(Policy::Up.apply(u32::MAX), 1 << 31);
+        assert_eq!(Policy::Down.apply(dimension), 1 << 31);
+        assert_eq!(Policy::Nearest.apply(dimension), 1 << 31);
+        assert_eq!(Policy::Nearest.apply(u32::MAX), 1 << 31);
+        assert_eq!(Policy::Up.apply(1 << 31), 1 << 31);
+    }
+
+    #[test]
+    fn test_nearest_filter_doubling_repeats_pixels() {
+        let src = vec![RgbaF32::new(0.0, 0.0, 0.0, 1.0), RgbaF32::new(1.0, 1.0, 1.0, 1.0)];
+        let result = resample(&src, 2, 1, 4, 1, Filter::Nearest);
+        let expected = vec![src[0], src[0], src[1], src[1]];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_premultiplied_filtering_ignores_color_of_transparent_pixels() {
+        let src = vec![RgbaF32::new(1.0, 0.0, 0.0, 1.0), RgbaF32::new(0.0, 1.0, 0.0, 0.0)];
+        let result = resample_premultiplied(&src, 2, 1, 1, 1, Filter::Box);
+
+        assert_eq!(result, vec![RgbaF32::new(1.0, 0.0, 0.0, 0.5)]);
+    }
+}
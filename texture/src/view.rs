@@ -0,0 +1,318 @@
+use crate::{Pixel, PixelFormat, TexImage2D};
+use std::mem;
+
+
+/// A borrowed view of a texture image. Unlike `TexImage2D`, a view can be
+/// constructed in a constant expression, so generated code can embed a
+/// texture image in a `static` without allocating when the program starts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TexImage2DRef<'a, P = crate::Rgba> {
+    pub width: u32,
+    pub height: u32,
+    pub data: &'a [P],
+}
+
+impl<'a, P: Pixel> TexImage2DRef<'a, P> {
+    pub const fn new(width: u32, height: u32, data: &'a [P]) -> TexImage2DRef<'a, P> {
+        TexImage2DRef {
+            width: width,
+            height: height,
+            data: data,
+        }
+    }
+
+    /// Borrow raw pixel data in the layout `TexImage2D::to_bytes` produces as
+    /// a view, without copying it.
+    ///
+    /// Formats with byte channels can always be borrowed. Formats with wider
+    /// channels can only be borrowed when the data is aligned for the channel
+    /// type and the target is little endian, since the data stores channels
+    /// in little endian byte order. Otherwise `None` is returned, and
+    /// `TexImage2D::from_bytes` has to copy the data instead.
+    ///
+    /// # Panics
+    /// Panics if the length of the data does not match the dimensions.
+    pub fn from_bytes(width: u32, height: u32, bytes: &'a [u8]) -> Option<TexImage2DRef<'a, P>> {
+        if mem::align_of::<P>() > 1 && cfg!(target_endian = "big") {
+            check_byte_count::<P>(width, height, bytes);
+            return None;
+        }
+
+        TexImage2DRef::from_native_bytes(width, height, bytes)
+    }
+
+    /// Borrow raw pixel data whose channels are stored in the byte order of
+    /// the target as a view, without copying it. This lets generated code
+    /// embed a texture image as a static byte array and use it in place,
+    /// after converting the array with `to_native_endian` at compile time.
+    /// Returns `None` if the data is not aligned for the channel type.
+    ///
+    /// # Panics
+    /// Panics if the length of the data does not match the dimensions.
+    pub fn from_native_bytes(width: u32, height: u32, bytes: &'a [u8]) -> Option<TexImage2DRef<'a, P>> {
+        check_byte_count::<P>(width, height, bytes);
+
+        bytemuck::try_cast_slice(bytes).ok().map(|data| TexImage2DRef::new(width, height, data))
+    }
+
+    /// The pixel format the texture image is stored in.
+    pub fn format(&self) -> PixelFormat {
+        P::FORMAT
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Copy the texture image into an owned texture image.
+    pub fn to_tex_image(&self) -> TexImage2D<P> {
+        TexImage2D::from_data(self.width, self.height, self.data.to_vec())
+    }
+
+    #[inline]
+    pub fn as_ptr(&self) -> *const P::Channel {
+        self.data.as_ptr() as *const P::Channel
+    }
+}
+
+fn check_byte_count<P: Pixel>(width: u32, height: u32, bytes: &[u8]) {
+    assert_eq!(
+        bytes.len(), (width as usize) * (height as usize) * P::FORMAT.bytes_per_pixel(),
+        "The raw pixel data does not match a {} x {} {} texture image.", width, height, P::FORMAT
+    );
+}
+
+/// Convert raw pixel data whose channels of `channel_size` bytes are stored
+/// in little endian byte order, as `TexImage2D::to_bytes` stores them, to the
+/// byte order of the target. This is a `const fn`, so generated code converts
+/// an embedded byte array while compiling, and the data is returned as it is
+/// on little endian targets.
+pub const fn to_native_endian<const N: usize>(bytes: [u8; N], channel_size: usize) -> [u8; N] {
+    if cfg!(target_endian = "little") || channel_size <= 1 {
+        return bytes;
+    }
+
+    let mut native = bytes;
+    let mut channel = 0;
+    while channel + channel_size <= N {
+        let mut i = 0;
+        while i < channel_size {
+            native[channel + i] = bytes[channel + channel_size - 1 - i];
+            i += 1;
+        }
+        channel += channel_size;
+    }
+
+    native
+}
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Borrow the texture image as a view.
+    pub fn as_view(&self) -> TexImage2DRef<'_, P> {
+        TexImage2DRef::new(self.width, self.height, &self.data)
+    }
+}
+
+impl<'a, P: Pixel> From<TexImage2DRef<'a, P>> for TexImage2D<P> {
+    fn from(view: TexImage2DRef<'a, P>) -> TexImage2D<P> {
+        view.to_tex_image()
+    }
+}
+
+/// A borrowed view of a rectangle of pixels in a texture image. The rows of
+/// the rectangle are `stride` pixels apart in the pixel data, so a sub-image
+/// can be uploaded straight from the pixel data of the texture image, for
+/// example with `glTexSubImage2D` after setting `GL_UNPACK_ROW_LENGTH` to the
+/// stride.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SubImage<'a, P = crate::Rgba> {
+    /// The column of the first pixel of the sub-image in the texture image.
+    pub x: u32,
+    /// The row of the first pixel of the sub-image in the texture image.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The number of pixels between the starts of consecutive rows, which is
+    /// the width of the texture image.
+    pub stride: u32,
+    /// The pixel data of the whole texture image.
+    pub data: &'a [P],
+}
+
+impl<'a, P: Pixel> SubImage<'a, P> {
+    /// The pixel format the sub-image is stored in.
+    pub fn format(&self) -> PixelFormat {
+        P::FORMAT
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        (self.width as usize) * (self.height as usize)
+    }
+
+    /// The index into the pixel data of the first pixel of the sub-image.
+    pub fn offset(&self) -> usize {
+        (self.y as usize) * (self.stride as usize) + (self.x as usize)
+    }
+
+    /// Get the pixel at the given coordinates relative to the sub-image.
+    ///
+    /// # Panics
+    /// Panics if the coordinates lie outside the sub-image.
+    pub fn get_pixel(&self, x: u32, y: u32) -> P {
+        assert!(
+            x < self.width && y < self.height,
+            "The pixel ({}, {}) lies outside a {} x {} sub-image.", x, y, self.width, self.height
+        );
+
+        self.data[self.offset() + (y as usize) * (self.stride as usize) + (x as usize)]
+    }
+
+    /// Iterate over the rows of the sub-image in the order they are stored.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [P]> + 'a {
+        let data = self.data;
+        let offset = self.offset();
+        let (width, stride) = (self.width as usize, self.stride as usize);
+
+        (0..self.height as usize).map(move |row| {
+            let start = offset + row * stride;
+            &data[start..(start + width)]
+        })
+    }
+
+    /// The pixel data from the first pixel to the last pixel of the
+    /// sub-image as bytes in native byte order, including the pixels of the
+    /// texture image between the rows of the sub-image. This is the data a
+    /// graphics API reads when told the row length is the stride.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        if self.pixel_count() == 0 {
+            return &[];
+        }
+
+        let start = self.offset();
+        let end = start + (self.height as usize - 1) * (self.stride as usize) + (self.width as usize);
+
+        bytemuck::cast_slice(&self.data[start..end])
+    }
+
+    /// Get a view of a rectangle of pixels inside the sub-image.
+    ///
+    /// # Panics
+    /// Panics if the rectangle does not lie inside the sub-image.
+    pub fn sub_image(&self, x: u32, y: u32, width: u32, height: u32) -> SubImage<'a, P> {
+        assert!(
+            x.checked_add(width).is_some_and(|right| right <= self.width) &&
+            y.checked_add(height).is_some_and(|top| top <= self.height),
+            "The rectangle at ({}, {}) of size {} x {} does not lie inside a {} x {} sub-image.",
+            x, y, width, height, self.width, self.height
+        );
+
+        SubImage {
+            x: self.x + x,
+            y: self.y + y,
+            width: width,
+            height: height,
+            stride: self.stride,
+            data: self.data,
+        }
+    }
+
+    /// Copy the sub-image into an owned texture image.
+    pub fn to_tex_image(&self) -> TexImage2D<P> {
+        let mut data = Vec::with_capacity(self.pixel_count());
+        for row in self.rows() {
+            data.extend_from_slice(row);
+        }
+
+        TexImage2D::from_data(self.width, self.height, data)
+    }
+}
+
+impl<P: Pixel> TexImage2D<P> {
+    /// Get a view of a rectangle of pixels in the texture image.
+    ///
+    /// # Panics
+    /// Panics if the rectangle does not lie inside the texture image.
+    pub fn sub_image(&self, x: u32, y: u32, width: u32, height: u32) -> SubImage<'_, P> {
+        let whole = SubImage {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+            stride: self.width,
+            data: &self.data,
+        };
+
+        whole.sub_image(x, y, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{R16, R8, Rg8};
+
+
+    static VIEW: TexImage2DRef<'static, R8> = TexImage2DRef::new(2, 1, &[R8::new(0x01), R8::new(0x02)]);
+
+    #[test]
+    fn test_static_view_converts_to_owned_image() {
+        let expected = TexImage2D::from_data(2, 1, vec![R8::new(0x01), R8::new(0x02)]);
+
+        assert_eq!(VIEW.to_tex_image(), expected);
+        assert_eq!(expected.as_view(), VIEW);
+    }
+
+    #[test]
+    fn test_view_borrows_raw_bytes_without_copying() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        let view = TexImage2DRef::<Rg8>::from_bytes(3, 1, &bytes).unwrap();
+
+        assert_eq!(view.data, &[Rg8::new(0x01, 0x02), Rg8::new(0x03, 0x04), Rg8::new(0x05, 0x06)]);
+        assert_eq!(view.as_ptr(), bytes.as_ptr());
+    }
+
+    #[test]
+    fn test_native_endian_bytes_are_borrowed_as_wide_channels() {
+        const BYTES: [u8; 4] = to_native_endian([0x02, 0x01, 0x04, 0x03], 2);
+        #[repr(C, align(2))]
+        struct Aligned([u8; 4]);
+        static ALIGNED: Aligned = Aligned(BYTES);
+        let view = TexImage2DRef::<R16>::from_native_bytes(2, 1, &ALIGNED.0).unwrap();
+
+        assert_eq!(view.data, &[R16::new(0x0102), R16::new(0x0304)]);
+        assert_eq!(to_native_endian([1, 2, 3], 1), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_view_of_wide_channels_checks_alignment() {
+        let words = [0x0102_u16.to_le(), 0xFFFE_u16.to_le(), 0];
+        let bytes: &[u8] = bytemuck::cast_slice(&words);
+
+        assert_eq!(TexImage2DRef::<R16>::from_bytes(2, 1, &bytes[1..5]), None);
+        if cfg!(target_endian = "little") {
+            let view = TexImage2DRef::<R16>::from_bytes(2, 1, &bytes[0..4]).unwrap();
+            assert_eq!(view.data, &[R16::new(0x0102), R16::new(0xFFFE)]);
+        }
+    }
+
+    #[test]
+    fn test_sub_image_rows_skip_the_stride() {
+        let data = (0..12).map(R8::new).collect();
+        let image = TexImage2D::from_data(4, 3, data);
+        let sub_image = image.sub_image(1, 1, 2, 2);
+        let rows: Vec<&[R8]> = sub_image.rows().collect();
+
+        assert_eq!(sub_image.offset(), 5);
+        assert_eq!(rows, vec![&[R8::new(5), R8::new(6)][..], &[R8::new(9), R8::new(10)][..]]);
+        assert_eq!(sub_image.get_pixel(1, 1), R8::new(10));
+        assert_eq!(sub_image.as_bytes(), &[5, 6, 7, 8, 9, 10]);
+        assert_eq!(sub_image.sub_image(1, 0, 1, 2).to_tex_image().data, vec![R8::new(6), R8::new(10)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sub_image_outside_the_texture_image_panics() {
+        let image: TexImage2D<R8> = TexImage2D::new(4, 3);
+        image.sub_image(3, 0, 2, 1);
+    }
+}
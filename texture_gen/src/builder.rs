@@ -0,0 +1,475 @@
+use crate::{
+    compressed_to_rust_code_with, dynamic_to_rust_code, to_png_bytes, CodegenOptions, DataEncoding, TokenStream,
+};
+use quote::quote;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use texture::{
+    CompressedFormat, CompressedTexImage2D, DynamicTexImage2D, ImageFormat, LoadOptions, PixelFormat, Quality,
+    TexImage2DError,
+};
+
+
+/// The errors that can occur when generating code for a batch of texture
+/// images. Every error names the file or pattern that caused it.
+#[derive(Debug)]
+pub enum BuildError {
+    /// A glob pattern is malformed, or a path matching it could not be read.
+    Pattern {
+        pattern: String,
+        reason: String,
+    },
+    /// No output directory was given and `OUT_DIR` is not set.
+    MissingOutDir,
+    /// A texture image failed to load.
+    Load(TexImage2DError),
+    /// A generated file could not be written.
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// Two texture images map to the same item name.
+    DuplicateName {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::Pattern { ref pattern, ref reason } => {
+                write!(f, "{}: Invalid texture file pattern: {}", pattern, reason)
+            }
+            BuildError::MissingOutDir => {
+                write!(f, "No output directory was given and OUT_DIR is not set.")
+            }
+            BuildError::Load(ref error) => write!(f, "{}", error),
+            BuildError::Io { ref path, ref source } => {
+                write!(f, "{}: Could not write generated code: {}", path.display(), source)
+            }
+            BuildError::DuplicateName { ref name, ref first, ref second } => {
+                write!(
+                    f, "{}: The item name `{}` is already used by {}",
+                    second.display(), name, first.display()
+                )
+            }
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            BuildError::Load(ref error) => Some(error),
+            BuildError::Io { ref source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// How the `Builder` embeds a texture image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Embedding {
+    /// Embed the pixel data in a function returning the texture image. The
+    /// texture image is ready to use without decoding, but the pixel data is
+    /// stored uncompressed in the binary.
+    #[default]
+    Eager,
+    /// Embed the compressed PNG bytes of the texture image in a `LazyTexture`
+    /// static that decodes the texture image the first time it is accessed.
+    /// This keeps large texture images small in the binary and fast to
+    /// compile, at the cost of decoding them at run time. The static is named
+    /// after the file in upper case.
+    Lazy,
+    /// Compress the texture image into blocks of a GPU format at build time,
+    /// and embed the blocks in a function returning a `CompressedTexImage2DRef`
+    /// with the base level only. The blocks are ready to upload without
+    /// decoding, and take up far less room in the binary than the pixel data.
+    /// The pixel format of the builder is ignored.
+    Compressed {
+        format: CompressedFormat,
+        quality: Quality,
+    },
+}
+
+/// Embeds every texture image matching a set of glob patterns from a build
+/// script. The builder writes one code fragment per texture image into the
+/// output directory, along with a module file holding one function per
+/// texture image, and tells cargo to rerun the build script when any of the
+/// texture images change.
+///
+/// ```ignore
+/// // build.rs
+/// texture_gen::Builder::new().glob("assets/*.png").build().unwrap();
+///
+/// // lib.rs
+/// mod textures {
+///     include!(concat!(env!("OUT_DIR"), "/textures.rs"));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Builder {
+    patterns: Vec<(String, Option<Embedding>)>,
+    embedding: Embedding,
+    out_dir: Option<PathBuf>,
+    module_name: String,
+    load_options: LoadOptions,
+    format: PixelFormat,
+    codegen_options: CodegenOptions,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder {
+            patterns: vec![],
+            embedding: Embedding::Eager,
+            out_dir: None,
+            module_name: String::from("textures"),
+            load_options: LoadOptions::new(),
+            format: PixelFormat::Rgba8,
+            codegen_options: CodegenOptions::new(),
+        }
+    }
+
+    /// Add a glob pattern matching texture image files, such as `assets/*.png`.
+    pub fn glob(mut self, pattern: &str) -> Builder {
+        self.patterns.push((String::from(pattern), None));
+        self
+    }
+
+    /// Add a glob pattern matching texture image files that are embedded
+    /// the given way, whatever the default embedding is.
+    pub fn glob_with(mut self, pattern: &str, embedding: Embedding) -> Builder {
+        self.patterns.push((String::from(pattern), Some(embedding)));
+        self
+    }
+
+    /// How texture images are embedded unless their pattern says otherwise.
+    /// Defaults to embedding the pixel data eagerly.
+    pub fn embedding(mut self, embedding: Embedding) -> Builder {
+        self.embedding = embedding;
+        self
+    }
+
+    /// The directory the generated code is written to. Defaults to the
+    /// `OUT_DIR` directory cargo gives build scripts.
+    pub fn out_dir<P: AsRef<Path>>(mut self, out_dir: P) -> Builder {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// The name of the generated module file, without the `.rs` extension.
+    /// Defaults to `textures`.
+    pub fn module_name(mut self, module_name: &str) -> Builder {
+        self.module_name = String::from(module_name);
+        self
+    }
+
+    /// The load options used to load every texture image.
+    pub fn load_options(mut self, load_options: LoadOptions) -> Builder {
+        self.load_options = load_options;
+        self
+    }
+
+    /// The pixel format every texture image is embedded in. Defaults to RGBA8.
+    pub fn format(mut self, format: PixelFormat) -> Builder {
+        self.format = format;
+        self
+    }
+
+    /// The path the generated code names the texture types by, such as
+    /// `texture` or `::teximage2d`, the same way `CodegenOptions::crate_path`
+    /// sets it. Defaults to `teximage2d`.
+    ///
+    /// # Panics
+    /// Panics if the path is not a valid Rust path.
+    pub fn crate_path(mut self, crate_path: &str) -> Builder {
+        self.codegen_options = self.codegen_options.crate_path(crate_path);
+        self
+    }
+
+    /// Generate the code for every matching texture image, and return the
+    /// path of the generated module file.
+    pub fn build(&self) -> Result<PathBuf, BuildError> {
+        let out_dir = match self.out_dir {
+            Some(ref out_dir) => out_dir.clone(),
+            None => env::var_os("OUT_DIR").map(PathBuf::from).ok_or(BuildError::MissingOutDir)?,
+        };
+
+        let mut textures: BTreeMap<String, (PathBuf, Embedding)> = BTreeMap::new();
+        for &(ref pattern, embedding) in self.patterns.iter() {
+            let embedding = embedding.unwrap_or(self.embedding);
+            println!("cargo:rerun-if-changed={}", glob_base_dir(pattern).display());
+            for file_path in find_files(pattern)? {
+                let name = sanitize_identifier(&file_path);
+                if let Some((first, _)) = textures.get(&name) {
+                    if *first == file_path {
+                        continue;
+                    }
+                    return Err(BuildError::DuplicateName { name: name, first: first.clone(), second: file_path });
+                }
+                textures.insert(name, (file_path, embedding));
+            }
+        }
+
+        let crate_path = self.codegen_options.crate_path_tokens();
+        let pixel_type = crate::pixel_type_ident(self.format);
+        let mut items = TokenStream::new();
+        for (name, &(ref file_path, embedding)) in textures.iter() {
+            println!("cargo:rerun-if-changed={}", file_path.display());
+            let result = texture::load_file_with(file_path, &self.load_options).map_err(BuildError::Load)?;
+            let doc = format!("The texture image embedded from `{}`.", file_path.display());
+            match embedding {
+                Embedding::Eager => {
+                    let fragment_name = format!("{}.in", name);
+                    let fragment = dynamic_to_rust_code(
+                        &result.image, self.format, DataEncoding::ByteArray, &self.codegen_options
+                    );
+                    write_file(&out_dir.join(&fragment_name), fragment.as_bytes())?;
+
+                    let name = crate::ident(name);
+                    items.extend(quote! {
+                        #[doc = #doc]
+                        pub fn #name() -> #crate_path::TexImage2DRef<'static, #crate_path::#pixel_type> {
+                            include!(#fragment_name)
+                        }
+                    });
+                }
+                Embedding::Compressed { format, quality } => {
+                    let fragment_name = format!("{}.compressed.in", name);
+                    let compressed = CompressedTexImage2D::from_image(&result.image.to_rgba8(), format, quality);
+                    let fragment = compressed_to_rust_code_with(&compressed, &self.codegen_options);
+                    write_file(&out_dir.join(&fragment_name), fragment.as_bytes())?;
+
+                    let name = crate::ident(name);
+                    items.extend(quote! {
+                        #[doc = #doc]
+                        pub fn #name() -> #crate_path::CompressedTexImage2DRef<'static> {
+                            include!(#fragment_name)
+                        }
+                    });
+                }
+                Embedding::Lazy => {
+                    let png_name = format!("{}.png", name);
+                    let bytes = self.png_bytes(file_path, &result.image)?;
+                    write_file(&out_dir.join(&png_name), &bytes)?;
+
+                    let name = crate::ident(&name.to_ascii_uppercase());
+                    let item = crate::generate_lazy_static_code(
+                        self.format, &crate_path, &quote!(pub), &name, &png_name
+                    );
+                    items.extend(quote! {
+                        #[doc = #doc]
+                        #item
+                    });
+                }
+            }
+        }
+        let module = format!("// This file is generated by texture_gen::Builder. Do not edit it by hand.\n{}\n", items);
+
+        let module_path = out_dir.join(format!("{}.rs", self.module_name));
+        write_file(&module_path, module.as_bytes())?;
+
+        Ok(module_path)
+    }
+
+    /// The PNG bytes a lazily embedded texture image is decoded from. A PNG
+    /// file loaded with the default load options decodes to the same texture
+    /// image as the loaded texture image, so it is embedded as is. Any other
+    /// file is encoded again from the loaded texture image.
+    fn png_bytes(&self, file_path: &Path, image: &DynamicTexImage2D) -> Result<Vec<u8>, BuildError> {
+        if ImageFormat::from_path(file_path) == Some(ImageFormat::Png) && self.load_options == LoadOptions::new() {
+            fs::read(file_path).map_err(|e| {
+                BuildError::Load(TexImage2DError::Io { path: Some(file_path.to_path_buf()), source: e })
+            })
+        } else {
+            Ok(to_png_bytes(&image.to_rgba8()))
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), BuildError> {
+    fs::write(path, contents).map_err(|e| BuildError::Io { path: path.to_path_buf(), source: e })
+}
+
+/// The files matching a glob pattern, in sorted order.
+fn find_files(pattern: &str) -> Result<Vec<PathBuf>, BuildError> {
+    let paths = glob::glob(pattern).map_err(|e| {
+        BuildError::Pattern { pattern: String::from(pattern), reason: format!("{}", e) }
+    })?;
+
+    let mut files = vec![];
+    for path in paths {
+        let path = path.map_err(|e| {
+            BuildError::Pattern { pattern: String::from(pattern), reason: format!("{}", e) }
+        })?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// The directory a glob pattern searches, which is the part of the pattern
+/// before the first path component with a wildcard in it. Cargo reruns the
+/// build script when files are added to or removed from this directory.
+fn glob_base_dir(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        let text = component.as_os_str().to_string_lossy();
+        if text.contains(['*', '?', '[']) {
+            break;
+        }
+        base.push(component);
+    }
+
+    if base == Path::new(pattern) {
+        // The pattern has no wildcards, so it names a single file.
+        base
+    } else if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    }
+}
+
+const KEYWORDS: [&str; 51] = [
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if",
+    "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "async",
+    "await", "dyn", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "typeof",
+    "unsized", "virtual", "yield", "try",
+];
+
+/// Convert the file name of a texture image into a Rust identifier. Letters
+/// are lowercased, every other character that cannot appear in an identifier
+/// becomes an underscore, and names that would start with a digit or collide
+/// with a keyword get an extra underscore.
+fn sanitize_identifier(file_path: &Path) -> String {
+    let stem = file_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut name: String = stem.chars().map(|c| {
+        if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }
+    }).collect();
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if name == "_" || KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_sanitize_identifier() {
+        assert_eq!(sanitize_identifier(Path::new("assets/Stone Wall-01.png")), "stone_wall_01");
+        assert_eq!(sanitize_identifier(Path::new("assets/8bit.png")), "_8bit");
+        assert_eq!(sanitize_identifier(Path::new("assets/type.png")), "type_");
+        assert_eq!(sanitize_identifier(Path::new("assets/_.png")), "__");
+    }
+
+    #[test]
+    fn test_glob_base_dir() {
+        assert_eq!(glob_base_dir("assets/*.png"), PathBuf::from("assets"));
+        assert_eq!(glob_base_dir("assets/**/textures/*.png"), PathBuf::from("assets"));
+        assert_eq!(glob_base_dir("*.png"), PathBuf::from("."));
+        assert_eq!(glob_base_dir("assets/sample.png"), PathBuf::from("assets/sample.png"));
+    }
+
+    #[test]
+    fn test_build_names_the_file_that_failed_to_load() {
+        let out_dir = env::temp_dir().join("texture_gen_builder_load_error");
+        fs::create_dir_all(&out_dir).unwrap();
+        let bad_file = out_dir.join("broken.png");
+        fs::write(&bad_file, b"this is not an image").unwrap();
+        let pattern = format!("{}/*.png", out_dir.display());
+        let error = Builder::new().glob(&pattern).out_dir(&out_dir).build().unwrap_err();
+
+        assert!(format!("{}", error).starts_with(&format!("{}: ", bad_file.display())));
+    }
+
+    #[test]
+    fn test_build_embeds_lazy_textures_as_png_files() {
+        let asset_dir = env::temp_dir().join("texture_gen_builder_lazy_assets");
+        let out_dir = env::temp_dir().join("texture_gen_builder_lazy");
+        fs::create_dir_all(&asset_dir).unwrap();
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::copy("../assets/sample.png", asset_dir.join("stone-wall.png")).unwrap();
+        let pattern = format!("{}/*.png", asset_dir.display());
+        let module_path = Builder::new().glob_with(&pattern, Embedding::Lazy).out_dir(&out_dir).build().unwrap();
+        let module = fs::read_to_string(module_path).unwrap();
+
+        assert!(module.contains("pub static STONE_WALL : teximage2d :: LazyTexture"));
+        assert_eq!(fs::read(out_dir.join("stone_wall.png")).unwrap(), fs::read("../assets/sample.png").unwrap());
+    }
+
+    #[test]
+    fn test_build_embeds_compressed_textures_as_blocks() {
+        let asset_dir = env::temp_dir().join("texture_gen_builder_compressed_assets");
+        let out_dir = env::temp_dir().join("texture_gen_builder_compressed");
+        fs::create_dir_all(&asset_dir).unwrap();
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::copy("../assets/sample.png", asset_dir.join("stone-wall.png")).unwrap();
+        let pattern = format!("{}/*.png", asset_dir.display());
+        let embedding = Embedding::Compressed { format: CompressedFormat::Bc1, quality: Quality::Fast };
+        let module_path = Builder::new().glob_with(&pattern, embedding).out_dir(&out_dir).build().unwrap();
+        let module = fs::read_to_string(module_path).unwrap();
+        let fragment = fs::read_to_string(out_dir.join("stone_wall.compressed.in")).unwrap();
+
+        assert!(module.contains("pub fn stone_wall () -> teximage2d :: CompressedTexImage2DRef < 'static >"));
+        assert!(fragment.contains("CompressedFormat :: Bc1"));
+        assert!(fragment.contains("static LEVEL_0 : [u8 ; 128]"));
+    }
+
+    #[test]
+    fn test_build_names_the_texture_types_through_the_crate_path() {
+        let asset_dir = env::temp_dir().join("texture_gen_builder_crate_path_assets");
+        let out_dir = env::temp_dir().join("texture_gen_builder_crate_path");
+        fs::create_dir_all(&asset_dir).unwrap();
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::copy("../assets/sample.png", asset_dir.join("stone-wall.png")).unwrap();
+        let pattern = format!("{}/*.png", asset_dir.display());
+        let module_path = Builder::new().glob(&pattern).crate_path("::texture").out_dir(&out_dir).build().unwrap();
+        let module = fs::read_to_string(module_path).unwrap();
+        let fragment = fs::read_to_string(out_dir.join("stone_wall.in")).unwrap();
+
+        let signature = "pub fn stone_wall () -> :: texture :: TexImage2DRef < 'static , :: texture :: Rgba >";
+
+        assert!(module.contains(signature));
+        assert!(fragment.contains("use :: texture :: { Rgba , TexImage2DRef }"));
+        assert!(!module.contains("teximage2d") && !fragment.contains("teximage2d"));
+    }
+
+    #[test]
+    fn test_build_reports_duplicate_names() {
+        let out_dir = env::temp_dir().join("texture_gen_builder_duplicate_names");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::copy("../assets/sample.png", out_dir.join("stone-wall.png")).unwrap();
+        fs::copy("../assets/sample.png", out_dir.join("stone_wall.png")).unwrap();
+        let pattern = format!("{}/*.png", out_dir.display());
+        let error = Builder::new().glob(&pattern).out_dir(&out_dir).build().unwrap_err();
+
+        assert!(format!("{}", error).contains("The item name `stone_wall` is already used by"));
+    }
+}
@@ -1,285 +1,1035 @@
+extern crate glob;
+extern crate proc_macro2;
+extern crate quote;
 extern crate texture;
 
-use texture::TexImage2D;
+mod builder;
+mod options;
+
+pub use builder::{BuildError, Builder, Embedding};
+pub use options::{CodegenOptions, Visibility};
+pub use proc_macro2::TokenStream;
+
+use proc_macro2::{Ident, Literal, Span};
+use quote::quote;
+use texture::{
+    AtlasRegion, Channel, CompressedFormat, CompressedTexImage2D, DynamicTexImage2D, ImageFormat, L8, La8, MipChain,
+    Pixel, PixelFormat, R16, R8, RF32, Rg8, Rgb8, Rgba, Rgba16, RgbaF32, TexImage2D, TexImage2DArray, TexImageCube,
+    TextureAtlas,
+};
+use std::mem;
 
 
 /// Convert a texture image to a block of Rust code that can be
-/// included into a computer program at build time.
-pub fn to_rust_code(tex: &TexImage2D) -> String {
-    let ir = generate_code(tex);
-    synthesize_code(&ir)
-}
-
-
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum Token {
-    SymUse,
-    SymImportTexImage2D,
-    SymLet,
-    SymHeight,
-    SymWidth,
-    SymDepth,
-    SymData,
-    SymTypeU32,
-    SymTypeRgba,
-    SymTypeTexImage2D,
-    SymTypeVec,
-    SymRgbaNew,
-    SymTexImage2DNew,
-    SymMacroVec,
-    Equals,
-    Colon,
-    DoubleColon,
-    Semicolon,
-    LBracket,
-    RBracket,
-    LCurlyBrace,
-    RCurlyBrace,
-    GreaterThan,
-    LessThan,
-    Comma,
-    LParen,
-    RParen,
-    U8(u8),
-    U32(u32),
-    Newline,
-    Whitespace(usize),
-}
-
-#[derive(Clone, Debug, PartialEq)]
-struct TexImage2DIR { 
-    data: Vec<Token> 
-}
-
-impl TexImage2DIR {
-    fn new(data: Vec<Token>) -> TexImage2DIR {
-        TexImage2DIR { data: data }
-    }
-
-    fn push(&mut self, item: Token) {
-        self.data.push(item);
-    }
-}
-
-/// Generate an import statement.
-fn generate_imports(ir: &mut TexImage2DIR, indent: usize) {
-    use Token::*;
-    
-    ir.push(Whitespace(indent));
-    ir.push(SymUse);
-    ir.push(Whitespace(1));
-    ir.push(SymImportTexImage2D);
-    ir.push(DoubleColon);
-    ir.push(LCurlyBrace);
-    ir.push(SymTypeRgba);
-    ir.push(Comma);
-    ir.push(Whitespace(1));
-    ir.push(SymTypeTexImage2D);
-    ir.push(RCurlyBrace);
-    ir.push(Semicolon);
-}
-
-/// Generate the data set code for the texture image.
-fn generate_data_code(ir: &mut TexImage2DIR, tex: &TexImage2D, indent: usize) {
-    use Token::*;
-
-    ir.push(Whitespace(indent));
-    ir.push(SymLet);
-    ir.push(Whitespace(1));
-    ir.push(SymData);
-    ir.push(Colon);
-    ir.push(Whitespace(1));
-    ir.push(SymTypeVec);
-    ir.push(LessThan); ir.push(SymTypeRgba); ir.push(GreaterThan);
-    ir.push(Whitespace(1));
-    ir.push(Equals);
-    ir.push(Whitespace(1));
-    ir.push(SymMacroVec);
-    ir.push(LBracket);
-    ir.push(Newline);
-    ir.push(Whitespace(indent));
-    ir.push(Whitespace(indent));
-
-    for pixel in tex.data.iter() {
-        ir.push(SymTypeRgba);
-        ir.push(DoubleColon);
-        ir.push(SymRgbaNew);
-        ir.push(LParen);
-        ir.push(U8(pixel.r));
-        ir.push(Comma);
-        ir.push(Whitespace(1));
-        ir.push(U8(pixel.g));
-        ir.push(Comma);
-        ir.push(Whitespace(1));
-        ir.push(U8(pixel.b));
-        ir.push(Comma);
-        ir.push(Whitespace(1));
-        ir.push(U8(pixel.a));
-        ir.push(RParen);
-        ir.push(Comma);
-        ir.push(Whitespace(1));
-    }
-
-    ir.push(Newline);
-    ir.push(Whitespace(indent));
-    ir.push(RBracket);
-    ir.push(Semicolon);
-}
-
-fn generate_height_code(ir: &mut TexImage2DIR, tex: &TexImage2D, indent: usize) {
-    use Token::*;
-    
-    ir.push(Whitespace(indent));
-    ir.push(SymLet);
-    ir.push(Whitespace(1));
-    ir.push(SymHeight);
-    ir.push(Colon);
-    ir.push(Whitespace(1));
-    ir.push(SymTypeU32);
-    ir.push(Whitespace(1));
-    ir.push(Equals);
-    ir.push(Whitespace(1));
-    ir.push(U32(tex.height));
-    ir.push(Semicolon);
-}
-
-fn generate_width_code(ir: &mut TexImage2DIR, tex: &TexImage2D, indent: usize) {
-    use Token::*;
-    
-    ir.push(Whitespace(indent));
-    ir.push(SymLet);
-    ir.push(Whitespace(1));
-    ir.push(SymWidth);
-    ir.push(Colon);
-    ir.push(Whitespace(1));
-    ir.push(SymTypeU32);
-    ir.push(Whitespace(1));
-    ir.push(Equals);
-    ir.push(Whitespace(1));
-    ir.push(U32(tex.width));
-    ir.push(Semicolon);
-}
-
-fn generate_depth_code(ir: &mut TexImage2DIR, tex: &TexImage2D, indent: usize) {
-    use Token::*;
- 
-    ir.push(Whitespace(indent));
-    ir.push(SymLet);
-    ir.push(Whitespace(1));
-    ir.push(SymDepth);
-    ir.push(Colon);
-    ir.push(Whitespace(1));
-    ir.push(SymTypeU32);
-    ir.push(Whitespace(1));
-    ir.push(Equals);
-    ir.push(Whitespace(1));
-    ir.push(U32(tex.depth));
-    ir.push(Semicolon);
-}
-
-/// Generate the type constructor invocation code.
-fn generate_type_constructor_invocation(ir: &mut TexImage2DIR, indent: usize) {
-    use Token::*;
-
-    ir.push(Whitespace(indent));
-    ir.push(SymTypeTexImage2D);
-    ir.push(DoubleColon);
-    ir.push(SymTexImage2DNew);
-    ir.push(LParen);
-    ir.push(SymWidth); ir.push(Comma); ir.push(Whitespace(1)); 
-    ir.push(SymHeight); ir.push(Comma); ir.push(Whitespace(1)); 
-    ir.push(SymData);
-    ir.push(RParen);
+/// included into a computer program at build time. The generated code
+/// stores the texture in the same pixel format as the input texture.
+pub fn to_rust_code<P: Pixel>(tex: &TexImage2D<P>) -> String {
+    to_token_stream(tex).to_string()
+}
+
+/// Convert a texture image to the tokens of a Rust expression block
+/// constructing the texture image. The tokens can be spliced into the output
+/// of a procedural macro or quoted into a larger generated module. The
+/// string form of the tokens is the code `to_rust_code` returns.
+pub fn to_token_stream<P: Pixel>(tex: &TexImage2D<P>) -> TokenStream {
+    to_token_stream_with(tex, &CodegenOptions::new())
+}
+
+
+/// Convert a texture image to Rust code shaped by the given options, such
+/// as the path the texture types are named by, or a named function or
+/// `static` item to embed the texture image in.
+pub fn to_rust_code_with<P: Pixel>(tex: &TexImage2D<P>, options: &CodegenOptions) -> String {
+    to_token_stream_with(tex, options).to_string()
+}
+
+/// The tokens of the code `to_rust_code_with` returns.
+pub fn to_token_stream_with<P: Pixel>(tex: &TexImage2D<P>, options: &CodegenOptions) -> TokenStream {
+    generate_code_with(tex, DataEncoding::PixelLiterals, options)
+}
+
+
+/// How the pixel data of a texture image is embedded in generated code.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DataEncoding<'a> {
+    /// One pixel constructor call per pixel. The generated code is easy to
+    /// read, but large texture images take a long time to compile.
+    #[default]
+    PixelLiterals,
+    /// A static byte array initialized from a byte string literal holding
+    /// the raw pixel data.
+    ByteArray,
+    /// A static byte array read by `include_bytes!` from a raw pixel data
+    /// file. The path is relative to the generated code fragment, and the
+    /// file holds the data returned by `to_raw_bytes`. This compiles the
+    /// fastest, since the compiler never parses the pixel data.
+    IncludeBytes(&'a str),
+}
+
+
+/// Convert a texture image to a block of Rust code that can be included
+/// into a computer program at build time, embedding the pixel data with the
+/// given encoding. The byte array encodings evaluate to a `TexImage2DRef`
+/// borrowing the static pixel data with `TexImage2DRef::from_native_bytes`,
+/// so the texture image is neither copied nor parsed at run time, on big
+/// endian targets as well. Call
+/// `to_tex_image` on the view for an owned texture image.
+pub fn to_rust_code_with_encoding<P: Pixel>(tex: &TexImage2D<P>, encoding: DataEncoding) -> String {
+    to_token_stream_with_encoding(tex, encoding).to_string()
+}
+
+/// The tokens of the code `to_rust_code_with_encoding` returns.
+pub fn to_token_stream_with_encoding<P: Pixel>(tex: &TexImage2D<P>, encoding: DataEncoding) -> TokenStream {
+    to_token_stream_with_encoding_and_options(tex, encoding, &CodegenOptions::new())
+}
+
+/// Convert a texture image to Rust code embedding the pixel data with the
+/// given encoding, shaped by the given options. Naming an item with the
+/// byte array encodings generates a function returning the borrowed view,
+/// whether or not the options wrap the item in a function, since the view
+/// cannot be constructed in a `static` initializer.
+pub fn to_rust_code_with_encoding_and_options<P: Pixel>(
+    tex: &TexImage2D<P>,
+    encoding: DataEncoding,
+    options: &CodegenOptions) -> String
+{
+    to_token_stream_with_encoding_and_options(tex, encoding, options).to_string()
+}
+
+/// The tokens of the code `to_rust_code_with_encoding_and_options` returns.
+pub fn to_token_stream_with_encoding_and_options<P: Pixel>(
+    tex: &TexImage2D<P>,
+    encoding: DataEncoding,
+    options: &CodegenOptions) -> TokenStream
+{
+    generate_code_with(tex, encoding, options)
+}
+
+/// Convert a loaded texture image to Rust code constructing it in the given
+/// pixel format, whatever format it was loaded in. This is the code the
+/// `include_texture!` macro expands to and the `Builder` embeds.
+pub fn dynamic_to_rust_code(
+    image: &DynamicTexImage2D,
+    format: PixelFormat,
+    encoding: DataEncoding,
+    options: &CodegenOptions) -> String
+{
+    dynamic_to_token_stream(image, format, encoding, options).to_string()
+}
+
+/// The tokens of the code `dynamic_to_rust_code` returns.
+pub fn dynamic_to_token_stream(
+    image: &DynamicTexImage2D,
+    format: PixelFormat,
+    encoding: DataEncoding,
+    options: &CodegenOptions) -> TokenStream
+{
+    match format {
+        PixelFormat::R8 => generate_code_with(&image.convert::<R8>(), encoding, options),
+        PixelFormat::Rg8 => generate_code_with(&image.convert::<Rg8>(), encoding, options),
+        PixelFormat::L8 => generate_code_with(&image.convert::<L8>(), encoding, options),
+        PixelFormat::La8 => generate_code_with(&image.convert::<La8>(), encoding, options),
+        PixelFormat::Rgb8 => generate_code_with(&image.convert::<Rgb8>(), encoding, options),
+        PixelFormat::Rgba8 => generate_code_with(&image.convert::<Rgba>(), encoding, options),
+        PixelFormat::R16 => generate_code_with(&image.convert::<R16>(), encoding, options),
+        PixelFormat::Rgba16 => generate_code_with(&image.convert::<Rgba16>(), encoding, options),
+        PixelFormat::RF32 => generate_code_with(&image.convert::<RF32>(), encoding, options),
+        PixelFormat::RgbaF32 => generate_code_with(&image.convert::<RgbaF32>(), encoding, options),
+    }
+}
+
+
+/// The raw pixel data of a texture image, to be written to the sidecar file
+/// read by code generated with `DataEncoding::IncludeBytes`.
+pub fn to_raw_bytes<P: Pixel>(tex: &TexImage2D<P>) -> Vec<u8> {
+    tex.to_bytes()
+}
+
+
+/// Convert a texture image to a Rust `static` item named `name` that can be
+/// included into a computer program at build time. The item is a borrowed
+/// view of the texture image, so the embedded texture image is never copied
+/// onto the heap.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_static_rust_code<P: Pixel>(tex: &TexImage2D<P>, name: &str) -> String {
+    to_static_token_stream(tex, name).to_string()
+}
+
+/// The tokens of the code `to_static_rust_code` returns.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_static_token_stream<P: Pixel>(tex: &TexImage2D<P>, name: &str) -> TokenStream {
+    to_static_token_stream_with(tex, name, &CodegenOptions::new())
+}
+
+/// Convert a texture image to a Rust `static` item named `name`, shaped by
+/// the given options. This is the same as `to_rust_code_with` with the item
+/// named `name` and function wrapping disabled.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_static_rust_code_with<P: Pixel>(tex: &TexImage2D<P>, name: &str, options: &CodegenOptions) -> String {
+    to_static_token_stream_with(tex, name, options).to_string()
+}
+
+/// The tokens of the code `to_static_rust_code_with` returns.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_static_token_stream_with<P: Pixel>(
+    tex: &TexImage2D<P>,
+    name: &str,
+    options: &CodegenOptions) -> TokenStream
+{
+    let options = options.clone().item_name(name).wrap_in_fn(false);
+
+    generate_code_with(tex, DataEncoding::PixelLiterals, &options)
+}
+
+
+/// The bytes of a texture image encoded as a PNG file, to be written to the
+/// sidecar file read by code generated with `to_lazy_static_rust_code`.
+///
+/// # Panics
+/// Panics if the texture image has no pixels.
+pub fn to_png_bytes<P: Pixel>(tex: &TexImage2D<P>) -> Vec<u8> {
+    let mut bytes = vec![];
+    tex.write_to(&mut bytes, ImageFormat::Png).unwrap_or_else(|error| {
+        panic!("Failed to encode the texture image as a PNG file: {}", error)
+    });
+
+    bytes
+}
+
+/// Convert an image file to a Rust `static` item named `name` holding a
+/// `LazyTexture` of the given pixel format. The item reads the image file
+/// with `include_bytes!`, and decodes it the first time it is accessed, so
+/// the pixel data is neither parsed by the compiler nor stored uncompressed
+/// in the binary. The path is relative to the generated code fragment, and
+/// the file is an image file `load_from_memory` can decode, such as the
+/// original PNG file or the data returned by `to_png_bytes`.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_lazy_static_rust_code(format: PixelFormat, name: &str, path: &str) -> String {
+    to_lazy_static_token_stream(format, name, path).to_string()
+}
+
+/// The tokens of the code `to_lazy_static_rust_code` returns.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_lazy_static_token_stream(format: PixelFormat, name: &str, path: &str) -> TokenStream {
+    to_lazy_static_token_stream_with(format, name, path, &CodegenOptions::new())
+}
+
+/// Convert an image file to a Rust `static` item named `name` holding a
+/// `LazyTexture`, shaped by the given options. The crate path, visibility
+/// and attributes of the options apply to the item.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_lazy_static_rust_code_with(format: PixelFormat, name: &str, path: &str, options: &CodegenOptions) -> String {
+    to_lazy_static_token_stream_with(format, name, path, options).to_string()
+}
+
+/// The tokens of the code `to_lazy_static_rust_code_with` returns.
+///
+/// # Panics
+/// Panics if `name` is not a valid Rust identifier.
+pub fn to_lazy_static_token_stream_with(
+    format: PixelFormat,
+    name: &str,
+    path: &str,
+    options: &CodegenOptions) -> TokenStream
+{
+    let crate_path = options.crate_path_tokens();
+    let visibility = options.visibility.to_token_stream();
+    let attributes = options.attributes();
+    let item = generate_lazy_static_code(format, &crate_path, &visibility, &ident(name), path);
+
+    quote! {
+        #attributes
+        #item
+    }
+}
+
+
+/// Convert a mipmap chain to a block of Rust code that can be included
+/// into a computer program at build time. Every level of the chain is
+/// embedded in the generated code.
+pub fn mip_chain_to_rust_code<P: Pixel>(chain: &MipChain<P>) -> String {
+    mip_chain_to_token_stream(chain).to_string()
+}
+
+/// The tokens of the code `mip_chain_to_rust_code` returns.
+pub fn mip_chain_to_token_stream<P: Pixel>(chain: &MipChain<P>) -> TokenStream {
+    mip_chain_to_token_stream_with(chain, &CodegenOptions::new())
+}
+
+/// Convert a mipmap chain to a block of Rust code naming the texture types
+/// through the crate path of the given options.
+pub fn mip_chain_to_rust_code_with<P: Pixel>(chain: &MipChain<P>, options: &CodegenOptions) -> String {
+    mip_chain_to_token_stream_with(chain, options).to_string()
+}
+
+/// The tokens of the code `mip_chain_to_rust_code_with` returns.
+pub fn mip_chain_to_token_stream_with<P: Pixel>(chain: &MipChain<P>, options: &CodegenOptions) -> TokenStream {
+    generate_mip_chain_code(chain, &options.crate_path_tokens())
+}
+
+
+/// Convert a compressed texture image to a block of Rust code that can be
+/// included into a computer program at build time. The blocks of every
+/// mipmap level are embedded as a static byte array, and the block evaluates
+/// to a `CompressedTexImage2DRef<'static>` borrowing them, ready to be
+/// uploaded to the GPU without decompressing or copying them.
+pub fn compressed_to_rust_code(tex: &CompressedTexImage2D) -> String {
+    compressed_to_token_stream(tex).to_string()
+}
+
+/// The tokens of the code `compressed_to_rust_code` returns.
+pub fn compressed_to_token_stream(tex: &CompressedTexImage2D) -> TokenStream {
+    compressed_to_token_stream_with(tex, &CodegenOptions::new())
+}
+
+/// Convert a compressed texture image to a block of Rust code naming the
+/// texture types through the crate path of the given options.
+pub fn compressed_to_rust_code_with(tex: &CompressedTexImage2D, options: &CodegenOptions) -> String {
+    compressed_to_token_stream_with(tex, options).to_string()
+}
+
+/// The tokens of the code `compressed_to_rust_code_with` returns.
+pub fn compressed_to_token_stream_with(tex: &CompressedTexImage2D, options: &CodegenOptions) -> TokenStream {
+    generate_compressed_block(tex, &options.crate_path_tokens())
+}
+
+
+/// Convert a cube map to a block of Rust code that can be included into a
+/// computer program at build time. Every face of the cube map is embedded
+/// in the generated code.
+pub fn cube_to_rust_code<P: Pixel>(cube: &TexImageCube<P>) -> String {
+    cube_to_token_stream(cube).to_string()
+}
+
+/// The tokens of the code `cube_to_rust_code` returns.
+pub fn cube_to_token_stream<P: Pixel>(cube: &TexImageCube<P>) -> TokenStream {
+    cube_to_token_stream_with(cube, &CodegenOptions::new())
+}
+
+/// Convert a cube map to a block of Rust code naming the texture types
+/// through the crate path of the given options.
+pub fn cube_to_rust_code_with<P: Pixel>(cube: &TexImageCube<P>, options: &CodegenOptions) -> String {
+    cube_to_token_stream_with(cube, options).to_string()
+}
+
+/// The tokens of the code `cube_to_rust_code_with` returns.
+pub fn cube_to_token_stream_with<P: Pixel>(cube: &TexImageCube<P>, options: &CodegenOptions) -> TokenStream {
+    generate_image_collection_code("TexImageCube", "from_faces", false, cube.iter(), &options.crate_path_tokens())
+}
+
+
+/// Convert a texture array to a block of Rust code that can be included
+/// into a computer program at build time. Every layer of the texture array
+/// is embedded in the generated code.
+pub fn array_to_rust_code<P: Pixel>(array: &TexImage2DArray<P>) -> String {
+    array_to_token_stream(array).to_string()
+}
+
+/// The tokens of the code `array_to_rust_code` returns.
+pub fn array_to_token_stream<P: Pixel>(array: &TexImage2DArray<P>) -> TokenStream {
+    array_to_token_stream_with(array, &CodegenOptions::new())
+}
+
+/// Convert a texture array to a block of Rust code naming the texture types
+/// through the crate path of the given options.
+pub fn array_to_rust_code_with<P: Pixel>(array: &TexImage2DArray<P>, options: &CodegenOptions) -> String {
+    array_to_token_stream_with(array, options).to_string()
+}
+
+/// The tokens of the code `array_to_rust_code_with` returns.
+pub fn array_to_token_stream_with<P: Pixel>(array: &TexImage2DArray<P>, options: &CodegenOptions) -> TokenStream {
+    let crate_path = options.crate_path_tokens();
+
+    generate_image_collection_code("TexImage2DArray", "from_layers", true, array.iter(), &crate_path)
+}
+
+
+/// Convert the regions of a texture atlas to a block of Rust code for a
+/// lookup table of named regions that can be included into a computer
+/// program at build time. The generated table is sorted by name, so it can
+/// be assigned to a constant of type `&[(&str, AtlasRegion)]` and searched
+/// with `find_atlas_region`. The atlas image itself is converted with
+/// `to_rust_code`.
+pub fn atlas_regions_to_rust_code<P: Pixel>(atlas: &TextureAtlas<P>) -> String {
+    atlas_regions_to_token_stream(atlas).to_string()
+}
+
+/// The tokens of the code `atlas_regions_to_rust_code` returns.
+pub fn atlas_regions_to_token_stream<P: Pixel>(atlas: &TextureAtlas<P>) -> TokenStream {
+    atlas_regions_to_token_stream_with(atlas, &CodegenOptions::new())
+}
+
+/// Convert the regions of a texture atlas to a block of Rust code naming
+/// the atlas types through the crate path of the given options.
+pub fn atlas_regions_to_rust_code_with<P: Pixel>(atlas: &TextureAtlas<P>, options: &CodegenOptions) -> String {
+    atlas_regions_to_token_stream_with(atlas, options).to_string()
+}
+
+/// The tokens of the code `atlas_regions_to_rust_code_with` returns.
+pub fn atlas_regions_to_token_stream_with<P: Pixel>(atlas: &TextureAtlas<P>, options: &CodegenOptions) -> TokenStream {
+    generate_atlas_regions_code(atlas, &options.crate_path_tokens())
+}
+
+
+/// The name of the pixel type for a given pixel format.
+fn pixel_type_name(format: PixelFormat) -> &'static str {
+    match format {
+        PixelFormat::R8 => "R8",
+        PixelFormat::Rg8 => "Rg8",
+        PixelFormat::L8 => "L8",
+        PixelFormat::La8 => "La8",
+        PixelFormat::Rgb8 => "Rgb8",
+        PixelFormat::Rgba8 => "Rgba",
+        PixelFormat::R16 => "R16",
+        PixelFormat::Rgba16 => "Rgba16",
+        PixelFormat::RF32 => "RF32",
+        PixelFormat::RgbaF32 => "RgbaF32",
+    }
+}
+
+fn ident(name: &str) -> Ident {
+    Ident::new(name, Span::call_site())
+}
+
+/// The identifier of the pixel type for a given pixel format.
+fn pixel_type_ident(format: PixelFormat) -> Ident {
+    ident(pixel_type_name(format))
+}
+
+/// Floating point literals need a decimal point or an exponent to be typed as
+/// floats, and the special values have no literal syntax at all.
+fn generate_f32(number: f32) -> TokenStream {
+    if number.is_nan() {
+        quote!(f32::NAN)
+    } else if number == f32::INFINITY {
+        quote!(f32::INFINITY)
+    } else if number == f32::NEG_INFINITY {
+        quote!(f32::NEG_INFINITY)
+    } else if number.is_sign_negative() {
+        // A literal token cannot carry a sign, so the negation is a separate token.
+        let literal = Literal::f32_unsuffixed(-number);
+        quote!(-#literal)
+    } else {
+        let literal = Literal::f32_unsuffixed(number);
+        quote!(#literal)
+    }
+}
+
+/// Generate the literal for a single color channel.
+fn generate_channel<C: Channel>(format: PixelFormat, value: C) -> TokenStream {
+    match format.bytes_per_channel() {
+        1 => {
+            let literal = Literal::u8_unsuffixed(u8::from_f32(value.to_f32()));
+            quote!(#literal)
+        }
+        2 => {
+            let literal = Literal::u16_unsuffixed(u16::from_f32(value.to_f32()));
+            quote!(#literal)
+        }
+        _ => generate_f32(value.to_f32()),
+    }
+}
+
+/// Generate the constructor call for a single pixel. Constructor calls given
+/// a crate path name the pixel type by its full path, for use outside of a
+/// block with its own import statements.
+fn generate_pixel<P: Pixel>(pixel: &P, crate_path: Option<&TokenStream>) -> TokenStream {
+    let pixel_type = pixel_type_ident(P::FORMAT);
+    let channels = (0..P::CHANNEL_COUNT).map(|i| generate_channel(P::FORMAT, pixel.channel(i)));
+    if let Some(crate_path) = crate_path {
+        quote!(#crate_path::#pixel_type::new(#(#channels),*))
+    } else {
+        quote!(#pixel_type::new(#(#channels),*))
+    }
+}
+
+/// Generate the Rust code expression block for constructing the texture
+/// image at compile time.
+fn generate_block<P: Pixel>(tex: &TexImage2D<P>, crate_path: &TokenStream) -> TokenStream {
+    let pixel_type = pixel_type_ident(P::FORMAT);
+    let height = Literal::u32_unsuffixed(tex.height);
+    let width = Literal::u32_unsuffixed(tex.width);
+    let pixels = tex.data.iter().map(|pixel| generate_pixel(pixel, None));
+
+    quote! {
+        {
+            use #crate_path::{#pixel_type, TexImage2D};
+
+            let height: u32 = #height;
+            let width: u32 = #width;
+            let data: Vec<#pixel_type> = vec![#(#pixels),*];
+
+            TexImage2D::from_data(width, height, data)
+        }
+    }
+}
+
+/// Generate the Rust code expression block for constructing the texture
+/// image from a static byte array. The byte array is embedded as a single
+/// byte string literal rather than one token per byte, since the pixel data
+/// of a large texture image runs into the millions of bytes, and the compiler
+/// reads a byte string literal far faster than an array expression with one
+/// element per byte.
+fn generate_byte_block<P: Pixel>(tex: &TexImage2D<P>, encoding: DataEncoding, crate_path: &TokenStream) -> TokenStream {
+    let pixel_type = pixel_type_ident(P::FORMAT);
+    let height = Literal::u32_unsuffixed(tex.height);
+    let width = Literal::u32_unsuffixed(tex.width);
+    let bytes = tex.to_bytes();
+    let length = Literal::usize_unsuffixed(bytes.len());
+    let data = match encoding {
+        DataEncoding::IncludeBytes(file_path) => quote!(*include_bytes!(#file_path)),
+        _ => {
+            let literal = Literal::byte_string(&bytes);
+            quote!(*#literal)
+        }
+    };
+
+    // Formats with channels wider than a byte are borrowed in place, so the
+    // byte array has to be aligned for the channel type, and its channels
+    // are converted from little endian to the byte order of the target while
+    // compiling.
+    let align = mem::align_of::<P>();
+    let statics = if align == 1 {
+        quote!(static DATA: [u8; #length] = #data;)
+    } else {
+        let align = Literal::usize_unsuffixed(align);
+        let channel_size = Literal::usize_unsuffixed(P::FORMAT.bytes_per_channel());
+        quote! {
+            #[repr(C, align(#align))]
+            struct Aligned([u8; #length]);
+            static ALIGNED: Aligned = Aligned(#crate_path::to_native_endian(#data, #channel_size));
+            static DATA: &[u8; #length] = &ALIGNED.0;
+        }
+    };
+
+    quote! {
+        {
+            use #crate_path::{#pixel_type, TexImage2DRef};
+
+            let height: u32 = #height;
+            let width: u32 = #width;
+            #statics
+
+            TexImage2DRef::<#pixel_type>::from_native_bytes(width, height, &DATA[..])
+                .expect("the embedded pixel data is aligned for its channels")
+        }
+    }
+}
+
+/// The identifier of the `CompressedFormat` variant for a compressed format.
+/// The variants are named the way their `Debug` form prints them.
+fn compressed_format_ident(format: CompressedFormat) -> Ident {
+    ident(&format!("{:?}", format))
+}
+
+/// Generate the Rust code expression block for constructing a view of a
+/// compressed texture image. The blocks of each mipmap level are embedded as
+/// a byte string literal, for the same reason as in `generate_byte_block`,
+/// and the view borrows them in place.
+fn generate_compressed_block(tex: &CompressedTexImage2D, crate_path: &TokenStream) -> TokenStream {
+    let format = compressed_format_ident(tex.format());
+    let height = Literal::u32_unsuffixed(tex.height());
+    let width = Literal::u32_unsuffixed(tex.width());
+    let names: Vec<Ident> = (0..tex.level_count()).map(|level| ident(&format!("LEVEL_{}", level))).collect();
+    let statics = names.iter().zip(tex.levels()).map(|(name, blocks)| {
+        let length = Literal::usize_unsuffixed(blocks.len());
+        let literal = Literal::byte_string(blocks);
+        quote!(static #name: [u8; #length] = *#literal;)
+    });
+    let level_count = Literal::usize_unsuffixed(names.len());
+
+    quote! {
+        {
+            use #crate_path::{CompressedFormat, CompressedTexImage2DRef};
+
+            #(#statics)*
+            static LEVELS: [&[u8]; #level_count] = [#(&#names),*];
+
+            CompressedTexImage2DRef::new(CompressedFormat::#format, #width, #height, &LEVELS).unwrap()
+        }
+    }
+}
+
+/// Generate a `static` item holding a borrowed view of the texture image.
+/// Every path is fully qualified, since the item is included at module level
+/// where the generated code cannot add import statements of its own.
+fn generate_static_code<P: Pixel>(
+    tex: &TexImage2D<P>,
+    crate_path: &TokenStream,
+    visibility: &TokenStream,
+    name: &Ident) -> TokenStream
+{
+    let pixel_type = pixel_type_ident(P::FORMAT);
+    let width = Literal::u32_unsuffixed(tex.width);
+    let height = Literal::u32_unsuffixed(tex.height);
+    let pixels = tex.data.iter().map(|pixel| generate_pixel(pixel, Some(crate_path)));
+
+    quote! {
+        #visibility static #name: #crate_path::TexImage2DRef<'static, #crate_path::#pixel_type> =
+            #crate_path::TexImage2DRef::new(#width, #height, &[#(#pixels),*]);
+    }
+}
+
+fn generate_lazy_static_code(
+    format: PixelFormat,
+    crate_path: &TokenStream,
+    visibility: &TokenStream,
+    name: &Ident,
+    path: &str) -> TokenStream
+{
+    let pixel_type = pixel_type_ident(format);
+
+    quote! {
+        #visibility static #name: #crate_path::LazyTexture<#crate_path::#pixel_type> =
+            #crate_path::LazyTexture::new(include_bytes!(#path));
+    }
+}
+
+/// A 64 bit FNV-1a hash of the pixel data of a texture image. The hash is
+/// stable across compiler versions and platforms, unlike the hashers in the
+/// standard library, so it can be compared between generated files.
+fn hash_pixel_data<P: Pixel>(tex: &TexImage2D<P>) -> u64 {
+    tex.to_bytes().iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// Generate the doc comment describing where a generated texture image came from.
+fn generate_doc_comment<P: Pixel>(tex: &TexImage2D<P>, options: &CodegenOptions) -> TokenStream {
+    let mut lines = vec![];
+    if let Some(ref source_path) = options.source_path {
+        lines.push(format!(" Generated from `{}`.", source_path.display()));
+        lines.push(String::new());
+    }
+    lines.push(format!(" A {}x{} {} texture image.", tex.width, tex.height, P::FORMAT));
+    lines.push(format!(" Pixel data hash (FNV-1a): `{:016x}`.", hash_pixel_data(tex)));
+
+    quote!(#(#[doc = #lines])*)
 }
 
+/// Generate the code for a texture image shaped by the given options, with
+/// the pixel data embedded in the given encoding.
+fn generate_code_with<P: Pixel>(tex: &TexImage2D<P>, encoding: DataEncoding, options: &CodegenOptions) -> TokenStream {
+    let crate_path = options.crate_path_tokens();
+    let block = match encoding {
+        DataEncoding::PixelLiterals => generate_block(tex, &crate_path),
+        _ => generate_byte_block(tex, encoding, &crate_path),
+    };
+    let name = match options.item_name {
+        Some(ref name) => ident(name),
+        None => return block,
+    };
+    let visibility = options.visibility.to_token_stream();
+    let attributes = options.attributes();
+    let doc_comment = if options.doc_comment {
+        generate_doc_comment(tex, options)
+    } else {
+        TokenStream::new()
+    };
+    let pixel_type = pixel_type_ident(P::FORMAT);
+    let item = match encoding {
+        DataEncoding::PixelLiterals if options.wrap_in_fn => quote! {
+            #visibility fn #name() -> #crate_path::TexImage2D<#crate_path::#pixel_type> #block
+        },
+        DataEncoding::PixelLiterals => generate_static_code(tex, &crate_path, &visibility, &name),
+        _ => quote! {
+            #visibility fn #name() -> #crate_path::TexImage2DRef<'static, #crate_path::#pixel_type> #block
+        },
+    };
+
+    quote! {
+        #doc_comment
+        #attributes
+        #item
+    }
+}
 
 /// Generate the Rust code expression block for constructing the
-/// texture image at compile time.
-fn generate_code(tex: &TexImage2D) -> TexImage2DIR {
-    use Token::*;
-    
-    let mut ir = TexImage2DIR::new(vec![]);
-    let indent = 4;
-    // Start the code block.
-    ir.push(LCurlyBrace);
-    ir.push(Newline);
-
-    // Generate the import statements.
-    generate_imports(&mut ir, indent);
-    ir.push(Newline);
-    ir.push(Newline);
-
-    generate_height_code(&mut ir, tex, indent);
-    ir.push(Newline);
-
-    generate_width_code(&mut ir, tex, indent);
-    ir.push(Newline);
-
-    generate_depth_code(&mut ir, tex, indent);
-    ir.push(Newline);
-
-    // Generate the data set.
-    generate_data_code(&mut ir, tex, indent);
-    ir.push(Newline);
-    ir.push(Newline);
-
-    // Generate the type constructor invocation.
-    generate_type_constructor_invocation(&mut ir, indent);
-    ir.push(Newline);
-
-    // End the code block.    
-    ir.push(RCurlyBrace);
-
-    ir
-}
-
-fn synthesize_token(token: Token) -> String {
-    use Token::*;
-    match token {
-        SymUse => format!("{}", "use"),
-        SymImportTexImage2D => format!("{}", "teximage2d"),
-        SymLet => format!("{}", "let"),
-        SymHeight => format!("{}", "height"),
-        SymWidth => format!("{}", "width"),
-        SymDepth => format!("{}", "depth"),
-        SymData => format!("{}", "data"),
-        SymTypeU32 => format!("{}", "u32"),
-        SymTypeRgba => format!("{}", "Rgba"),
-        SymTypeTexImage2D => format!("{}", "TexImage2D"),
-        SymTypeVec => format!("{}", "Vec"),
-        SymRgbaNew => format!("{}", "new"),
-        SymTexImage2DNew => format!("{}", "from_rgba_data"),
-        SymMacroVec => format!("{}", "vec!"),
-        Equals => format!("{}", "="),
-        Colon => format!("{}", ":"),
-        DoubleColon => format!("{}", "::"),
-        Semicolon => format!("{}", ";"),
-        LBracket => format!("{}", "["),
-        RBracket => format!("{}", "]"),
-        LCurlyBrace => format!("{}", "{"),
-        RCurlyBrace => format!("{}", "}"),
-        GreaterThan => format!("{}", ">"),
-        LessThan => format!("{}", "<"),
-        Comma => format!("{}", ","),
-        LParen => format!("{}", "("),
-        RParen => format!("{}", ")"),
-        U8(number) => format!("{:#02X}", number),
-        U32(number) => format!("{}", number),
-        Newline => format!("{}", "\n"),
-        Whitespace(number) => format!("{:width$}", "", width = number),
-    }
-}
-
-fn synthesize_code(ir: &TexImage2DIR) -> String {
-    let mut fragment = String::new();
-    for token in ir.data.iter() {
-        fragment.push_str(&synthesize_token(*token));
-    }
-
-    fragment
+/// mipmap chain at compile time.
+fn generate_mip_chain_code<P: Pixel>(chain: &MipChain<P>, crate_path: &TokenStream) -> TokenStream {
+    let levels = chain.iter().map(|level| generate_block(level, crate_path));
+
+    quote! {
+        {
+            use #crate_path::MipChain;
+
+            let levels = vec![#(#levels),*];
+
+            MipChain::from_levels(levels)
+        }
+    }
+}
+
+/// Generate the Rust code expression block for constructing a texture type
+/// made of several texture images, such as a cube map or a texture array.
+/// The images are passed to the validating constructor `constructor` of the
+/// type, which takes either a vector or an array of texture images.
+fn generate_image_collection_code<'a, P, I>(
+    type_name: &'static str,
+    constructor: &'static str,
+    is_vec: bool,
+    images: I,
+    crate_path: &TokenStream) -> TokenStream
+where
+    P: Pixel + 'a,
+    I: Iterator<Item = &'a TexImage2D<P>>,
+{
+    let type_name = ident(type_name);
+    let constructor = ident(constructor);
+    let images = images.map(|image| generate_block(image, crate_path));
+    let images = if is_vec {
+        quote!(vec![#(#images),*])
+    } else {
+        quote!([#(#images),*])
+    };
+
+    quote! {
+        {
+            use #crate_path::#type_name;
+
+            #type_name::#constructor(#images).unwrap()
+        }
+    }
+}
+
+/// Generate the struct expression for a single atlas region.
+fn generate_atlas_region(region: &AtlasRegion) -> TokenStream {
+    let x = Literal::u32_unsuffixed(region.x);
+    let y = Literal::u32_unsuffixed(region.y);
+    let width = Literal::u32_unsuffixed(region.width);
+    let height = Literal::u32_unsuffixed(region.height);
+    let rotated = region.rotated;
+    let u_min = generate_f32(region.uv.u_min);
+    let v_min = generate_f32(region.uv.v_min);
+    let u_max = generate_f32(region.uv.u_max);
+    let v_max = generate_f32(region.uv.v_max);
+
+    quote! {
+        AtlasRegion {
+            x: #x, y: #y, width: #width, height: #height, rotated: #rotated,
+            uv: UvRect { u_min: #u_min, v_min: #v_min, u_max: #u_max, v_max: #v_max },
+        }
+    }
+}
+
+/// Generate the Rust code expression block for the lookup table of named
+/// regions in a texture atlas. The regions of an atlas are stored in name
+/// order, so the table is sorted by name.
+fn generate_atlas_regions_code<P: Pixel>(atlas: &TextureAtlas<P>, crate_path: &TokenStream) -> TokenStream {
+    let entries = atlas.regions.iter().map(|(name, region)| {
+        let region = generate_atlas_region(region);
+        quote!((#name, #region))
+    });
+
+    quote! {
+        {
+            use #crate_path::{AtlasRegion, UvRect};
+
+            &[#(#entries),*]
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{CodegenOptions, Visibility};
+    use proc_macro2::Literal;
+    use quote::quote;
+    use std::convert::TryInto;
+    use texture::{
+        AtlasOptions, CompressedFormat, CompressedTexImage2D, Filter, Quality, R16, R8, Rgba, RgbaF32, TexImage2D,
+        TexImage2DArray, TexImageCube, TextureAtlas,
+    };
+
+
+    #[test]
+    fn test_generated_code_uses_texture_pixel_format() {
+        let tex = TexImage2D::from_data(2, 1, vec![R8::new(0x01), R8::new(0xFF)]);
+        let code = super::to_rust_code(&tex);
+
+        assert!(code.contains(&quote!(use teximage2d::{R8, TexImage2D};).to_string()));
+        assert!(code.contains(&quote!(let data: Vec<R8> = vec![R8::new(1), R8::new(255)];).to_string()));
+    }
+
+    #[test]
+    fn test_generated_code_is_the_string_form_of_the_tokens() {
+        let tex = TexImage2D::from_data(1, 1, vec![Rgba::new(1, 2, 3, 4)]);
+        let expected = quote! {
+            {
+                use teximage2d::{Rgba, TexImage2D};
+
+                let height: u32 = 1;
+                let width: u32 = 1;
+                let data: Vec<Rgba> = vec![Rgba::new(1, 2, 3, 4)];
+
+                TexImage2D::from_data(width, height, data)
+            }
+        };
 
-}
\ No newline at end of file
+        assert_eq!(super::to_token_stream(&tex).to_string(), expected.to_string());
+        assert_eq!(super::to_rust_code(&tex), expected.to_string());
+    }
+
+    #[test]
+    fn test_generated_compressed_code_names_the_format() {
+        let format = CompressedFormat::Astc8x6;
+        let levels = vec![vec![7; 16], vec![9; 16]];
+        let tex = CompressedTexImage2D::new(format, 8, 6, levels).unwrap();
+        let code = super::compressed_to_rust_code(&tex);
+        let level = Literal::byte_string(&[9; 16]);
+
+        let view = quote!(CompressedTexImage2DRef::new(CompressedFormat::Astc8x6, 8, 6, &LEVELS).unwrap());
+
+        assert!(code.contains(&quote!(use teximage2d::{CompressedFormat, CompressedTexImage2DRef};).to_string()));
+        assert!(code.contains(&quote!(static LEVEL_1: [u8; 16] = *#level;).to_string()));
+        assert!(code.contains(&quote!(static LEVELS: [&[u8]; 2] = [&LEVEL_0, &LEVEL_1];).to_string()));
+        assert!(code.contains(&view.to_string()));
+        assert!(!code.contains("to_vec"));
+    }
+
+    #[test]
+    fn test_generated_float_literals_are_valid_rust() {
+        let data = vec![RgbaF32::new(1.0, -0.5, f32::INFINITY, f32::NAN)];
+        let tex = TexImage2D::from_data(1, 1, data);
+        let code = super::to_rust_code(&tex);
+
+        assert!(code.contains(&quote!(RgbaF32::new(1.0, -0.5, f32::INFINITY, f32::NAN)).to_string()));
+    }
+
+    #[test]
+    fn test_generated_mip_chain_code_embeds_every_level() {
+        let tex = TexImage2D::from_data(4, 2, vec![R8::new(0x80); 8]);
+        let chain = tex.generate_mipmaps(Filter::Box);
+        let code = super::mip_chain_to_rust_code(&chain);
+
+        assert!(code.contains(&quote!(use teximage2d::MipChain;).to_string()));
+        assert_eq!(code.matches(&quote!(TexImage2D::from_data(width, height, data)).to_string()).count(), 3);
+        assert!(code.contains(&quote!(MipChain::from_levels(levels)).to_string()));
+    }
+
+    #[test]
+    fn test_generated_byte_array_code() {
+        let bytes: Vec<u8> = (0..17).collect();
+        let data = bytes.iter().map(|&i| R8::new(i)).collect();
+        let tex = TexImage2D::from_data(17, 1, data);
+        let code = super::to_rust_code_with_encoding(&tex, super::DataEncoding::ByteArray);
+        let literal = Literal::byte_string(&bytes);
+
+        assert!(code.contains(&quote!(static DATA: [u8; 17] = *#literal;).to_string()));
+        assert!(code.contains("b\"\\0\\x01\\x02"));
+        assert!(code.contains(&quote!(TexImage2DRef::<R8>::from_native_bytes(width, height, &DATA[..])).to_string()));
+    }
+
+    #[test]
+    fn test_generated_byte_array_code_aligns_wide_channels() {
+        let tex = TexImage2D::from_data(1, 1, vec![R16::new(0x0102)]);
+        let code = super::to_rust_code_with_encoding(&tex, super::DataEncoding::ByteArray);
+
+        let literal = Literal::byte_string(&[0x02, 0x01]);
+
+        assert!(code.contains(&quote!(#[repr(C, align(2))]).to_string()));
+        assert!(code.contains(&quote!(Aligned(teximage2d::to_native_endian(*#literal, 2))).to_string()));
+        assert!(code.contains(&quote!(static DATA: &[u8; 2] = &ALIGNED.0;).to_string()));
+    }
+
+    #[test]
+    fn test_generated_include_bytes_code() {
+        let tex = TexImage2D::from_data(1, 1, vec![Rgba::new(1, 2, 3, 4)]);
+        let code = super::to_rust_code_with_encoding(&tex, super::DataEncoding::IncludeBytes("sample.raw"));
+
+        assert!(code.contains(&quote!(static DATA: [u8; 4] = *include_bytes!("sample.raw");).to_string()));
+        assert_eq!(super::to_raw_bytes(&tex), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_generated_static_code() {
+        let tex = TexImage2D::from_data(2, 1, vec![R8::new(0x01), R8::new(0xFF)]);
+        let expected = quote! {
+            pub static FONT: teximage2d::TexImage2DRef<'static, teximage2d::R8> =
+                teximage2d::TexImage2DRef::new(2, 1, &[teximage2d::R8::new(1), teximage2d::R8::new(255)]);
+        };
+
+        assert_eq!(super::to_static_rust_code(&tex, "FONT"), expected.to_string());
+    }
+
+    #[test]
+    fn test_generated_lazy_static_code() {
+        let expected = quote! {
+            pub static STONE_WALL: teximage2d::LazyTexture<teximage2d::Rgba> =
+                teximage2d::LazyTexture::new(include_bytes!("stone_wall.png"));
+        };
+        let code = super::to_lazy_static_rust_code(texture::PixelFormat::Rgba8, "STONE_WALL", "stone_wall.png");
+
+        assert_eq!(code, expected.to_string());
+    }
+
+    #[test]
+    fn test_png_bytes_decode_to_the_same_texture_image() {
+        let tex = TexImage2D::from_data(2, 1, vec![Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)]);
+        let bytes = super::to_png_bytes(&tex);
+        let result = texture::load_from_memory(&bytes).unwrap();
+
+        assert_eq!(result.image.to_rgba8(), tex);
+    }
+
+    #[test]
+    fn test_generated_cube_code_embeds_every_face() {
+        let faces: Vec<TexImage2D<R8>> = (0..6).map(|i| TexImage2D::from_data(1, 1, vec![R8::new(i)])).collect();
+        let cube = TexImageCube::from_faces(faces.try_into().unwrap()).unwrap();
+        let code = super::cube_to_rust_code(&cube);
+
+        assert!(code.contains(&quote!(use teximage2d::TexImageCube;).to_string()));
+        assert!(code.contains(&quote!(TexImageCube::from_faces).to_string()));
+        assert_eq!(code.matches(&quote!(TexImage2D::from_data(width, height, data)).to_string()).count(), 6);
+        assert!(code.contains(&quote!(R8::new(5)).to_string()));
+    }
+
+    #[test]
+    fn test_generated_array_code_embeds_every_layer() {
+        let layers = vec![TexImage2D::<R8>::new(2, 1), TexImage2D::new(2, 1)];
+        let array = TexImage2DArray::from_layers(layers).unwrap();
+        let code = super::array_to_rust_code(&array);
+
+        assert!(code.contains(&quote!(use teximage2d::TexImage2DArray;).to_string()));
+        assert!(code.contains(&quote!(TexImage2DArray::from_layers).to_string()));
+        assert_eq!(code.matches(&quote!(TexImage2D::from_data(width, height, data)).to_string()).count(), 2);
+    }
+
+    #[test]
+    fn test_generated_atlas_regions_are_sorted_by_name() {
+        let small = TexImage2D::from_data(1, 1, vec![Rgba::new(0, 0, 0, 255)]);
+        let large = TexImage2D::from_data(2, 2, vec![Rgba::new(255, 255, 255, 255); 4]);
+        let sprites = vec![("zebra", &large), ("ant \"quoted\"", &small)];
+        let atlas = TextureAtlas::pack(sprites, &AtlasOptions::new()).unwrap();
+        let code = super::atlas_regions_to_rust_code(&atlas);
+        let zebra_entry = quote! {
+            ("zebra", AtlasRegion {
+                x: 0, y: 0, width: 2, height: 2, rotated: false,
+                uv: UvRect { u_min: 0.0, v_min: 0.0, u_max: 0.5, v_max: 1.0 },
+            })
+        };
+        let ant = code.find(&quote!("ant \"quoted\"").to_string()).unwrap();
+        let zebra = code.find(&zebra_entry.to_string()).unwrap();
+
+        assert!(code.contains(&quote!(use teximage2d::{AtlasRegion, UvRect};).to_string()));
+        assert!(ant < zebra);
+    }
+
+    #[test]
+    fn test_default_codegen_options_generate_an_expression_block() {
+        let tex = TexImage2D::from_data(2, 1, vec![R8::new(0x01), R8::new(0xFF)]);
+
+        assert_eq!(super::to_rust_code_with(&tex, &CodegenOptions::new()), super::to_rust_code(&tex));
+    }
+
+    #[test]
+    fn test_codegen_options_generate_a_function_item() {
+        let tex = TexImage2D::from_data(1, 1, vec![R8::new(0x01)]);
+        let options = CodegenOptions::new()
+            .crate_path("::texture")
+            .item_name("font")
+            .visibility(Visibility::Crate)
+            .rustfmt_skip(true)
+            .allow("clippy::all")
+            .allow("dead_code")
+            .doc_comment(true)
+            .source_path("assets/font.png");
+        let code = super::to_rust_code_with(&tex, &options);
+        let expected = quote! {
+            #[doc = " Generated from `assets/font.png`."]
+            #[doc = ""]
+            #[doc = " A 1x1 R8 texture image."]
+            #[doc = " Pixel data hash (FNV-1a): `af63bc4c8601b62c`."]
+            #[rustfmt::skip]
+            #[allow(clippy::all, dead_code)]
+            pub(crate) fn font() -> ::texture::TexImage2D<::texture::R8> {
+                use ::texture::{R8, TexImage2D};
+
+                let height: u32 = 1;
+                let width: u32 = 1;
+                let data: Vec<R8> = vec![R8::new(1)];
+
+                TexImage2D::from_data(width, height, data)
+            }
+        };
+
+        assert_eq!(code, expected.to_string());
+    }
+
+    #[test]
+    fn test_codegen_options_generate_a_static_item() {
+        let tex = TexImage2D::from_data(1, 1, vec![R8::new(0x01)]);
+        let options = CodegenOptions::new()
+            .crate_path("crate::gfx")
+            .item_name("FONT")
+            .visibility(Visibility::Private)
+            .wrap_in_fn(false);
+        let expected = quote! {
+            static FONT: crate::gfx::TexImage2DRef<'static, crate::gfx::R8> =
+                crate::gfx::TexImage2DRef::new(1, 1, &[crate::gfx::R8::new(1)]);
+        };
+
+        assert_eq!(super::to_rust_code_with(&tex, &options), expected.to_string());
+    }
+
+    #[test]
+    fn test_codegen_options_generate_a_byte_array_function_item() {
+        let tex = TexImage2D::from_data(1, 1, vec![R8::new(0x01)]);
+        let options = CodegenOptions::new().crate_path("::texture").item_name("font").wrap_in_fn(false);
+        let code = super::to_rust_code_with_encoding_and_options(&tex, super::DataEncoding::ByteArray, &options);
+
+        let signature = quote!(pub fn font() -> ::texture::TexImage2DRef<'static, ::texture::R8>);
+
+        assert!(code.starts_with(&signature.to_string()));
+        assert!(code.contains(&quote!(use ::texture::{R8, TexImage2DRef};).to_string()));
+    }
+
+    #[test]
+    fn test_every_generator_honors_the_crate_path() {
+        let options = CodegenOptions::new().crate_path("crate::gfx");
+        let tex = TexImage2D::from_data(1, 1, vec![Rgba::new(1, 2, 3, 4)]);
+        let chain = tex.generate_mipmaps(Filter::Box);
+        let cube = TexImageCube::from_faces([
+            tex.clone(), tex.clone(), tex.clone(), tex.clone(), tex.clone(), tex.clone(),
+        ]).unwrap();
+        let array = TexImage2DArray::from_layers(vec![tex.clone()]).unwrap();
+        let atlas = TextureAtlas::pack(vec![("pixel", &tex)], &AtlasOptions::new()).unwrap();
+        let compressed = CompressedTexImage2D::from_image(&tex, CompressedFormat::Bc1, Quality::Fast);
+        let codes = vec![
+            super::to_rust_code_with(&tex, &options),
+            super::to_rust_code_with_encoding_and_options(&tex, super::DataEncoding::ByteArray, &options),
+            super::to_static_rust_code_with(&tex, "PIXEL", &options),
+            super::to_lazy_static_rust_code_with(texture::PixelFormat::Rgba8, "PIXEL", "pixel.png", &options),
+            super::mip_chain_to_rust_code_with(&chain, &options),
+            super::compressed_to_rust_code_with(&compressed, &options),
+            super::cube_to_rust_code_with(&cube, &options),
+            super::array_to_rust_code_with(&array, &options),
+            super::atlas_regions_to_rust_code_with(&atlas, &options),
+        ];
+        let crate_path = quote!(crate::gfx).to_string();
+        for code in codes.iter() {
+            assert!(code.contains(&crate_path), "{}", code);
+            assert!(!code.contains("teximage2d"), "{}", code);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_codegen_options_reject_invalid_item_names() {
+        let _ = CodegenOptions::new().item_name("stone-wall");
+    }
+}
@@ -0,0 +1,165 @@
+use crate::TokenStream;
+use quote::quote;
+use std::path::{Path, PathBuf};
+
+
+/// The visibility of an item in generated code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// The item is private to the module it is included into.
+    Private,
+    /// The item is visible in the crate it is included into, i.e. `pub(crate)`.
+    Crate,
+    /// The item is public, i.e. `pub`.
+    Public,
+}
+
+impl Visibility {
+    pub(crate) fn to_token_stream(self) -> TokenStream {
+        match self {
+            Visibility::Private => quote!(),
+            Visibility::Crate => quote!(pub(crate)),
+            Visibility::Public => quote!(pub),
+        }
+    }
+}
+
+/// The options controlling the shape of the code `to_rust_code_with`
+/// generates. The default options generate the same expression block as
+/// `to_rust_code`. Naming an item generates a function returning the
+/// texture image instead, or a `static` view of the texture image when
+/// function wrapping is disabled. The visibility, attributes, and doc
+/// comment apply to the generated item, so they are ignored when no item is
+/// named.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodegenOptions {
+    pub(crate) crate_path: String,
+    pub(crate) item_name: Option<String>,
+    pub(crate) visibility: Visibility,
+    pub(crate) wrap_in_fn: bool,
+    pub(crate) rustfmt_skip: bool,
+    pub(crate) allowed_lints: Vec<String>,
+    pub(crate) doc_comment: bool,
+    pub(crate) source_path: Option<PathBuf>,
+}
+
+impl CodegenOptions {
+    pub fn new() -> CodegenOptions {
+        CodegenOptions {
+            crate_path: String::from("teximage2d"),
+            item_name: None,
+            visibility: Visibility::Public,
+            wrap_in_fn: true,
+            rustfmt_skip: false,
+            allowed_lints: vec![],
+            doc_comment: false,
+            source_path: None,
+        }
+    }
+
+    /// The path the generated code names the texture types by, such as
+    /// `texture`, `::teximage2d`, or `crate::gfx`. Defaults to `teximage2d`.
+    ///
+    /// # Panics
+    /// Panics if the path is not a valid Rust path.
+    pub fn crate_path(mut self, crate_path: &str) -> CodegenOptions {
+        assert!(
+            crate_path.parse::<TokenStream>().map(|tokens| !tokens.is_empty()).unwrap_or(false),
+            "The crate path must be a valid Rust path, got `{}`.", crate_path
+        );
+        self.crate_path = String::from(crate_path);
+        self
+    }
+
+    /// Generate an item with the given name instead of an expression block.
+    ///
+    /// # Panics
+    /// Panics if the name is not a valid Rust identifier.
+    pub fn item_name(mut self, item_name: &str) -> CodegenOptions {
+        assert!(is_identifier(item_name), "The item name must be a valid Rust identifier, got `{}`.", item_name);
+        self.item_name = Some(String::from(item_name));
+        self
+    }
+
+    /// The visibility of the generated item. Defaults to `pub`.
+    pub fn visibility(mut self, visibility: Visibility) -> CodegenOptions {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Wrap the generated item in a function returning an owned texture
+    /// image. Otherwise the item is a `static` borrowed view of the texture
+    /// image. Defaults to wrapping the item in a function.
+    pub fn wrap_in_fn(mut self, wrap: bool) -> CodegenOptions {
+        self.wrap_in_fn = wrap;
+        self
+    }
+
+    /// Mark the generated item with `#[rustfmt::skip]`, so formatting the
+    /// including crate does not spread the pixel data over many lines.
+    pub fn rustfmt_skip(mut self, skip: bool) -> CodegenOptions {
+        self.rustfmt_skip = skip;
+        self
+    }
+
+    /// Allow a lint on the generated item, such as `clippy::unreadable_literal`.
+    ///
+    /// # Panics
+    /// Panics if the lint name is not a valid Rust path.
+    pub fn allow(mut self, lint: &str) -> CodegenOptions {
+        assert!(lint.parse::<TokenStream>().is_ok(), "The lint name must be a valid Rust path, got `{}`.", lint);
+        self.allowed_lints.push(String::from(lint));
+        self
+    }
+
+    /// Document the generated item with the source path, the dimensions
+    /// and pixel format, and a hash of the pixel data, so a stale generated
+    /// file can be spotted by comparing hashes.
+    pub fn doc_comment(mut self, doc_comment: bool) -> CodegenOptions {
+        self.doc_comment = doc_comment;
+        self
+    }
+
+    /// The path of the image file the texture image was loaded from, quoted
+    /// in the doc comment of the generated item.
+    pub fn source_path<P: AsRef<Path>>(mut self, source_path: P) -> CodegenOptions {
+        self.source_path = Some(source_path.as_ref().to_path_buf());
+        self
+    }
+
+    pub(crate) fn crate_path_tokens(&self) -> TokenStream {
+        // The path is checked when it is set.
+        self.crate_path.parse().unwrap()
+    }
+
+    /// The attributes placed on the generated item.
+    pub(crate) fn attributes(&self) -> TokenStream {
+        let mut attributes = TokenStream::new();
+        if self.rustfmt_skip {
+            attributes.extend(quote!(#[rustfmt::skip]));
+        }
+        if !self.allowed_lints.is_empty() {
+            // The lint names are checked when they are added.
+            let lints = self.allowed_lints.iter().map(|lint| lint.parse::<TokenStream>().unwrap());
+            attributes.extend(quote!(#[allow(#(#lints),*)]));
+        }
+
+        attributes
+    }
+}
+
+impl Default for CodegenOptions {
+    fn default() -> CodegenOptions {
+        CodegenOptions::new()
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_well = match chars.next() {
+        Some(c) => c == '_' || c.is_ascii_alphabetic(),
+        None => false,
+    };
+
+    starts_well && chars.all(|c| c == '_' || c.is_ascii_alphanumeric()) && name != "_"
+}
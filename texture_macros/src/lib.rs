@@ -0,0 +1,190 @@
+extern crate proc_macro;
+extern crate texture;
+extern crate texture_gen;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::env;
+use std::path::{Path, PathBuf};
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Lit, LitStr, Token};
+use texture::{LoadOptions, PixelFormat};
+use texture_gen::{CodegenOptions, DataEncoding};
+
+
+/// Embed a texture image into a program at compile time.
+///
+/// The image file is decoded while the program is compiled, and the macro
+/// expands to a `TexImage2DRef<'static, P>` borrowing the pixel data of the
+/// same `TexImage2D` that loading the file at run time would produce, so the
+/// texture image is neither copied nor parsed at run time. Call
+/// `to_tex_image` on it for an owned texture image. Relative paths are resolved against
+/// the directory of the `Cargo.toml` file of the crate being compiled, and
+/// the program is rebuilt whenever the image file changes.
+///
+/// The macro accepts these options after the path:
+///
+/// * `flip = false` leaves the rows in the order the image file stores them
+///   in instead of flipping the image vertically.
+/// * `format = "rgb8"` sets the pixel format of the texture image. The
+///   format names are the names `PixelFormat::from_name` accepts, and the
+///   default format is `rgba8`.
+///
+/// ```ignore
+/// let texture = teximage2d::include_texture!("assets/sample.png", flip = false, format = "rgb8");
+/// ```
+#[proc_macro]
+pub fn include_texture(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+    match expand(input.into(), &manifest_dir) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// A single `name = value` option passed to the macro.
+struct MacroOption {
+    name: Ident,
+    value: Lit,
+}
+
+/// The arguments to the `include_texture!` macro.
+struct IncludeTexture {
+    path: LitStr,
+    options: Vec<MacroOption>,
+}
+
+impl Parse for IncludeTexture {
+    fn parse(input: ParseStream) -> syn::Result<IncludeTexture> {
+        let path: LitStr = input.parse()?;
+        let mut options = vec![];
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Lit = input.parse()?;
+            options.push(MacroOption { name: name, value: value });
+        }
+
+        Ok(IncludeTexture {
+            path: path,
+            options: options,
+        })
+    }
+}
+
+/// The settings the macro options select.
+struct Settings {
+    load_options: LoadOptions,
+    format: PixelFormat,
+}
+
+fn parse_settings(options: &[MacroOption]) -> syn::Result<Settings> {
+    let mut settings = Settings {
+        load_options: LoadOptions::new(),
+        format: PixelFormat::Rgba8,
+    };
+    for option in options.iter() {
+        match (option.name.to_string().as_str(), &option.value) {
+            ("flip", Lit::Bool(flip)) => {
+                settings.load_options = settings.load_options.flip_vertically(flip.value);
+            }
+            ("flip", value) => {
+                return Err(syn::Error::new(value.span(), "expected `true` or `false`"));
+            }
+            ("format", Lit::Str(name)) => {
+                settings.format = PixelFormat::from_name(&name.value()).ok_or_else(|| {
+                    syn::Error::new(name.span(), format!("unknown pixel format `{}`", name.value()))
+                })?;
+            }
+            ("format", value) => {
+                return Err(syn::Error::new(value.span(), "expected a pixel format name such as \"rgba8\""));
+            }
+            (name, _) => {
+                return Err(syn::Error::new(
+                    option.name.span(),
+                    format!("unknown option `{}`, expected `flip` or `format`", name),
+                ));
+            }
+        }
+    }
+
+    Ok(settings)
+}
+
+fn expand(input: TokenStream, manifest_dir: &Path) -> syn::Result<TokenStream> {
+    let args: IncludeTexture = syn::parse2(input)?;
+    let settings = parse_settings(&args.options)?;
+    let file_path = manifest_dir.join(args.path.value());
+    let result = texture::load_file_with(&file_path, &settings.load_options)
+        .map_err(|error| syn::Error::new(args.path.span(), format!("{}", error)))?;
+
+    // The pixel data is embedded as a byte string, which compiles far faster
+    // than one constructor call per pixel and yields the same texture image.
+    let block = texture_gen::dynamic_to_token_stream(
+        &result.image, settings.format, DataEncoding::ByteArray, &CodegenOptions::new()
+    );
+    // Including the image file as bytes makes the compiler track it, so the
+    // program is rebuilt when the image file changes. The bytes themselves
+    // are never used.
+    let tracked_path = file_path.to_str().ok_or_else(|| {
+        syn::Error::new(args.path.span(), "the path of the image file is not valid unicode")
+    })?;
+
+    Ok(quote! {
+        {
+            const _: &[u8] = include_bytes!(#tracked_path);
+            #block
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn expand_error(input: TokenStream) -> String {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        match expand(input, &manifest_dir) {
+            Ok(_) => panic!("Expected the macro expansion to fail."),
+            Err(error) => format!("{}", error),
+        }
+    }
+
+    #[test]
+    fn test_expansion_tracks_the_image_file() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let tokens = expand(quote! { "assets/sample.png", format = "r8" }, &manifest_dir).unwrap();
+        let code: String = tokens.to_string().split_whitespace().collect();
+
+        assert!(code.contains("include_bytes!"));
+        assert!(code.contains("sample.png"));
+        assert!(code.contains("TexImage2DRef::<R8>::from_native_bytes"));
+    }
+
+    #[test]
+    fn test_unknown_option_is_reported() {
+        let error = expand_error(quote! { "assets/sample.png", mipmaps = true });
+
+        assert!(error.contains("unknown option `mipmaps`"));
+    }
+
+    #[test]
+    fn test_unknown_format_is_reported() {
+        let error = expand_error(quote! { "assets/sample.png", format = "bgra8" });
+
+        assert!(error.contains("unknown pixel format `bgra8`"));
+    }
+
+    #[test]
+    fn test_missing_file_is_reported_with_its_path() {
+        let error = expand_error(quote! { "assets/does_not_exist.png" });
+
+        assert!(error.contains("does_not_exist.png"));
+        assert!(error.contains("Could not read image file"));
+    }
+}